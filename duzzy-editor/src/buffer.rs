@@ -1,12 +1,31 @@
+use std::{
+    cell::OnceCell,
+    ops::{Range, RangeInclusive},
+};
+
 use ropey::Rope;
 
-#[derive(Debug, Default)]
+use crate::{
+    grapheme::{is_grapheme_boundary, next_grapheme_boundary, prev_grapheme_boundary, Graphemes},
+    sql,
+    transaction::Transaction,
+};
+
+/// Text with a cursor at line `index` and char `offset` within that line.
+#[derive(Debug, Default, Clone)]
 pub struct Buffer {
     text: Rope,
     index: usize,
     offset: usize,
     vscroll: usize,
+    hscroll: usize,
     mode: CursorMode,
+    selection: Option<Selection>,
+    /// First and last line of the last selection, addressed by `'<` and `'>`.
+    last_selected: Option<(usize, usize)>,
+    block_insert: Option<BlockInsert>,
+    /// Literals and comments of the text, found again after it's changed.
+    literals: OnceCell<Vec<Range<usize>>>,
 }
 
 impl Buffer {
@@ -14,27 +33,56 @@ impl Buffer {
         self.mode
     }
 
-    pub fn set_mode(&mut self, mode: CursorMode) {
+    pub const fn set_mode(&mut self, mode: CursorMode) {
         self.mode = mode;
     }
 
+    /// Mode name shown to the user, telling visual selection kinds apart.
+    pub const fn mode_name(&self) -> &'static str {
+        match (self.mode, self.selection) {
+            (
+                CursorMode::Visual,
+                Some(Selection {
+                    kind: SelectionKind::Line,
+                    ..
+                }),
+            ) => "V-LINE",
+            (
+                CursorMode::Visual,
+                Some(Selection {
+                    kind: SelectionKind::Block,
+                    ..
+                }),
+            ) => "V-BLOCK",
+            (mode, _) => mode.name(),
+        }
+    }
+
     pub const fn text(&self) -> &Rope {
         &self.text
     }
 
     pub fn text_mut(&mut self) -> &mut Rope {
+        self.literals.take();
         &mut self.text
     }
 
     pub fn set_text(&mut self, text: Rope) {
+        self.literals.take();
         self.text = text;
     }
 
+    /// Char ranges of the string literals, quoted identifiers and comments, see [`sql::literals`].
+    pub fn literals(&self) -> &[Range<usize>] {
+        self.literals
+            .get_or_init(|| sql::literals(self.text.slice(..)))
+    }
+
     pub const fn index(&self) -> usize {
         self.index
     }
 
-    pub fn set_index(&mut self, index: usize) {
+    pub const fn set_index(&mut self, index: usize) {
         self.index = index;
     }
 
@@ -42,7 +90,7 @@ impl Buffer {
         self.offset
     }
 
-    pub fn set_offset(&mut self, offset: usize) {
+    pub const fn set_offset(&mut self, offset: usize) {
         self.offset = offset;
     }
 
@@ -50,27 +98,219 @@ impl Buffer {
         (self.index, self.offset)
     }
 
-    pub fn set_pos(&mut self, pos: (usize, usize)) {
+    pub const fn set_pos(&mut self, pos: (usize, usize)) {
         self.index = pos.0;
         self.offset = pos.1;
     }
 
-    pub fn as_byte_pos(&self) -> usize {
-        self.offset + self.text.line_to_byte(self.index)
+    /// Cursor, scroll and selection, kept by a view while another one has the focus.
+    pub fn view_state(&self) -> ViewState {
+        let line_char = |index: usize| self.text.line_to_char(index.min(self.len_lines() - 1));
+
+        ViewState {
+            cursor: self.as_char_pos().min(self.len_chars()),
+            vscroll: line_char(self.vscroll),
+            hscroll: self.hscroll,
+            selection: self.selection,
+            last_selected: self
+                .last_selected
+                .map(|(first, last)| (line_char(first), line_char(last))),
+        }
+    }
+
+    /// Takes the state of a view back, kept within the text in case
+    /// the changes it followed left it out.
+    pub fn set_view_state(&mut self, state: ViewState) {
+        let len = self.len_chars();
+        let line = |pos: usize| self.text.char_to_line(pos.min(len));
+
+        let cursor = state.cursor.min(len);
+        let index = line(cursor);
+        let offset = cursor - self.text.line_to_char(index);
+
+        self.index = index;
+        self.offset = self.clamp_offset(index, offset);
+        self.vscroll = line(state.vscroll).min(index);
+        self.hscroll = state.hscroll;
+        self.selection = state.selection.map(|selection| Selection {
+            anchor: selection.anchor.min(len),
+            ..selection
+        });
+        self.last_selected = state
+            .last_selected
+            .map(|(first, last)| (line(first), line(last)));
+    }
+
+    /// Char index of the cursor in the whole text.
+    pub fn as_char_pos(&self) -> usize {
+        self.offset + self.text.line_to_char(self.index)
     }
 
+    /// Cursor position of char index `pos`.
     pub fn as_curs_pos(&self, pos: usize) -> (usize, usize) {
-        let index = self.text.byte_to_line(pos);
-        let start = self.text.line_to_byte(index);
+        let index = self.text.char_to_line(pos);
+        let start = self.text.line_to_char(index);
         let offset = pos - start;
         (index, offset)
     }
 
+    /// Char index of the grapheme following the one at char index `pos`.
+    pub fn next_grapheme(&self, pos: usize) -> usize {
+        next_grapheme_boundary(self.text.slice(..), pos)
+    }
+
+    /// Char index of the grapheme preceding char index `pos`.
+    pub fn prev_grapheme(&self, pos: usize) -> usize {
+        prev_grapheme_boundary(self.text.slice(..), pos)
+    }
+
+    /// Offset of the last grapheme in line `index`, the line break included.
+    pub fn line_last(&self, index: usize) -> usize {
+        let start = self.text.line_to_char(index);
+        let end = start + self.text.line(index).len_chars();
+
+        self.prev_grapheme(end).max(start) - start
+    }
+
+    /// Moves `offset` into line `index`, onto the start of the grapheme it points into.
+    pub fn clamp_offset(&self, index: usize, offset: usize) -> usize {
+        let start = self.text.line_to_char(index);
+        let pos = start + offset.min(self.line_last(index));
+
+        if is_grapheme_boundary(self.text.slice(..), pos) {
+            pos - start
+        } else {
+            self.prev_grapheme(pos) - start
+        }
+    }
+
+    /// Offset of the first non-blank char in line `index`.
+    pub fn first_non_blank(&self, index: usize) -> usize {
+        self.text
+            .line(index)
+            .chars()
+            .take(self.line_len(index))
+            .take_while(|ch| *ch == ' ' || *ch == '\t')
+            .count()
+    }
+
+    pub const fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    /// Starts a selection of `kind` anchored at the cursor.
+    pub fn select(&mut self, kind: SelectionKind) {
+        self.selection = Some(Selection {
+            anchor: self.as_char_pos(),
+            kind,
+        });
+    }
+
+    pub const fn set_selection_kind(&mut self, kind: SelectionKind) {
+        if let Some(selection) = self.selection.as_mut() {
+            selection.kind = kind;
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        if let Some(selection) = self.selection.take() {
+            // the anchor may be past the end when the selected text was just deleted
+            let anchor = self
+                .text
+                .char_to_line(selection.anchor.min(self.len_chars()));
+            self.last_selected = Some((anchor.min(self.index), anchor.max(self.index)));
+        }
+    }
+
+    pub const fn last_selected(&self) -> Option<(usize, usize)> {
+        self.last_selected
+    }
+
+    /// Selected char range, the grapheme under the cursor and line breaks of whole lines included.
+    /// Blocks are not a contiguous range, see [`Self::block_cols`].
+    pub fn selection_range(&self) -> Option<Range<usize>> {
+        let selection = self.selection.filter(|s| s.kind != SelectionKind::Block)?;
+        let pos = self.as_char_pos();
+        let (start, end) = (selection.anchor.min(pos), selection.anchor.max(pos));
+
+        let range = match selection.kind {
+            SelectionKind::Char if end < self.len_chars() => start..self.next_grapheme(end),
+            SelectionKind::Char => start..end,
+            SelectionKind::Line => {
+                let last = self.text.char_to_line(end);
+                let start = self.line_char(self.text.char_to_line(start));
+                start..self.line_char(last) + self.text.line(last).len_chars()
+            }
+            SelectionKind::Block => unreachable!("filtered out above"),
+        };
+
+        Some(range)
+    }
+
+    /// Display columns of a block selection, the grapheme under the anchor and the cursor included.
+    pub fn block_cols(&self, tab_width: usize) -> Option<Range<usize>> {
+        let selection = self.selection.filter(|s| s.kind == SelectionKind::Block)?;
+
+        let cols = |(index, offset): (usize, usize)| {
+            let col = self.col_of(index, offset, tab_width);
+            let width = self
+                .graphemes(index, (offset, col), tab_width)
+                .next()
+                .map_or(1, |g| g.width.max(1));
+
+            col..col + width
+        };
+
+        let (anchor, cursor) = (cols(self.as_curs_pos(selection.anchor)), cols(self.pos()));
+        Some(anchor.start.min(cursor.start)..anchor.end.max(cursor.end))
+    }
+
+    /// Offsets of the graphemes in line `index` overlapping display columns `cols`.
+    pub fn block_offsets(
+        &self,
+        index: usize,
+        cols: &Range<usize>,
+        tab_width: usize,
+    ) -> Range<usize> {
+        let len = self.line_len(index);
+        let mut graphemes = self.graphemes(index, (0, 0), tab_width);
+
+        let start = graphemes
+            .find(|g| g.col + g.width > cols.start)
+            .map_or(len, |g| g.offset);
+        let end = self
+            .graphemes(index, (0, 0), tab_width)
+            .find(|g| g.col >= cols.end)
+            .map_or(len, |g| g.offset);
+
+        start..end.max(start)
+    }
+
+    pub const fn set_block_insert(&mut self, block_insert: BlockInsert) {
+        self.block_insert = Some(block_insert);
+    }
+
+    pub const fn take_block_insert(&mut self) -> Option<BlockInsert> {
+        self.block_insert.take()
+    }
+
+    /// Lines touched by the selection.
+    pub fn selected_lines(&self) -> Option<RangeInclusive<usize>> {
+        let selection = self.selection?;
+        let pos = self.as_char_pos();
+
+        let first = self.text.char_to_line(selection.anchor.min(pos));
+        let last = self.text.char_to_line(selection.anchor.max(pos));
+
+        Some(first..=last)
+    }
+
     pub const fn vscroll(&self) -> usize {
         self.vscroll
     }
 
-    pub fn update_vscroll(&mut self, max: usize) {
+    pub const fn update_vscroll(&mut self, max: usize) {
+        let max = if max == 0 { 1 } else { max };
         let upper_bound = self.vscroll + max - 1;
 
         if self.index < self.vscroll {
@@ -80,12 +320,136 @@ impl Buffer {
         }
     }
 
-    pub fn line_byte(&self, index: usize) -> usize {
-        self.text.line_to_byte(index)
+    /// Keeps the cursor visible when lines are wrapped at `width` columns.
+    pub fn update_wrapped_vscroll(&mut self, max: usize, width: usize, tab_width: usize) {
+        if self.index < self.vscroll {
+            self.vscroll = self.index;
+            return;
+        }
+
+        let (row, _) = self.wrapped_pos(width, tab_width);
+        let mut used = row + 1;
+        let mut top = self.index;
+
+        while top > self.vscroll {
+            let rows = self.line_rows(top - 1, width, tab_width);
+            if used + rows > max {
+                break;
+            }
+
+            used += rows;
+            top -= 1;
+        }
+
+        self.vscroll = top;
+    }
+
+    pub const fn hscroll(&self) -> usize {
+        self.hscroll
+    }
+
+    /// Keeps the cursor column visible in `max` columns, wide graphemes included.
+    pub fn update_hscroll(&mut self, max: usize, tab_width: usize) {
+        let max = max.max(1);
+        let start = self.col_of(self.index, self.offset, tab_width);
+        let end = self
+            .graphemes(self.index, (self.offset, start), tab_width)
+            .next()
+            .map_or(start + 1, |g| start + g.width.clamp(1, max));
+
+        if start < self.hscroll {
+            self.hscroll = start;
+        } else if end > self.hscroll + max {
+            self.hscroll = end - max;
+        }
+    }
+
+    pub fn line_char(&self, index: usize) -> usize {
+        self.text.line_to_char(index)
+    }
+
+    /// Number of chars in line `index` without the line break.
+    pub fn line_len(&self, index: usize) -> usize {
+        let line = self.text.line(index);
+        let mut len = line.len_chars();
+
+        if len > 0 && line.char(len - 1) == '\n' {
+            len -= 1;
+            if len > 0 && line.char(len - 1) == '\r' {
+                len -= 1;
+            }
+        }
+
+        len
+    }
+
+    /// Graphemes of line `index` without the line break, starting at `(offset, col)`.
+    pub fn graphemes(
+        &self,
+        index: usize,
+        start: (usize, usize),
+        tab_width: usize,
+    ) -> Graphemes<'_> {
+        let line = self.text.line(index).slice(..self.line_len(index));
+        Graphemes::new(line, start, tab_width)
+    }
+
+    /// Display column of char `offset` in line `index`.
+    pub fn col_of(&self, index: usize, offset: usize, tab_width: usize) -> usize {
+        let mut col = 0;
+
+        for g in self.graphemes(index, (0, 0), tab_width) {
+            if g.offset >= offset {
+                return g.col;
+            }
+            col = g.col + g.width;
+        }
+
+        col
+    }
+
+    /// Offset of the grapheme covering display column `col` in line `index`,
+    /// the line length when the line is shorter.
+    pub fn offset_at_col(&self, index: usize, col: usize, tab_width: usize) -> usize {
+        self.graphemes(index, (0, 0), tab_width)
+            .find(|g| g.col + g.width > col)
+            .map_or_else(|| self.line_len(index), |g| g.offset)
+    }
+
+    /// Offset and display column of each screen row of line `index` wrapped at `width` columns.
+    pub fn row_starts(&self, index: usize, width: usize, tab_width: usize) -> Vec<(usize, usize)> {
+        let width = width.max(1);
+        let mut rows = vec![(0, 0)];
+
+        for g in self.graphemes(index, (0, 0), tab_width) {
+            let (_, row_col) = rows[rows.len() - 1];
+            if g.col > row_col && g.col + g.width > row_col + width {
+                rows.push((g.offset, g.col));
+            }
+        }
+
+        rows
     }
 
-    pub fn len_bytes(&self, index: usize) -> usize {
-        self.text.line(index).len_bytes()
+    /// Screen rows taken by line `index` when wrapped at `width` columns.
+    pub fn line_rows(&self, index: usize, width: usize, tab_width: usize) -> usize {
+        self.row_starts(index, width, tab_width).len()
+    }
+
+    /// Cursor row within its line and column within that row when wrapped at `width` columns.
+    /// The insert cursor at the end of a row taking the whole width goes on the row below.
+    pub fn wrapped_pos(&self, width: usize, tab_width: usize) -> (usize, usize) {
+        let rows = self.row_starts(self.index, width, tab_width);
+        let row = rows
+            .iter()
+            .rposition(|&(offset, _)| offset <= self.offset)
+            .unwrap_or(0);
+
+        let col = self.col_of(self.index, self.offset, tab_width) - rows[row].1;
+        match self.is_insert() && col >= width.max(1) {
+            true => (row + 1, 0),
+            false => (row, col),
+        }
     }
 
     pub fn len_lines(&self) -> usize {
@@ -100,15 +464,205 @@ impl Buffer {
         self.mode == CursorMode::Insert
     }
 
+    pub fn is_command(&self) -> bool {
+        self.mode == CursorMode::Command
+    }
+
     pub fn char(&self, pos: usize) -> char {
         self.text.char(pos)
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SelectionKind {
+    Char,
+    Line,
+    /// Rectangle of display columns over a range of lines.
+    Block,
+}
+
+/// Cursor, scroll and selection of one view onto a buffer, by char index
+/// so they can follow the changes made to the text through other views.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ViewState {
+    cursor: usize,
+    /// Start of the first line shown.
+    vscroll: usize,
+    hscroll: usize,
+    selection: Option<Selection>,
+    /// Starts of the first and last line of the last selection.
+    last_selected: Option<(usize, usize)>,
+}
+
+impl ViewState {
+    /// Moves the positions along with the text changed by `tx`.
+    pub fn map(&mut self, tx: &Transaction) {
+        self.cursor = tx.map_pos(self.cursor);
+        self.vscroll = tx.map_pos(self.vscroll);
+
+        if let Some(selection) = self.selection.as_mut() {
+            selection.anchor = tx.map_pos(selection.anchor);
+        }
+
+        self.last_selected = self
+            .last_selected
+            .map(|(first, last)| (tx.map_pos(first), tx.map_pos(last)));
+    }
+}
+
+/// Selection between the `anchor` char index and the cursor.
+#[derive(Debug, Clone, Copy)]
+pub struct Selection {
+    pub anchor: usize,
+    pub kind: SelectionKind,
+}
+
+/// Insert on the first line of a block, repeated on the other `lines` when it's done.
+#[derive(Debug, Clone)]
+pub struct BlockInsert {
+    pub lines: RangeInclusive<usize>,
+    /// Display column to insert at, the line end when `None`.
+    pub col: Option<usize>,
+    /// Char index the insert started at.
+    pub start: usize,
+    /// Pad lines shorter than `col` with spaces instead of skipping them.
+    pub pad: bool,
+}
+
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum CursorMode {
     Insert,
     #[default]
     Normal,
     Visual,
+    Command,
+}
+
+impl CursorMode {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Insert => "INSERT",
+            Self::Normal => "NORMAL",
+            Self::Visual => "VISUAL",
+            Self::Command => "COMMAND",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::buffer;
+
+    use super::*;
+
+    #[test]
+    fn test_buffer_positions() {
+        let mut buf = buffer("héllo\nпривет 👍🏽\nne\u{301}\r\n");
+
+        buf.set_pos((1, 7));
+        assert_eq!(13, buf.as_char_pos());
+        assert_eq!((1, 7), buf.as_curs_pos(13));
+        assert_eq!(15, buf.next_grapheme(13));
+        assert_eq!(12, buf.prev_grapheme(13));
+
+        assert_eq!(5, buf.line_last(0));
+        assert_eq!(9, buf.line_last(1));
+        assert_eq!(3, buf.line_last(2));
+        assert_eq!(0, buf.line_last(3));
+
+        assert_eq!(3, buf.line_len(2));
+        assert_eq!(9, buf.clamp_offset(1, 20));
+        assert_eq!(7, buf.clamp_offset(1, 8));
+        assert_eq!(1, buf.clamp_offset(2, 2));
+    }
+
+    #[test]
+    fn test_buffer_literals() {
+        let mut buf = buffer("select 'a'");
+        assert_eq!(Some(&(7..10)), buf.literals().first());
+
+        buf.text_mut().insert(0, "-- q\n");
+        assert_eq!([0..4, 12..15], buf.literals());
+    }
+
+    #[test]
+    fn test_selection_range() {
+        let mut buf = buffer("ab\n  cd👍🏽\nef");
+
+        buf.set_mode(CursorMode::Visual);
+        buf.set_pos((1, 4));
+        buf.select(SelectionKind::Char);
+        buf.set_pos((0, 1));
+        assert_eq!(Some(1..9), buf.selection_range());
+        assert_eq!(Some(0..=1), buf.selected_lines());
+
+        buf.set_pos((1, 6));
+        assert_eq!(Some(7..10), buf.selection_range());
+
+        buf.set_selection_kind(SelectionKind::Line);
+        buf.set_pos((2, 1));
+        assert_eq!(Some(3..12), buf.selection_range());
+        assert_eq!("V-LINE", buf.mode_name());
+        assert_eq!(2, buf.first_non_blank(1));
+
+        buf.set_selection_kind(SelectionKind::Block);
+        assert_eq!(None, buf.selection_range());
+        assert_eq!("V-BLOCK", buf.mode_name());
+
+        buf.clear_selection();
+        assert_eq!(None, buf.selection_range());
+    }
+
+    #[test]
+    fn test_block_selection() {
+        let mut buf = buffer("abcdef\n表表\n\tx\nab");
+
+        buf.set_pos((0, 1));
+        buf.select(SelectionKind::Block);
+        buf.set_pos((2, 1));
+        assert_eq!(Some(1..5), buf.block_cols(4));
+        assert_eq!(Some(0..=2), buf.selected_lines());
+
+        let cols = 1..5;
+        assert_eq!(1..5, buf.block_offsets(0, &cols, 4));
+        assert_eq!(0..2, buf.block_offsets(1, &cols, 4));
+        assert_eq!(0..2, buf.block_offsets(2, &cols, 4));
+        assert_eq!(1..2, buf.block_offsets(3, &cols, 4));
+
+        buf.set_pos((1, 1));
+        assert_eq!(Some(1..4), buf.block_cols(4));
+    }
+
+    #[test]
+    fn test_buffer_columns() {
+        let mut buf = buffer("\tab\n表表表x\n");
+
+        assert_eq!(4, buf.col_of(0, 1, 4));
+        assert_eq!(6, buf.col_of(0, 3, 4));
+        assert_eq!(0, buf.offset_at_col(0, 3, 4));
+        assert_eq!(2, buf.offset_at_col(0, 5, 4));
+        assert_eq!(3, buf.offset_at_col(0, 10, 4));
+
+        assert_eq!(4, buf.col_of(1, 2, 4));
+        assert_eq!(1, buf.offset_at_col(1, 3, 4));
+
+        assert_eq!(vec![(0, 0), (1, 2), (2, 4)], buf.row_starts(1, 3, 4));
+        assert_eq!(vec![(0, 0), (2, 4)], buf.row_starts(1, 5, 4));
+
+        buf.set_pos((1, 3));
+        assert_eq!((1, 2), buf.wrapped_pos(5, 4));
+
+        assert_eq!((2, 2), buf.wrapped_pos(3, 4));
+
+        buf.update_hscroll(3, 4);
+        assert_eq!(4, buf.hscroll());
+        buf.set_pos((1, 1));
+        buf.update_hscroll(3, 4);
+        assert_eq!(2, buf.hscroll());
+
+        buf.set_mode(CursorMode::Insert);
+        buf.set_pos((1, 4));
+        assert_eq!((1, 3), buf.wrapped_pos(5, 4));
+        assert_eq!((3, 0), buf.wrapped_pos(3, 4));
+    }
 }
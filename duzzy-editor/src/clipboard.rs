@@ -0,0 +1,286 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::Context;
+
+/// System clipboard behind the `+` register, or the primary selection behind `*`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ClipboardKind {
+    Clipboard,
+    Selection,
+}
+
+impl ClipboardKind {
+    pub const fn from_register(name: char) -> Option<Self> {
+        match name {
+            '+' => Some(Self::Clipboard),
+            '*' => Some(Self::Selection),
+            _ => None,
+        }
+    }
+}
+
+/// Copies text to and pastes it from outside of the editor.
+pub trait ClipboardProvider {
+    fn name(&self) -> &str;
+
+    fn get(&mut self, kind: ClipboardKind) -> anyhow::Result<String>;
+
+    fn set(&mut self, kind: ClipboardKind, text: &str) -> anyhow::Result<()>;
+
+    /// Escape sequences left for the terminal by copying through it, written by the caller.
+    fn take_output(&mut self) -> Option<String> {
+        None
+    }
+}
+
+/// Clipboard tool found on the system, or OSC 52 escapes when there is none.
+pub fn detect() -> Box<dyn ClipboardProvider> {
+    match CommandProvider::detect() {
+        Some(provider) => Box::new(provider),
+        None => Box::new(Osc52::default()),
+    }
+}
+
+/// Copies through the terminal with OSC 52 escapes, which also works over SSH.
+/// Terminals rarely answer clipboard queries, so pasting gives back the last copied text.
+#[derive(Debug, Default)]
+pub struct Osc52 {
+    copied: HashMap<ClipboardKind, String>,
+    output: String,
+}
+
+impl Osc52 {
+    fn sequence(kind: ClipboardKind, text: &str) -> String {
+        let target = match kind {
+            ClipboardKind::Clipboard => 'c',
+            ClipboardKind::Selection => 'p',
+        };
+
+        format!("\x1b]52;{target};{}\x07", base64(text.as_bytes()))
+    }
+}
+
+impl ClipboardProvider for Osc52 {
+    fn name(&self) -> &str {
+        "osc52"
+    }
+
+    fn get(&mut self, kind: ClipboardKind) -> anyhow::Result<String> {
+        self.copied
+            .get(&kind)
+            .cloned()
+            .context("Nothing copied to the terminal clipboard")
+    }
+
+    fn set(&mut self, kind: ClipboardKind, text: &str) -> anyhow::Result<()> {
+        self.output.push_str(&Self::sequence(kind, text));
+        self.copied.insert(kind, text.to_string());
+        Ok(())
+    }
+
+    fn take_output(&mut self) -> Option<String> {
+        Some(std::mem::take(&mut self.output)).filter(|output| !output.is_empty())
+    }
+}
+
+/// Copies and pastes by running external tools like `wl-copy` or `xclip`.
+#[derive(Debug, Clone)]
+pub struct CommandProvider {
+    name: &'static str,
+    copy: HashMap<ClipboardKind, Vec<&'static str>>,
+    paste: HashMap<ClipboardKind, Vec<&'static str>>,
+}
+
+impl CommandProvider {
+    /// Tools of the running display server, checked to be on `PATH`.
+    pub fn detect() -> Option<Self> {
+        use ClipboardKind::{Clipboard, Selection};
+
+        let has_env = |name: &str| env::var_os(name).is_some_and(|value| !value.is_empty());
+
+        let provider = if has_env("WAYLAND_DISPLAY") && on_path("wl-copy") && on_path("wl-paste") {
+            Self {
+                name: "wl-clipboard",
+                copy: HashMap::from([
+                    (Clipboard, vec!["wl-copy", "--type", "text/plain"]),
+                    (
+                        Selection,
+                        vec!["wl-copy", "--primary", "--type", "text/plain"],
+                    ),
+                ]),
+                paste: HashMap::from([
+                    (Clipboard, vec!["wl-paste", "--no-newline"]),
+                    (Selection, vec!["wl-paste", "--no-newline", "--primary"]),
+                ]),
+            }
+        } else if has_env("DISPLAY") && on_path("xclip") {
+            Self {
+                name: "xclip",
+                copy: HashMap::from([
+                    (Clipboard, vec!["xclip", "-in", "-selection", "clipboard"]),
+                    (Selection, vec!["xclip", "-in", "-selection", "primary"]),
+                ]),
+                paste: HashMap::from([
+                    (Clipboard, vec!["xclip", "-out", "-selection", "clipboard"]),
+                    (Selection, vec!["xclip", "-out", "-selection", "primary"]),
+                ]),
+            }
+        } else if has_env("DISPLAY") && on_path("xsel") {
+            Self {
+                name: "xsel",
+                copy: HashMap::from([
+                    (Clipboard, vec!["xsel", "--input", "--clipboard"]),
+                    (Selection, vec!["xsel", "--input", "--primary"]),
+                ]),
+                paste: HashMap::from([
+                    (Clipboard, vec!["xsel", "--output", "--clipboard"]),
+                    (Selection, vec!["xsel", "--output", "--primary"]),
+                ]),
+            }
+        } else if on_path("pbcopy") && on_path("pbpaste") {
+            Self {
+                name: "pbcopy",
+                copy: HashMap::from([(Clipboard, vec!["pbcopy"]), (Selection, vec!["pbcopy"])]),
+                paste: HashMap::from([(Clipboard, vec!["pbpaste"]), (Selection, vec!["pbpaste"])]),
+            }
+        } else {
+            return None;
+        };
+
+        Some(provider)
+    }
+
+    fn command(args: &[&str]) -> Command {
+        let mut command = Command::new(args[0]);
+        command.args(&args[1..]).stderr(Stdio::null());
+        command
+    }
+}
+
+impl ClipboardProvider for CommandProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn get(&mut self, kind: ClipboardKind) -> anyhow::Result<String> {
+        let output = Self::command(&self.paste[&kind])
+            .stdin(Stdio::null())
+            .output()
+            .with_context(|| format!("Failed to run {}", self.name))?;
+
+        if !output.status.success() {
+            anyhow::bail!("{} exited with {}", self.name, output.status);
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    fn set(&mut self, kind: ClipboardKind, text: &str) -> anyhow::Result<()> {
+        let mut child = Self::command(&self.copy[&kind])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.name))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {status}", self.name);
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps copied text in memory, standing in for the system clipboard in tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct FakeClipboard {
+    pub contents: HashMap<ClipboardKind, String>,
+}
+
+#[cfg(test)]
+impl ClipboardProvider for FakeClipboard {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    fn get(&mut self, kind: ClipboardKind) -> anyhow::Result<String> {
+        Ok(self.contents.get(&kind).cloned().unwrap_or_default())
+    }
+
+    fn set(&mut self, kind: ClipboardKind, text: &str) -> anyhow::Result<()> {
+        self.contents.insert(kind, text.to_string());
+        Ok(())
+    }
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file())
+    })
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!("", base64(b""));
+        assert_eq!("Zg==", base64(b"f"));
+        assert_eq!("Zm8=", base64(b"fo"));
+        assert_eq!("Zm9v", base64(b"foo"));
+        assert_eq!("c2VsZWN0IDE7", base64(b"select 1;"));
+
+        assert_eq!(
+            "\x1b]52;c;c2VsZWN0IDE7\x07",
+            Osc52::sequence(ClipboardKind::Clipboard, "select 1;")
+        );
+        assert_eq!(
+            "\x1b]52;p;Zm9v\x07",
+            Osc52::sequence(ClipboardKind::Selection, "foo")
+        );
+
+        let mut osc52 = Osc52::default();
+        assert_eq!(None, osc52.take_output());
+
+        osc52.set(ClipboardKind::Clipboard, "foo").unwrap();
+        assert_eq!("foo", osc52.get(ClipboardKind::Clipboard).unwrap());
+        assert_eq!(Some("\x1b]52;c;Zm9v\x07".into()), osc52.take_output());
+        assert_eq!(None, osc52.take_output());
+    }
+}
@@ -1,13 +1,16 @@
 use crate::{
-    editor::Workspace,
+    editor::{Message, Workspace},
+    register::Register,
     transaction::{Transaction, TransactionResult},
 };
 
+use super::Context;
+
 pub(super) fn insert_char(ws: &mut Workspace, ch: char) {
     let doc = ws.curr_mut();
 
     doc.with_transaction(|insert_tx, buf| {
-        let pos = buf.as_byte_pos();
+        let pos = buf.as_char_pos();
         let mut tx = Transaction::new();
 
         tx.insert_char(pos, ch);
@@ -21,11 +24,41 @@ pub(super) fn insert_char(ws: &mut Workspace, ch: char) {
     });
 }
 
+/// Inserts `text` at the cursor as one change, its line breaks turned into `\n`.
+/// In insert mode the change joins the ones typed, in other modes it's committed.
+pub(super) fn insert_text(ws: &mut Workspace, text: &str) {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    if text.is_empty() {
+        return;
+    }
+
+    ws.curr_mut().with_transaction(|insert_tx, buf| {
+        let pos = buf.as_char_pos();
+        let end = pos + text.chars().count();
+        let mut tx = Transaction::new();
+
+        tx.shift(pos);
+        tx.insert_str(pos, &text);
+        tx.apply(buf.text_mut());
+
+        insert_tx.merge(tx);
+
+        if buf.is_insert() {
+            buf.set_pos(buf.as_curs_pos(end));
+            return TransactionResult::Keep;
+        }
+
+        // on the last char inserted, like after `p`
+        buf.set_pos(buf.as_curs_pos(buf.prev_grapheme(end)));
+        TransactionResult::Commit
+    });
+}
+
 pub(super) fn new_line(ws: &mut Workspace) {
     let doc = ws.curr_mut();
 
     doc.with_transaction(|insert_tx, buf| {
-        let pos = buf.as_byte_pos();
+        let pos = buf.as_char_pos();
         let mut tx = Transaction::new();
 
         tx.insert_char(pos, '\n');
@@ -42,22 +75,36 @@ pub(super) fn new_line(ws: &mut Workspace) {
     });
 }
 
-pub(super) fn delete_char_inplace(ws: &mut Workspace) {
-    let doc = ws.curr_mut();
+/// Deletes `count` graphemes from the cursor into a register,
+/// a count never reaches past the line end.
+pub(super) fn delete_char_inplace(ws: &mut Workspace, ctx: Context) {
+    let buf = ws.curr().buf();
+    let pos = buf.as_char_pos();
 
-    doc.with_transaction(|tx, buf| {
-        let pos = buf.as_byte_pos();
+    if pos >= buf.len_chars() {
+        return;
+    }
 
-        if pos < buf.len_chars() {
-            let ch = buf.char(pos);
+    let mut end = buf.next_grapheme(pos);
+    for _ in 1..ctx.count() {
+        if end == buf.len_chars() || matches!(buf.text().char(end), '\n' | '\r') {
+            break;
+        }
+        end = buf.next_grapheme(end);
+    }
 
-            tx.delete_char(pos, ch);
-            tx.apply(buf.text_mut());
+    let text = buf.text().slice(pos..end).to_string();
+    let register = Register::new(text.clone(), false);
 
-            return TransactionResult::Commit;
-        }
+    if let Err(e) = ws.registers_mut().delete(ctx.register(), register) {
+        return ws.set_message(Message::Error(format!("{e:#}")));
+    }
+
+    ws.curr_mut().with_transaction(|tx, buf| {
+        tx.delete_str(end, &text);
+        tx.apply(buf.text_mut());
 
-        TransactionResult::Abort
+        TransactionResult::Commit
     });
 }
 
@@ -65,20 +112,20 @@ pub(super) fn delete_char(ws: &mut Workspace) {
     let doc = ws.curr_mut();
 
     doc.with_transaction(|delete_tx, buf| {
-        let pos = buf.as_byte_pos();
+        let pos = buf.as_char_pos();
 
         if pos > 0 {
-            let char_pos = pos - 1;
+            let start = buf.prev_grapheme(pos);
+            let grapheme = buf.text().slice(start..pos).to_string();
 
             let mut tx = Transaction::new();
-            let ch = buf.char(char_pos);
 
-            tx.delete_char(char_pos, ch);
+            tx.delete_str(pos, &grapheme);
             tx.apply(buf.text_mut());
 
             delete_tx.merge(tx);
 
-            let new_pos = super::shift_left(buf);
+            let new_pos = buf.as_curs_pos(start);
             buf.set_pos(new_pos);
         }
 
@@ -125,12 +172,50 @@ mod tests {
 
         ws.curr_mut().commit();
 
-        delete_char_inplace(&mut ws);
-        delete_char_inplace(&mut ws);
-        delete_char_inplace(&mut ws);
+        delete_char_inplace(&mut ws, Context::default());
+        delete_char_inplace(&mut ws, Context::default());
+        delete_char_inplace(&mut ws, Context::default());
 
         let buf = ws.curr().buf();
         assert_eq!((4, 0), buf.pos());
         assert_eq!(&buf.text().to_string(), "\n\n\n\n");
     }
+
+    #[test]
+    fn test_adjustment_unicode() {
+        let mut ws = Workspace::default();
+        ws.add_doc(Document::default());
+
+        for ch in "имя_👍🏽e\u{301}".chars() {
+            insert_char(&mut ws, ch);
+        }
+
+        let buf = ws.curr().buf();
+        assert_eq!((0, 8), buf.pos());
+        assert_eq!(&buf.text().to_string(), "имя_👍🏽e\u{301}");
+
+        delete_char(&mut ws);
+        delete_char(&mut ws);
+
+        let buf = ws.curr().buf();
+        assert_eq!((0, 4), buf.pos());
+        assert_eq!(&buf.text().to_string(), "имя_");
+
+        ws.curr_mut().commit();
+        ws.curr_mut().buf_mut().set_pos((0, 1));
+        new_line(&mut ws);
+        insert_char(&mut ws, 'ё');
+        ws.curr_mut().commit();
+
+        let buf = ws.curr().buf();
+        assert_eq!((1, 1), buf.pos());
+        assert_eq!(&buf.text().to_string(), "и\nёмя_");
+
+        ws.curr_mut().undo();
+        assert_eq!(&ws.curr().buf().text().to_string(), "имя_");
+
+        ws.curr_mut().buf_mut().set_pos((0, 3));
+        delete_char_inplace(&mut ws, Context::default());
+        assert_eq!(&ws.curr().buf().text().to_string(), "имя");
+    }
 }
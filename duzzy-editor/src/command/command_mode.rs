@@ -0,0 +1,404 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    buffer::{Buffer, CursorMode},
+    editor::{Message, Workspace},
+    input::{Event, Input},
+    renderer::EventOutcome,
+};
+
+use super::{CmdType, CommandRegistry, Context};
+
+type TypedCallback = fn(&mut Workspace, &str) -> anyhow::Result<()>;
+type RangedCallback = fn(&mut Workspace, RangeInclusive<usize>, &str) -> anyhow::Result<()>;
+
+/// Ex commands called without arguments, resolved through the command registry.
+const COMMANDS: &[(&str, CmdType)] = &[
+    ("w", CmdType::Save),
+    ("write", CmdType::Save),
+    ("w!", CmdType::ForceSave),
+    ("write!", CmdType::ForceSave),
+    ("q", CmdType::Quit),
+    ("quit", CmdType::Quit),
+    ("q!", CmdType::ForceQuit),
+    ("quit!", CmdType::ForceQuit),
+    ("wq", CmdType::SaveQuit),
+    ("x", CmdType::SaveQuit),
+    ("u", CmdType::Undo),
+    ("undo", CmdType::Undo),
+    ("redo", CmdType::Redo),
+    ("bn", CmdType::NextDocument),
+    ("bnext", CmdType::NextDocument),
+    ("bp", CmdType::PrevDocument),
+    ("bprevious", CmdType::PrevDocument),
+    ("ls", CmdType::DocumentPicker),
+    ("buffers", CmdType::DocumentPicker),
+    ("sp", CmdType::SplitWindow),
+    ("split", CmdType::SplitWindow),
+    ("vs", CmdType::VSplitWindow),
+    ("vsplit", CmdType::VSplitWindow),
+    ("clo", CmdType::CloseWindow),
+    ("close", CmdType::CloseWindow),
+    ("on", CmdType::OnlyWindow),
+    ("only", CmdType::OnlyWindow),
+    ("tabn", CmdType::NextTab),
+    ("tabnext", CmdType::NextTab),
+    ("tabp", CmdType::PrevTab),
+    ("tabprevious", CmdType::PrevTab),
+    ("tabc", CmdType::CloseTab),
+    ("tabclose", CmdType::CloseTab),
+    ("tabo", CmdType::OnlyTab),
+    ("tabonly", CmdType::OnlyTab),
+];
+
+/// Ex commands taking the rest of the line as an argument.
+const TYPED_COMMANDS: &[(&str, TypedCallback)] = &[
+    ("w", super::file::write_as),
+    ("write", super::file::write_as),
+    ("w!", super::file::force_write_as),
+    ("write!", super::file::force_write_as),
+    ("e", super::file::edit),
+    ("edit", super::file::edit),
+    ("se", super::options::set),
+    ("set", super::options::set),
+    ("b", super::documents::buffer),
+    ("buffer", super::documents::buffer),
+    ("bd", super::documents::buffer_delete),
+    ("bdelete", super::documents::buffer_delete),
+    ("bd!", super::documents::force_buffer_delete),
+    ("bdelete!", super::documents::force_buffer_delete),
+    ("tabnew", super::tabs::tab_new),
+    ("tabe", super::tabs::tab_new),
+    ("tabedit", super::tabs::tab_new),
+    ("tabn", super::tabs::tab_next),
+    ("tabnext", super::tabs::tab_next),
+    ("tabm", super::tabs::tab_move),
+    ("tabmove", super::tabs::tab_move),
+];
+
+/// Ex commands taking a range of lines, the cursor line without one,
+/// and the rest of the line as an argument.
+const RANGED_COMMANDS: &[(&str, RangedCallback)] = &[
+    ("s", super::substitute::substitute),
+    ("substitute", super::substitute::substitute),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum ExCommand<'a> {
+    Command(CmdType),
+    Typed(TypedCallback, &'a str),
+    Ranged(RangedCallback, Option<LineRange>, &'a str),
+    Line(usize),
+    /// Range typed alone, going to its last line.
+    GoTo(LineRange),
+}
+
+/// Line given by a number, `.` for the cursor line, `$` for the last line,
+/// `'<` and `'>` for the last selection, each followed by `+n` or `-n` offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Address {
+    Number(usize),
+    Current,
+    Last,
+    SelectionStart,
+    SelectionEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LineRange {
+    start: (Address, isize),
+    end: (Address, isize),
+}
+
+impl LineRange {
+    /// Lines of the range in `buf`, counted from zero.
+    fn resolve(&self, buf: &Buffer) -> anyhow::Result<RangeInclusive<usize>> {
+        let line = |(address, offset): (Address, isize)| {
+            let index = match address {
+                Address::Number(number) => number.saturating_sub(1),
+                Address::Current => buf.index(),
+                Address::Last => buf.len_lines() - 1,
+                Address::SelectionStart | Address::SelectionEnd => {
+                    let Some((first, last)) = buf.last_selected() else {
+                        anyhow::bail!("No previous selection");
+                    };
+
+                    match address {
+                        Address::SelectionStart => first,
+                        _ => last,
+                    }
+                }
+            };
+
+            index
+                .checked_add_signed(offset)
+                .filter(|index| *index < buf.len_lines())
+                .ok_or_else(|| anyhow::anyhow!("Invalid range"))
+        };
+
+        let (start, end) = (line(self.start)?, line(self.end)?);
+        if start > end {
+            anyhow::bail!("Backwards range");
+        }
+
+        Ok(start..=end)
+    }
+}
+
+pub fn on_key(ws: &mut Workspace, registry: &CommandRegistry, input: Input) -> EventOutcome {
+    let prompt = ws.cmdline_mut();
+
+    match input.event {
+        Event::Esc => close(ws),
+        Event::Enter => {
+            let line = prompt.submit();
+            close(ws);
+
+            if let Err(e) = execute(ws, registry, &line) {
+                ws.set_message(Message::Error(format!("{e:#}")));
+            }
+        }
+        Event::Backspace if prompt.input().is_empty() => close(ws),
+        Event::Backspace => prompt.delete_char(),
+        Event::Delete => prompt.delete_char_inplace(),
+        Event::Left => prompt.move_left(),
+        Event::Right => prompt.move_right(),
+        Event::Home => prompt.move_start(),
+        Event::End => prompt.move_end(),
+        Event::Up => prompt.recall_prev(),
+        Event::Down => prompt.recall_next(),
+        Event::Char(ch) if !input.modifiers.ctr && !input.modifiers.alt => prompt.insert_char(ch),
+        // only the first line of pasted text, enter runs the command
+        Event::Paste(text) => {
+            for ch in text.lines().next().unwrap_or_default().chars() {
+                prompt.insert_char(ch);
+            }
+        }
+        _ => return EventOutcome::Ignore,
+    }
+
+    EventOutcome::Render
+}
+
+fn close(ws: &mut Workspace) {
+    ws.cmdline_mut().reset();
+    ws.curr_mut().buf_mut().set_mode(CursorMode::Normal);
+}
+
+fn execute(ws: &mut Workspace, registry: &CommandRegistry, line: &str) -> anyhow::Result<()> {
+    match parse(line)? {
+        Some(ExCommand::Command(type_)) => {
+            if let Some(command) = registry.get(&type_) {
+                command.call(ws, Context::default());
+            }
+        }
+        Some(ExCommand::Typed(callback, args)) => callback(ws, args)?,
+        Some(ExCommand::Ranged(callback, range, args)) => {
+            let buf = ws.curr().buf();
+            let lines = match range {
+                Some(range) => range.resolve(buf)?,
+                None => buf.index()..=buf.index(),
+            };
+
+            callback(ws, lines, args)?;
+        }
+        Some(ExCommand::Line(line)) => super::movement::go_to_line(ws, line),
+        Some(ExCommand::GoTo(range)) => {
+            let lines = range.resolve(ws.curr().buf())?;
+            super::movement::go_to_line(ws, lines.end() + 1);
+        }
+        None => (),
+    }
+
+    Ok(())
+}
+
+fn parse(line: &str) -> anyhow::Result<Option<ExCommand<'_>>> {
+    let line = line.trim_start_matches([':', ' ']).trim_end();
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    if line.bytes().all(|b| b.is_ascii_digit()) {
+        let number = line.parse()?;
+        return Ok(Some(ExCommand::Line(number)));
+    }
+
+    let (range, line) = parse_range(line)?;
+
+    if let (Some(range), "") = (range, line) {
+        return Ok(Some(ExCommand::GoTo(range)));
+    }
+
+    let mut split = line
+        .find(|c: char| !c.is_alphanumeric())
+        .unwrap_or(line.len());
+    if line[split..].starts_with('!') {
+        split += 1;
+    }
+
+    let (name, args) = (&line[..split], line[split..].trim());
+
+    if let Some(callback) = lookup(RANGED_COMMANDS, name) {
+        return Ok(Some(ExCommand::Ranged(callback, range, args)));
+    }
+
+    if range.is_some() {
+        anyhow::bail!("No range allowed: {line}");
+    }
+
+    let command = match (lookup(COMMANDS, name), lookup(TYPED_COMMANDS, name)) {
+        (Some(type_), _) if args.is_empty() => ExCommand::Command(type_),
+        (_, Some(callback)) => ExCommand::Typed(callback, args),
+        (Some(_), None) => anyhow::bail!("Trailing characters: {args}"),
+        (None, None) => anyhow::bail!("Not an editor command: {line}"),
+    };
+
+    Ok(Some(command))
+}
+
+/// Splits a leading range off `line`, `%` standing for every line.
+fn parse_range(line: &str) -> anyhow::Result<(Option<LineRange>, &str)> {
+    if let Some(rest) = line.strip_prefix('%') {
+        let range = LineRange {
+            start: (Address::Number(1), 0),
+            end: (Address::Last, 0),
+        };
+        return Ok((Some(range), rest.trim_start()));
+    }
+
+    let Some((start, rest)) = parse_address(line)? else {
+        return Ok((None, line));
+    };
+
+    let Some(rest) = rest.strip_prefix(',') else {
+        let range = LineRange { start, end: start };
+        return Ok((Some(range), rest.trim_start()));
+    };
+
+    let Some((end, rest)) = parse_address(rest)? else {
+        anyhow::bail!("Invalid range: {line}");
+    };
+
+    Ok((Some(LineRange { start, end }), rest.trim_start()))
+}
+
+fn parse_address(line: &str) -> anyhow::Result<Option<((Address, isize), &str)>> {
+    let digits = |text: &str| {
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+
+    let (address, mut rest) = match line.chars().next() {
+        Some('0'..='9') => {
+            let split = digits(line);
+            (Address::Number(line[..split].parse()?), &line[split..])
+        }
+        Some('.') => (Address::Current, &line[1..]),
+        Some('$') => (Address::Last, &line[1..]),
+        Some('\'') if line[1..].starts_with('<') => (Address::SelectionStart, &line[2..]),
+        Some('\'') if line[1..].starts_with('>') => (Address::SelectionEnd, &line[2..]),
+        // an offset alone counts from the cursor line
+        Some('+' | '-') => (Address::Current, line),
+        _ => return Ok(None),
+    };
+
+    let mut offset = 0isize;
+    while let Some(sign) = rest.chars().next().filter(|c| matches!(c, '+' | '-')) {
+        let split = digits(&rest[1..]) + 1;
+        let number = match split {
+            1 => 1,
+            _ => rest[1..split].parse()?,
+        };
+
+        let sum = match sign {
+            '+' => offset.checked_add(number),
+            _ => offset.checked_sub(number),
+        };
+        let Some(sum) = sum else {
+            anyhow::bail!("Invalid range: {line}");
+        };
+
+        offset = sum;
+        rest = &rest[split..];
+    }
+
+    Ok(Some(((address, offset), rest)))
+}
+
+fn lookup<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("").unwrap().is_none());
+        assert!(parse(" : ").unwrap().is_none());
+        assert!(matches!(parse("42").unwrap(), Some(ExCommand::Line(42))));
+
+        let command = parse("w").unwrap();
+        assert!(matches!(command, Some(ExCommand::Command(CmdType::Save))));
+
+        let command = parse("q!").unwrap();
+        assert!(matches!(
+            command,
+            Some(ExCommand::Command(CmdType::ForceQuit))
+        ));
+
+        let Some(ExCommand::Typed(_, args)) = parse("w! schema.sql").unwrap() else {
+            panic!("expected typed command");
+        };
+        assert_eq!("schema.sql", args);
+
+        let Some(ExCommand::Typed(_, args)) = parse("e").unwrap() else {
+            panic!("expected typed command");
+        };
+        assert_eq!("", args);
+
+        let Some(ExCommand::Ranged(_, range, args)) = parse("s/a/b/g").unwrap() else {
+            panic!("expected ranged command");
+        };
+        assert_eq!(None, range);
+        assert_eq!("/a/b/g", args);
+
+        let Some(ExCommand::Ranged(_, range, _)) = parse("%s/a/b/").unwrap() else {
+            panic!("expected ranged command");
+        };
+        assert_eq!(
+            Some(LineRange {
+                start: (Address::Number(1), 0),
+                end: (Address::Last, 0)
+            }),
+            range
+        );
+
+        let Some(ExCommand::Ranged(_, range, _)) = parse("'<,'>+2 s/a/b/").unwrap() else {
+            panic!("expected ranged command");
+        };
+        assert_eq!(
+            Some(LineRange {
+                start: (Address::SelectionStart, 0),
+                end: (Address::SelectionEnd, 2)
+            }),
+            range
+        );
+
+        let Some(ExCommand::GoTo(range)) = parse(".-1,$").unwrap() else {
+            panic!("expected range");
+        };
+        assert_eq!((Address::Current, -1), range.start);
+        assert_eq!((Address::Last, 0), range.end);
+
+        assert!(parse("1,w").is_err());
+        assert!(parse("%w").is_err());
+        assert!(parse("q now").is_err());
+        assert!(parse("unknown").is_err());
+        assert!(parse(".+9223372036854775807+9223372036854775807").is_err());
+        assert!(parse("-9223372036854775807-2d").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    document::{Document, DocumentId},
+    editor::{Message, Workspace},
+    input::{Event, Input},
+    picker::DocumentPicker,
+    renderer::EventOutcome,
+};
+
+use super::Context;
+
+pub(super) fn next_document(ws: &mut Workspace, ctx: Context) {
+    cycle(ws, ctx.count(), true);
+}
+
+pub(super) fn prev_document(ws: &mut Workspace, ctx: Context) {
+    cycle(ws, ctx.count(), false);
+}
+
+/// Goes back to the document current before the last switch.
+pub(super) fn alternate_document(ws: &mut Workspace, _ctx: Context) {
+    match ws.alternate() {
+        Some(id) => ws.switch(id),
+        None => ws.set_message(Message::Error("No alternate document".into())),
+    }
+}
+
+/// Opens the list of documents, with the current one selected.
+pub(super) fn document_picker(ws: &mut Workspace, _ctx: Context) {
+    let ids = ws.documents().map(Document::id).collect();
+    ws.set_picker(Some(DocumentPicker::new(ids, ws.current())));
+}
+
+/// `:b`, switches to the document numbered or named by `args`, or to the alternate one for `#`.
+pub(super) fn buffer(ws: &mut Workspace, args: &str) -> anyhow::Result<()> {
+    if let Some(id) = find(ws, args)? {
+        ws.switch(id);
+    }
+
+    Ok(())
+}
+
+/// `:bd`, closes the document numbered or named by `args`, the current one without.
+pub(super) fn buffer_delete(ws: &mut Workspace, args: &str) -> anyhow::Result<()> {
+    delete(ws, args, false)
+}
+
+pub(super) fn force_buffer_delete(ws: &mut Workspace, args: &str) -> anyhow::Result<()> {
+    delete(ws, args, true)
+}
+
+/// Moves the picker selection with `j` and `k`, switches to the selected document
+/// with `Enter` and closes with `Esc` or `q`.
+pub fn on_picker_key(ws: &mut Workspace, input: Input) -> EventOutcome {
+    let Some(picker) = ws.picker_mut() else {
+        return EventOutcome::Ignore;
+    };
+
+    match input.event {
+        Event::Char('j') | Event::Down => picker.select_by(1),
+        Event::Char('k') | Event::Up => picker.select_by(-1),
+        Event::Enter => {
+            if let Some(id) = picker.selected_id() {
+                ws.switch(id);
+            }
+            ws.set_picker(None);
+        }
+        Event::Esc | Event::Char('q') => ws.set_picker(None),
+        _ => return EventOutcome::Ignore,
+    }
+
+    EventOutcome::Render
+}
+
+/// Moves `count` documents forward in order, or backward, wrapping around.
+fn cycle(ws: &mut Workspace, count: usize, forward: bool) {
+    let ids: Vec<_> = ws.documents().map(Document::id).collect();
+    let Some(index) = ids.iter().position(|id| *id == ws.current()) else {
+        return;
+    };
+
+    let len = ids.len();
+    let step = count % len;
+    let next = match forward {
+        true => (index + step) % len,
+        false => (index + len - step) % len,
+    };
+    ws.switch(ids[next]);
+}
+
+/// Document numbered from 1 in the picker, or the only one whose name contains `args`.
+fn find(ws: &Workspace, args: &str) -> anyhow::Result<Option<DocumentId>> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+
+    if args == "#" {
+        return match ws.alternate() {
+            Some(id) => Ok(Some(id)),
+            None => anyhow::bail!("No alternate document"),
+        };
+    }
+
+    if let Ok(number) = args.parse::<usize>() {
+        return match number.checked_sub(1).and_then(|n| ws.documents().nth(n)) {
+            Some(doc) => Ok(Some(doc.id())),
+            None => anyhow::bail!("No document {number}"),
+        };
+    }
+
+    let mut found = ws
+        .documents()
+        .filter(|doc| doc.meta().name().contains(args));
+
+    match (found.next(), found.next()) {
+        (Some(doc), None) => Ok(Some(doc.id())),
+        (Some(_), Some(_)) => anyhow::bail!("More than one match for {args}"),
+        (None, _) => anyhow::bail!("No matching document for {args}"),
+    }
+}
+
+fn delete(ws: &mut Workspace, args: &str, force: bool) -> anyhow::Result<()> {
+    let id = find(ws, args)?.unwrap_or(ws.current());
+    let doc = ws.document(id).expect("found document");
+
+    if doc.is_modified() && !force {
+        anyhow::bail!(
+            "No write since last change for {} (add ! to override)",
+            doc.meta().name()
+        );
+    }
+
+    ws.close(id);
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::editor::{Message, Quit, Workspace};
+
+use super::Context;
+
+pub(super) fn save(ws: &mut Workspace, _ctx: Context) {
+    write(ws, false);
+}
+
+pub(super) fn force_save(ws: &mut Workspace, _ctx: Context) {
+    write(ws, true);
+}
+
+pub(super) fn save_quit(ws: &mut Workspace, _ctx: Context) {
+    if write(ws, false) {
+        ws.request_quit(Quit::Safe);
+    }
+}
+
+pub(super) fn write_as(ws: &mut Workspace, path: &str) -> anyhow::Result<()> {
+    write_as_impl(ws, path, false)
+}
+
+pub(super) fn force_write_as(ws: &mut Workspace, path: &str) -> anyhow::Result<()> {
+    write_as_impl(ws, path, true)
+}
+
+pub(super) fn edit(ws: &mut Workspace, path: &str) -> anyhow::Result<()> {
+    if path.is_empty() {
+        anyhow::bail!("Argument required");
+    }
+
+    ws.open(path)?;
+    Ok(())
+}
+
+fn write_as_impl(ws: &mut Workspace, path: &str, force: bool) -> anyhow::Result<()> {
+    if path.is_empty() {
+        write(ws, force);
+        return Ok(());
+    }
+
+    ws.curr_mut().save_as(path, force)?;
+    written(ws);
+
+    Ok(())
+}
+
+fn write(ws: &mut Workspace, force: bool) -> bool {
+    match ws.curr_mut().save(force) {
+        Ok(()) => {
+            written(ws);
+            true
+        }
+        Err(e) => {
+            ws.set_message(Message::Error(format!("{e:#}")));
+            false
+        }
+    }
+}
+
+fn written(ws: &mut Workspace) {
+    let doc = ws.curr();
+    let message = format!(
+        "\"{}\" {}L written",
+        doc.meta().name(),
+        doc.buf().len_lines()
+    );
+    ws.set_message(Message::Info(message));
+}
+
+pub(super) const fn quit(ws: &mut Workspace, _ctx: Context) {
+    ws.request_quit(Quit::Safe);
+}
+
+pub(super) const fn force_quit(ws: &mut Workspace, _ctx: Context) {
+    ws.request_quit(Quit::Force);
+}
@@ -1,18 +1,26 @@
 use crate::editor::Workspace;
 
-pub(super) fn undo(ws: &mut Workspace) {
+use super::Context;
+
+pub(super) fn undo(ws: &mut Workspace, ctx: Context) {
     let doc = ws.curr_mut();
 
-    if let Some(pos) = doc.undo() {
+    for _ in 0..ctx.count() {
+        let Some(pos) = doc.undo() else {
+            break;
+        };
         let buf = doc.buf_mut();
         buf.set_pos(buf.as_curs_pos(pos));
     }
 }
 
-pub(super) fn redo(ws: &mut Workspace) {
+pub(super) fn redo(ws: &mut Workspace, ctx: Context) {
     let doc = ws.curr_mut();
 
-    if let Some(pos) = doc.redo() {
+    for _ in 0..ctx.count() {
+        let Some(pos) = doc.redo() else {
+            break;
+        };
         let buf = doc.buf_mut();
         buf.set_pos(buf.as_curs_pos(pos));
     }
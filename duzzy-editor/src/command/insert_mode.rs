@@ -4,13 +4,16 @@ use crate::{
     renderer::EventOutcome,
 };
 
+use super::Context;
+
 pub fn on_key(ws: &mut Workspace, input: Input) -> EventOutcome {
     if let Input {
         event: Event::Char('q'),
         modifiers: Modifiers { ctr: true, .. },
     } = input
     {
-        return EventOutcome::Exit;
+        super::file::quit(ws, Context::default());
+        return EventOutcome::Render;
     }
 
     let mut outcome = EventOutcome::Render;
@@ -20,22 +23,29 @@ pub fn on_key(ws: &mut Workspace, input: Input) -> EventOutcome {
             event: Event::Char(ch),
             ..
         } => super::adjustment::insert_char(ws, ch),
+        Input {
+            event: Event::Paste(text),
+            ..
+        } => super::adjustment::insert_text(ws, &text),
+        Input {
+            event: Event::Tab, ..
+        } => super::adjustment::insert_char(ws, '\t'),
         Input {
             event: Event::Esc, ..
         } => super::switch_mode::normal_mode_inplace(ws),
         Input {
             event: Event::Left, ..
-        } => super::movement::move_left(ws),
+        } => super::movement::move_left(ws, Context::default()),
         Input {
             event: Event::Right,
             ..
-        } => super::movement::move_right(ws),
+        } => super::movement::move_right(ws, Context::default()),
         Input {
             event: Event::Up, ..
-        } => super::movement::move_up(ws),
+        } => super::movement::move_up(ws, Context::default()),
         Input {
             event: Event::Down, ..
-        } => super::movement::move_down(ws),
+        } => super::movement::move_down(ws, Context::default()),
         Input {
             event: Event::Backspace,
             ..
@@ -47,11 +57,11 @@ pub fn on_key(ws: &mut Workspace, input: Input) -> EventOutcome {
         Input {
             event: Event::PageUp,
             ..
-        } => super::movement::go_to_top_line(ws),
+        } => super::movement::go_to_top_line(ws, Context::default()),
         Input {
             event: Event::PageDown,
             ..
-        } => super::movement::go_to_bottom_line(ws),
+        } => super::movement::go_to_bottom_line(ws, Context::default()),
         _ => outcome = EventOutcome::Ignore,
     }
 
@@ -1,24 +1,85 @@
 mod adjustment;
+pub mod command_mode;
+pub mod documents;
+mod file;
 mod history;
 pub mod insert_mode;
 mod movement;
+mod operator;
+mod options;
+mod put;
+pub mod search;
+pub mod substitute;
 mod switch_mode;
+mod tabs;
+mod text_object;
+mod visual;
+mod windows;
 
 use std::{collections::HashMap, sync::Arc};
 
 use adjustment::*;
+use documents::{alternate_document, document_picker, next_document, prev_document};
+use file::{force_quit, force_save, quit, save, save_quit};
 use history::{redo, undo};
+pub use movement::CharFind;
 use movement::*;
+use operator::Motion;
+pub use operator::Operator;
+use put::{put_after, put_before};
+use search::{
+    search_backward, search_forward, search_next, search_prev, search_word_backward,
+    search_word_forward,
+};
 use switch_mode::*;
+use tabs::{close_tab, next_tab, only_tab, prev_tab};
+pub use text_object::{Scope, TextObject};
+use visual::*;
+use windows::*;
 
 use crate::{
     buffer::Buffer,
     editor::Workspace,
-    input::Input,
+    input::{Event, Input, Modifiers},
     keymap::{Keymap, Keymaps},
+    register::Registers,
 };
 
-pub type Callback = fn(&mut Workspace);
+pub type Callback = fn(&mut Workspace, Context);
+
+/// Arguments a command is called with, besides the workspace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Context {
+    /// Count typed before the command, like the `5` of `5j`.
+    count: Option<usize>,
+    /// Char typed after the command, like the `,` of `f,`.
+    ch: Option<char>,
+    /// Register picked before the command, like the `a` of `"ayy`.
+    register: Option<char>,
+}
+
+impl Context {
+    /// Typed count, 1 when none was typed.
+    pub const fn count(&self) -> usize {
+        match self.count {
+            Some(count) => count,
+            None => 1,
+        }
+    }
+
+    /// Typed count, `None` when none was typed.
+    pub const fn raw_count(&self) -> Option<usize> {
+        self.count
+    }
+
+    pub const fn ch(&self) -> Option<char> {
+        self.ch
+    }
+
+    pub const fn register(&self) -> Option<char> {
+        self.register
+    }
+}
 
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd)]
 pub enum CmdType {
@@ -36,8 +97,77 @@ pub enum CmdType {
     GoToBottomLine,
     GoToLineStart,
     GoToLineEnd,
+    MatchBracket,
+    MoveNextWordStart,
+    MovePrevWordStart,
+    MoveNextWordEnd,
+    MovePrevWordEnd,
+    MoveNextLongWordStart,
+    MovePrevLongWordStart,
+    MoveNextLongWordEnd,
+    MovePrevLongWordEnd,
+    GoToNextParagraph,
+    GoToPrevParagraph,
+    FindNextChar,
+    FindPrevChar,
+    TillNextChar,
+    TillPrevChar,
+    RepeatFind,
+    ReverseFind,
+    SearchForward,
+    SearchBackward,
+    SearchNext,
+    SearchPrev,
+    SearchWordForward,
+    SearchWordBackward,
+    PutAfter,
+    PutBefore,
+    NextDocument,
+    PrevDocument,
+    AlternateDocument,
+    DocumentPicker,
+    SplitWindow,
+    VSplitWindow,
+    CloseWindow,
+    OnlyWindow,
+    NextWindow,
+    FocusLeftWindow,
+    FocusDownWindow,
+    FocusUpWindow,
+    FocusRightWindow,
+    IncreaseHeight,
+    DecreaseHeight,
+    IncreaseWidth,
+    DecreaseWidth,
+    EqualizeWindows,
+    NextTab,
+    PrevTab,
+    CloseTab,
+    OnlyTab,
     Undo,
     Redo,
+    Save,
+    ForceSave,
+    Quit,
+    ForceQuit,
+    SaveQuit,
+    CommandMode,
+    VisualMode,
+    VisualCommandMode,
+    VisualLineMode,
+    VisualBlockMode,
+    NormalMode,
+    VisualDelete,
+    VisualChange,
+    VisualYank,
+    VisualIndent,
+    VisualDedent,
+    VisualBlockInsert,
+    VisualBlockAppend,
+    /// Waits for a motion, applied to the text it covers.
+    Operator(Operator),
+    /// Target of a pending operator, or extends the selection to it in visual mode.
+    TextObject(TextObject, Scope),
 }
 
 pub struct Command {
@@ -50,8 +180,12 @@ impl Command {
         Self { type_, callback }
     }
 
-    pub fn call(&self, content: &mut Workspace) {
-        (self.callback)(content);
+    pub const fn type_(&self) -> CmdType {
+        self.type_
+    }
+
+    pub fn call(&self, content: &mut Workspace, ctx: Context) {
+        (self.callback)(content, ctx);
     }
 }
 
@@ -76,8 +210,73 @@ impl CommandRegistry {
             Command::new(CmdType::GoToBottomLine, go_to_bottom_line),
             Command::new(CmdType::GoToLineEnd, go_to_line_end),
             Command::new(CmdType::GoToLineStart, go_to_line_start),
+            Command::new(CmdType::MatchBracket, match_bracket),
+            Command::new(CmdType::MoveNextWordStart, move_next_word_start),
+            Command::new(CmdType::MovePrevWordStart, move_prev_word_start),
+            Command::new(CmdType::MoveNextWordEnd, move_next_word_end),
+            Command::new(CmdType::MovePrevWordEnd, move_prev_word_end),
+            Command::new(CmdType::MoveNextLongWordStart, move_next_long_word_start),
+            Command::new(CmdType::MovePrevLongWordStart, move_prev_long_word_start),
+            Command::new(CmdType::MoveNextLongWordEnd, move_next_long_word_end),
+            Command::new(CmdType::MovePrevLongWordEnd, move_prev_long_word_end),
+            Command::new(CmdType::GoToNextParagraph, go_to_next_paragraph),
+            Command::new(CmdType::GoToPrevParagraph, go_to_prev_paragraph),
+            Command::new(CmdType::FindNextChar, find_next_char),
+            Command::new(CmdType::FindPrevChar, find_prev_char),
+            Command::new(CmdType::TillNextChar, till_next_char),
+            Command::new(CmdType::TillPrevChar, till_prev_char),
+            Command::new(CmdType::RepeatFind, repeat_find),
+            Command::new(CmdType::ReverseFind, reverse_find),
+            Command::new(CmdType::SearchForward, search_forward),
+            Command::new(CmdType::SearchBackward, search_backward),
+            Command::new(CmdType::SearchNext, search_next),
+            Command::new(CmdType::SearchPrev, search_prev),
+            Command::new(CmdType::SearchWordForward, search_word_forward),
+            Command::new(CmdType::SearchWordBackward, search_word_backward),
+            Command::new(CmdType::PutAfter, put_after),
+            Command::new(CmdType::PutBefore, put_before),
+            Command::new(CmdType::NextDocument, next_document),
+            Command::new(CmdType::PrevDocument, prev_document),
+            Command::new(CmdType::AlternateDocument, alternate_document),
+            Command::new(CmdType::DocumentPicker, document_picker),
+            Command::new(CmdType::SplitWindow, split_window),
+            Command::new(CmdType::VSplitWindow, vsplit_window),
+            Command::new(CmdType::CloseWindow, close_window),
+            Command::new(CmdType::OnlyWindow, only_window),
+            Command::new(CmdType::NextWindow, next_window),
+            Command::new(CmdType::FocusLeftWindow, focus_left_window),
+            Command::new(CmdType::FocusDownWindow, focus_down_window),
+            Command::new(CmdType::FocusUpWindow, focus_up_window),
+            Command::new(CmdType::FocusRightWindow, focus_right_window),
+            Command::new(CmdType::IncreaseHeight, increase_height),
+            Command::new(CmdType::DecreaseHeight, decrease_height),
+            Command::new(CmdType::IncreaseWidth, increase_width),
+            Command::new(CmdType::DecreaseWidth, decrease_width),
+            Command::new(CmdType::EqualizeWindows, equalize_windows),
+            Command::new(CmdType::NextTab, next_tab),
+            Command::new(CmdType::PrevTab, prev_tab),
+            Command::new(CmdType::CloseTab, close_tab),
+            Command::new(CmdType::OnlyTab, only_tab),
             Command::new(CmdType::Undo, undo),
             Command::new(CmdType::Redo, redo),
+            Command::new(CmdType::Save, save),
+            Command::new(CmdType::ForceSave, force_save),
+            Command::new(CmdType::Quit, quit),
+            Command::new(CmdType::ForceQuit, force_quit),
+            Command::new(CmdType::SaveQuit, save_quit),
+            Command::new(CmdType::CommandMode, command_line_mode),
+            Command::new(CmdType::VisualMode, visual_mode),
+            Command::new(CmdType::VisualCommandMode, visual_command_mode),
+            Command::new(CmdType::VisualLineMode, visual_line_mode),
+            Command::new(CmdType::VisualBlockMode, visual_block_mode),
+            Command::new(CmdType::NormalMode, normal_mode),
+            Command::new(CmdType::VisualDelete, visual_delete),
+            Command::new(CmdType::VisualChange, visual_change),
+            Command::new(CmdType::VisualYank, visual_yank),
+            Command::new(CmdType::VisualIndent, visual_indent),
+            Command::new(CmdType::VisualDedent, visual_dedent),
+            Command::new(CmdType::VisualBlockInsert, visual_block_insert),
+            Command::new(CmdType::VisualBlockAppend, visual_block_append),
         ];
 
         let mut map = HashMap::new();
@@ -99,24 +298,106 @@ impl Default for CommandRegistry {
     }
 }
 
+/// What a key sequence resolved to.
+pub enum Action {
+    Command(Arc<Command>, Context),
+    Operate(Operator, Motion, Context),
+    Select(TextObject, Scope, Context),
+}
+
+impl Action {
+    pub fn call(&self, ws: &mut Workspace) {
+        match self {
+            Self::Command(command, ctx) => command.call(ws, *ctx),
+            Self::Operate(operator, motion, ctx) => {
+                operator::operate(ws, *operator, motion, *ctx);
+            }
+            Self::Select(object, scope, ctx) => text_object::select(ws, *object, *scope, *ctx),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct CommandFinder {
     registry: CommandRegistry,
     current: Option<&'static Keymap>,
+    pending: Vec<Input>,
+    /// Operator waiting for a motion.
+    operator: Option<Operator>,
+    /// Count typed before the command, or before the motion of a pending operator.
+    count: Option<usize>,
+    /// Count typed before a pending operator.
+    operator_count: Option<usize>,
+    /// Command waiting for a char argument.
+    awaiting: Option<CmdType>,
+    ch: Option<char>,
+    /// Register picked with `"`, `Some(None)` while waiting for its name.
+    register: Option<Option<char>>,
 }
 
 impl CommandFinder {
     pub fn reset(&mut self) {
         self.current = None;
+        self.pending.clear();
+        self.operator = None;
+        self.count = None;
+        self.operator_count = None;
+        self.awaiting = None;
+        self.ch = None;
+        self.register = None;
+    }
+
+    /// Inputs of a key chord that is started but not yet resolved to a command.
+    pub fn pending(&self) -> &[Input] {
+        &self.pending
+    }
+
+    pub const fn registry(&self) -> &CommandRegistry {
+        &self.registry
     }
 
     pub fn find(
         &mut self,
         keymaps: &'static Keymaps,
         buffer: &Buffer,
-        input: Input,
-    ) -> Option<Arc<Command>> {
-        let bindings = keymaps.get(&buffer.mode())?;
+        input: &Input,
+    ) -> Option<Action> {
+        if let Some(command) = self.awaiting.take() {
+            return match input.event {
+                Event::Char(ch) => {
+                    self.ch = Some(ch);
+                    self.resolve(command, input)
+                }
+                _ => {
+                    self.reset();
+                    None
+                }
+            };
+        }
+
+        let bindings = match self.operator {
+            Some(_) => keymaps.operator_pending(),
+            None => keymaps.get(&buffer.mode())?,
+        };
+
+        if self.register == Some(None) {
+            return match input.event {
+                Event::Char(name) if Registers::is_valid(name) => {
+                    self.register = Some(Some(name));
+                    self.pending.push(input.clone());
+                    None
+                }
+                _ => {
+                    self.reset();
+                    None
+                }
+            };
+        }
+
+        if self.current.is_none() && (self.push_count(input) || self.push_register(input)) {
+            self.pending.push(input.clone());
+            return None;
+        }
 
         self.current = match self.current {
             Some(node) => match node {
@@ -126,10 +407,120 @@ impl CommandFinder {
             None => bindings.get(input),
         };
 
-        if let Some(Keymap::Leaf(command)) = self.current {
-            return self.registry.get(command);
+        match self.current {
+            Some(Keymap::Leaf(command)) => return self.resolve(*command, input),
+            Some(Keymap::Node(_)) => self.pending.push(input.clone()),
+            None => self.reset(),
         }
 
         None
     }
+
+    /// Adds a digit of a count prefix, `0` only continues a count
+    /// since on its own it can be bound to a command.
+    fn push_count(&mut self, input: &Input) -> bool {
+        let Input {
+            event: Event::Char(ch),
+            modifiers,
+        } = *input
+        else {
+            return false;
+        };
+
+        let digit = match ch.to_digit(10) {
+            Some(0) if self.count.is_none() => return false,
+            Some(digit) if modifiers == Modifiers::default() => digit as usize,
+            _ => return false,
+        };
+
+        let count = self.count.unwrap_or(0);
+        self.count = Some(count.saturating_mul(10).saturating_add(digit));
+
+        true
+    }
+
+    /// Starts picking a register with `"`, only before an operator.
+    fn push_register(&mut self, input: &Input) -> bool {
+        let picks = input.event == Event::Char('"')
+            && input.modifiers == Modifiers::default()
+            && self.operator.is_none()
+            && self.register.is_none();
+
+        if picks {
+            self.register = Some(None);
+        }
+
+        picks
+    }
+
+    /// Count for the resolved command, the counts typed before an operator
+    /// and before its motion multiply like in `2d3w`.
+    fn context(&self) -> Context {
+        let count = match (self.operator_count, self.count) {
+            (Some(a), Some(b)) => Some(a.saturating_mul(b)),
+            (a, b) => a.or(b),
+        };
+
+        Context {
+            count,
+            ch: self.ch,
+            register: self.register.flatten(),
+        }
+    }
+
+    /// Turns a bound `command` into an action, entering or leaving operator-pending state.
+    fn resolve(&mut self, command: CmdType, input: &Input) -> Option<Action> {
+        if takes_char(command) && self.ch.is_none() {
+            self.awaiting = Some(command);
+            self.current = None;
+            self.pending.push(input.clone());
+            return None;
+        }
+
+        let ctx = self.context();
+
+        match (self.operator, command) {
+            (None, CmdType::Operator(operator)) => {
+                self.operator = Some(operator);
+                self.operator_count = self.count.take();
+                self.current = None;
+                self.pending.push(input.clone());
+                None
+            }
+            (None, CmdType::TextObject(object, scope)) => Some(Action::Select(object, scope, ctx)),
+            (None, command) => self
+                .registry
+                .get(&command)
+                .map(|command| Action::Command(command, ctx)),
+            (Some(operator), CmdType::Operator(other)) if operator == other => {
+                Some(Action::Operate(operator, Motion::Line, ctx))
+            }
+            (Some(operator), CmdType::TextObject(object, scope)) => Some(Action::Operate(
+                operator,
+                Motion::Object(object, scope),
+                ctx,
+            )),
+            (Some(operator), command) => {
+                // like vim, `cw` changes to the end of the word, keeping the blanks after it
+                let command = match (operator, command) {
+                    (Operator::Change, CmdType::MoveNextWordStart) => CmdType::MoveNextWordEnd,
+                    (Operator::Change, CmdType::MoveNextLongWordStart) => {
+                        CmdType::MoveNextLongWordEnd
+                    }
+                    _ => command,
+                };
+                let motion = motion_kind(command).zip(self.registry.get(&command));
+
+                match motion {
+                    Some((kind, command)) => {
+                        Some(Action::Operate(operator, Motion::Move(command, kind), ctx))
+                    }
+                    None => {
+                        self.reset();
+                        None
+                    }
+                }
+            }
+        }
+    }
 }
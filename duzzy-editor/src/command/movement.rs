@@ -1,80 +1,453 @@
-use crate::{buffer::Buffer, editor::Workspace};
+use crate::{
+    buffer::Buffer,
+    editor::Workspace,
+    sql,
+    word::{WordMotion, Words},
+};
+
+use super::{operator::MotionKind, CmdType, Context};
 
 enum Shift {
     Up(usize),
     Down(usize),
-    Left,
-    Right,
+    Left(usize),
+    Right(usize),
     Top,
     Bottom,
     LineStart,
     LineEnd,
 }
 
-pub(super) fn move_left(ws: &mut Workspace) {
-    shift_cursor(ws, Shift::Left);
+/// How an operator covers the text up to where `command` moves the cursor,
+/// `None` for commands that are not motions.
+pub(super) const fn motion_kind(command: CmdType) -> Option<MotionKind> {
+    let kind = match command {
+        CmdType::MoveLeft
+        | CmdType::MoveRight
+        | CmdType::GoToLineStart
+        | CmdType::MoveNextWordStart
+        | CmdType::MovePrevWordStart
+        | CmdType::MoveNextLongWordStart
+        | CmdType::MovePrevLongWordStart
+        | CmdType::GoToNextParagraph
+        | CmdType::GoToPrevParagraph
+        | CmdType::SearchNext
+        | CmdType::SearchPrev
+        | CmdType::SearchWordForward
+        | CmdType::SearchWordBackward => MotionKind::Exclusive,
+        CmdType::GoToLineEnd
+        | CmdType::MatchBracket
+        | CmdType::MoveNextWordEnd
+        | CmdType::MovePrevWordEnd
+        | CmdType::MoveNextLongWordEnd
+        | CmdType::MovePrevLongWordEnd => MotionKind::Inclusive,
+        CmdType::FindNextChar
+        | CmdType::FindPrevChar
+        | CmdType::TillNextChar
+        | CmdType::TillPrevChar
+        | CmdType::RepeatFind
+        | CmdType::ReverseFind => MotionKind::InclusiveForward,
+        CmdType::MoveUp | CmdType::MoveDown | CmdType::GoToTopLine | CmdType::GoToBottomLine => {
+            MotionKind::Linewise
+        }
+        _ => return None,
+    };
+
+    Some(kind)
+}
+
+/// Whether `command` waits for a char typed after it, like `f`.
+pub(super) const fn takes_char(command: CmdType) -> bool {
+    matches!(
+        command,
+        CmdType::FindNextChar
+            | CmdType::FindPrevChar
+            | CmdType::TillNextChar
+            | CmdType::TillPrevChar
+    )
 }
 
-pub(super) fn move_down(ws: &mut Workspace) {
-    shift_cursor(ws, Shift::Down(1));
+pub(super) fn move_left(ws: &mut Workspace, ctx: Context) {
+    shift_cursor(ws, Shift::Left(ctx.count()));
 }
 
-pub(super) fn move_up(ws: &mut Workspace) {
-    shift_cursor(ws, Shift::Up(1));
+pub(super) fn move_down(ws: &mut Workspace, ctx: Context) {
+    shift_cursor(ws, Shift::Down(ctx.count()));
 }
 
-pub(super) fn move_right(ws: &mut Workspace) {
-    shift_cursor(ws, Shift::Right);
+pub(super) fn move_up(ws: &mut Workspace, ctx: Context) {
+    shift_cursor(ws, Shift::Up(ctx.count()));
 }
 
-pub(super) fn go_to_top_line(ws: &mut Workspace) {
-    shift_cursor(ws, Shift::Top);
+pub(super) fn move_right(ws: &mut Workspace, ctx: Context) {
+    shift_cursor(ws, Shift::Right(ctx.count()));
 }
 
-pub(super) fn go_to_bottom_line(ws: &mut Workspace) {
-    shift_cursor(ws, Shift::Bottom);
+/// Goes to the first line, or to line `count` when one is typed.
+pub(super) fn go_to_top_line(ws: &mut Workspace, ctx: Context) {
+    match ctx.raw_count() {
+        Some(line) => go_to_line(ws, line),
+        None => shift_cursor(ws, Shift::Top),
+    }
+}
+
+/// Goes to the last line, or to line `count` when one is typed.
+pub(super) fn go_to_bottom_line(ws: &mut Workspace, ctx: Context) {
+    match ctx.raw_count() {
+        Some(line) => go_to_line(ws, line),
+        None => shift_cursor(ws, Shift::Bottom),
+    }
 }
 
-pub(super) fn go_to_line_end(ws: &mut Workspace) {
+pub(super) fn go_to_line_end(ws: &mut Workspace, _ctx: Context) {
     shift_cursor(ws, Shift::LineEnd);
 }
 
-pub(super) fn go_to_line_start(ws: &mut Workspace) {
+pub(super) fn go_to_line_start(ws: &mut Workspace, _ctx: Context) {
     shift_cursor(ws, Shift::LineStart);
 }
 
+pub(super) fn move_next_word_start(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::NextStart, false, ctx.count());
+}
+
+pub(super) fn move_prev_word_start(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::PrevStart, false, ctx.count());
+}
+
+pub(super) fn move_next_word_end(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::NextEnd, false, ctx.count());
+}
+
+pub(super) fn move_prev_word_end(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::PrevEnd, false, ctx.count());
+}
+
+pub(super) fn move_next_long_word_start(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::NextStart, true, ctx.count());
+}
+
+pub(super) fn move_prev_long_word_start(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::PrevStart, true, ctx.count());
+}
+
+pub(super) fn move_next_long_word_end(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::NextEnd, true, ctx.count());
+}
+
+pub(super) fn move_prev_long_word_end(ws: &mut Workspace, ctx: Context) {
+    shift_word(ws, WordMotion::PrevEnd, true, ctx.count());
+}
+
+pub(super) fn go_to_next_paragraph(ws: &mut Workspace, ctx: Context) {
+    let buf = ws.curr_mut().buf_mut();
+    let pos = next_paragraph(ctx.count(), buf);
+    buf.set_pos(pos);
+}
+
+pub(super) fn go_to_prev_paragraph(ws: &mut Workspace, ctx: Context) {
+    let buf = ws.curr_mut().buf_mut();
+    let pos = prev_paragraph(ctx.count(), buf);
+    buf.set_pos(pos);
+}
+
+pub(super) fn find_next_char(ws: &mut Workspace, ctx: Context) {
+    find_char(ws, ctx, true, false);
+}
+
+pub(super) fn find_prev_char(ws: &mut Workspace, ctx: Context) {
+    find_char(ws, ctx, false, false);
+}
+
+pub(super) fn till_next_char(ws: &mut Workspace, ctx: Context) {
+    find_char(ws, ctx, true, true);
+}
+
+pub(super) fn till_prev_char(ws: &mut Workspace, ctx: Context) {
+    find_char(ws, ctx, false, true);
+}
+
+/// Repeats the last char find.
+pub(super) fn repeat_find(ws: &mut Workspace, ctx: Context) {
+    if let Some(find) = ws.char_find() {
+        shift_find(ws, find, ctx.count(), true);
+    }
+}
+
+/// Repeats the last char find in the opposite direction.
+pub(super) fn reverse_find(ws: &mut Workspace, ctx: Context) {
+    if let Some(find) = ws.char_find() {
+        let find = CharFind {
+            forward: !find.forward,
+            ..find
+        };
+        shift_find(ws, find, ctx.count(), true);
+    }
+}
+
+/// Jumps to the bracket matching the one under the cursor, or the first one
+/// after it in the line.
+pub(super) fn match_bracket(ws: &mut Workspace, _ctx: Context) {
+    let buf = ws.curr_mut().buf_mut();
+    let text = buf.text().slice(..);
+
+    let pos = buf.as_char_pos();
+    let line_end = buf.line_char(buf.index()) + buf.line_len(buf.index());
+
+    let target = (pos..line_end)
+        .filter(|idx| sql::is_bracket(text.char(*idx)))
+        .find_map(|idx| sql::matching_bracket(text, buf.literals(), idx, 0..text.len_chars()));
+
+    if let Some(target) = target {
+        buf.set_pos(buf.as_curs_pos(target));
+    }
+}
+
+pub(super) fn go_to_line(ws: &mut Workspace, line: usize) {
+    let buf = ws.curr_mut().buf_mut();
+    let idx = line.saturating_sub(1).min(buf.len_lines() - 1);
+
+    buf.set_pos((idx, 0));
+}
+
 fn shift_cursor(ws: &mut Workspace, shift: Shift) {
+    let options = ws.options();
+    let rows_width = (options.wrap && options.visual_rows).then_some(ws.text_width());
+    let tab_width = options.tab_width;
+
     let buf = ws.curr_mut().buf_mut();
-    let idx = buf.index();
+    let (idx, ofs) = buf.pos();
+    let col = buf.col_of(idx, ofs, tab_width);
 
     let pos = match shift {
-        Shift::Up(n) => shift_up(n, buf),
-        Shift::Down(n) => shift_down(n, buf),
-        Shift::Left => shift_left(buf),
-        Shift::Right => shift_right(buf),
+        Shift::Up(n) => match rows_width {
+            Some(width) => shift_rows_up(n, buf, width, tab_width),
+            None => at_col(buf, shift_up(n, buf).0, col, tab_width),
+        },
+        Shift::Down(n) => match rows_width {
+            Some(width) => shift_rows_down(n, buf, width, tab_width),
+            None => at_col(buf, shift_down(n, buf).0, col, tab_width),
+        },
+        Shift::Left(n) => repeat(n, buf, shift_left),
+        Shift::Right(n) => repeat(n, buf, shift_right),
         Shift::Top => (0, 0),
         Shift::Bottom => (buf.len_lines() - 1, 0),
         Shift::LineStart => (idx, 0),
-        Shift::LineEnd => (idx, buf.len_bytes(idx).saturating_sub(1)),
+        Shift::LineEnd => (idx, buf.line_last(idx)),
     };
 
     buf.set_pos(pos);
 }
 
-pub(super) fn shift_up(n: usize, buf: &mut Buffer) -> (usize, usize) {
+/// Search for a char in the cursor line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharFind {
+    pub ch: char,
+    pub forward: bool,
+    /// Stop next to the char instead of on it.
+    pub till: bool,
+}
+
+fn find_char(ws: &mut Workspace, ctx: Context, forward: bool, till: bool) {
+    let Some(ch) = ctx.ch() else {
+        return;
+    };
+
+    let find = CharFind { ch, forward, till };
+    ws.set_char_find(find);
+    shift_find(ws, find, ctx.count(), false);
+}
+
+fn shift_find(ws: &mut Workspace, find: CharFind, count: usize, repeat: bool) {
+    let buf = ws.curr_mut().buf_mut();
+
+    if let Some(pos) = find_in_line(buf, find, count, repeat) {
+        buf.set_pos(pos);
+    }
+}
+
+/// Position of the `count`th `find.ch` in the cursor line, `None` when there are fewer.
+/// A `repeat`ed till skips the char right next to the cursor, so `;` doesn't get stuck.
+fn find_in_line(
+    buf: &Buffer,
+    find: CharFind,
+    count: usize,
+    repeat: bool,
+) -> Option<(usize, usize)> {
+    let (idx, ofs) = buf.pos();
+    let graphemes: Vec<_> = buf
+        .graphemes(idx, (0, 0), 1)
+        .map(|g| (g.offset, g.slice.char(0)))
+        .collect();
+    let at = graphemes.iter().position(|(offset, _)| *offset == ofs)?;
+
+    let target = match find.forward {
+        true => graphemes
+            .iter()
+            .enumerate()
+            .skip(at + 1)
+            .filter(|(i, (_, ch))| *ch == find.ch && !(repeat && find.till && *i == at + 1))
+            .nth(count - 1)
+            .map(|(i, _)| if find.till { i - 1 } else { i }),
+        false => graphemes
+            .iter()
+            .enumerate()
+            .take(at)
+            .rev()
+            .filter(|(i, (_, ch))| *ch == find.ch && !(repeat && find.till && i + 1 == at))
+            .nth(count - 1)
+            .map(|(i, _)| if find.till { i + 1 } else { i }),
+    }?;
+
+    Some((idx, graphemes[target].0))
+}
+
+/// Moves `count` words, `big` ones for the `WORD` motions.
+fn shift_word(ws: &mut Workspace, motion: WordMotion, big: bool, count: usize) {
+    let word_chars = ws.options().word_chars.clone();
+    let buf = ws.curr_mut().buf_mut();
+
+    let pos = Words::new(&word_chars, big).find(buf, buf.as_char_pos(), motion, count);
+    buf.set_pos(buf.as_curs_pos(pos));
+}
+
+/// Empty line after the `n`th paragraph below the cursor, or the end of the last line.
+fn next_paragraph(n: usize, buf: &Buffer) -> (usize, usize) {
+    let last = buf.len_lines() - 1;
+    let is_empty = |idx| buf.line_len(idx) == 0;
+    let mut idx = buf.index();
+
+    for _ in 0..n {
+        while idx < last && is_empty(idx) {
+            idx += 1;
+        }
+        while idx < last && !is_empty(idx) {
+            idx += 1;
+        }
+    }
+
+    match is_empty(idx) {
+        true => (idx, 0),
+        false => (idx, buf.line_last(idx)),
+    }
+}
+
+/// Empty line before the `n`th paragraph above the cursor, or the first line.
+fn prev_paragraph(n: usize, buf: &Buffer) -> (usize, usize) {
+    let is_empty = |idx| buf.line_len(idx) == 0;
+    let mut idx = buf.index();
+
+    for _ in 0..n {
+        while idx > 0 && is_empty(idx) {
+            idx -= 1;
+        }
+        while idx > 0 && !is_empty(idx) {
+            idx -= 1;
+        }
+    }
+
+    (idx, 0)
+}
+
+/// Position after `n` steps of `shift`, stopping early where it no longer moves.
+fn repeat(n: usize, buf: &mut Buffer, shift: fn(&mut Buffer) -> (usize, usize)) -> (usize, usize) {
+    let origin = buf.pos();
+
+    for _ in 0..n {
+        let pos = shift(buf);
+        if pos == buf.pos() {
+            break;
+        }
+        buf.set_pos(pos);
+    }
+
+    let pos = buf.pos();
+    buf.set_pos(origin);
+
+    pos
+}
+
+/// Position in line `idx` at display column `col`.
+fn at_col(buf: &Buffer, idx: usize, col: usize, tab_width: usize) -> (usize, usize) {
+    let ofs = buf.offset_at_col(idx, col, tab_width);
+    (idx, buf.clamp_offset(idx, ofs))
+}
+
+/// Position at column `col` of screen row `row` in line `idx`, kept within that row.
+fn at_row_col(
+    buf: &Buffer,
+    idx: usize,
+    row: usize,
+    col: usize,
+    (width, tab_width): (usize, usize),
+) -> (usize, usize) {
+    let rows = buf.row_starts(idx, width, tab_width);
+    // the row after the last one only holds the insert cursor at the end of the line
+    let Some(&(_, row_col)) = rows.get(row) else {
+        return (idx, buf.clamp_offset(idx, buf.line_len(idx)));
+    };
+    let mut ofs = buf.offset_at_col(idx, row_col + col, tab_width);
+
+    if let Some(&(next, _)) = rows.get(row + 1) {
+        if ofs >= next {
+            let start = buf.line_char(idx);
+            ofs = buf.prev_grapheme(start + next) - start;
+        }
+    }
+
+    (idx, buf.clamp_offset(idx, ofs))
+}
+
+fn shift_rows_up(n: usize, buf: &Buffer, width: usize, tab_width: usize) -> (usize, usize) {
+    let (mut idx, _) = buf.pos();
+    let (mut row, col) = buf.wrapped_pos(width, tab_width);
+
+    for _ in 0..n {
+        if row > 0 {
+            row -= 1;
+        } else if idx > 0 {
+            idx -= 1;
+            row = buf.line_rows(idx, width, tab_width) - 1;
+        } else {
+            break;
+        }
+    }
+
+    at_row_col(buf, idx, row, col, (width, tab_width))
+}
+
+fn shift_rows_down(n: usize, buf: &Buffer, width: usize, tab_width: usize) -> (usize, usize) {
+    let (mut idx, _) = buf.pos();
+    let (mut row, col) = buf.wrapped_pos(width, tab_width);
+
+    for _ in 0..n {
+        if row + 1 < buf.line_rows(idx, width, tab_width) {
+            row += 1;
+        } else if idx + 1 < buf.len_lines() {
+            idx += 1;
+            row = 0;
+        } else {
+            break;
+        }
+    }
+
+    at_row_col(buf, idx, row, col, (width, tab_width))
+}
+
+pub(super) fn shift_up(n: usize, buf: &Buffer) -> (usize, usize) {
     let (idx, ofs) = buf.pos();
 
     let idx = idx.saturating_sub(n);
-    let ofs = ofs.min(buf.len_bytes(idx).saturating_sub(1));
+    let ofs = buf.clamp_offset(idx, ofs);
 
     (idx, ofs)
 }
 
-pub(super) fn shift_down(n: usize, buf: &mut Buffer) -> (usize, usize) {
+pub(super) fn shift_down(n: usize, buf: &Buffer) -> (usize, usize) {
     let (idx, ofs) = buf.pos();
 
-    let idx = (idx + n).min(buf.len_lines() - 1);
-    let ofs = ofs.min(buf.len_bytes(idx).saturating_sub(1));
+    let idx = idx.saturating_add(n).min(buf.len_lines() - 1);
+    let ofs = buf.clamp_offset(idx, ofs);
 
     (idx, ofs)
 }
@@ -83,22 +456,23 @@ pub(super) fn shift_left(buf: &mut Buffer) -> (usize, usize) {
     let (idx, ofs) = buf.pos();
 
     match (ofs > 0, idx > 0) {
-        (true, _) => (idx, ofs - 1),
-        (false, true) => (idx - 1, buf.len_bytes(idx - 1).saturating_sub(1)),
+        (true, _) => buf.as_curs_pos(buf.prev_grapheme(buf.as_char_pos())),
+        (false, true) => (idx - 1, buf.line_last(idx - 1)),
         _ => (idx, ofs),
     }
 }
 
 pub(super) fn shift_right(buf: &mut Buffer) -> (usize, usize) {
     let (idx, ofs) = buf.pos();
+    let next = buf.next_grapheme(buf.as_char_pos()) - buf.line_char(idx);
 
     match (
-        ofs < buf.len_bytes(idx).saturating_sub(1),
+        ofs < buf.line_last(idx),
         idx < buf.len_lines().saturating_sub(1),
     ) {
-        (true, _) => (idx, ofs + 1),
+        (true, _) => (idx, next),
         (false, true) => ((idx + 1).min(buf.len_lines() - 1), 0),
-        (false, false) => (idx, (ofs + 1).min(buf.len_bytes(idx))),
+        (false, false) => (idx, next),
     }
 }
 
@@ -127,7 +501,7 @@ mod tests {
         shift_cursor(&mut ws, Shift::Top);
         assert_eq!((0, 0), ws.curr().buf().pos());
 
-        shift_cursor(&mut ws, Shift::Right);
+        shift_cursor(&mut ws, Shift::Right(1));
         assert_eq!((0, 1), ws.curr().buf().pos());
 
         shift_cursor(&mut ws, Shift::Down(1));
@@ -136,19 +510,117 @@ mod tests {
         shift_cursor(&mut ws, Shift::LineEnd);
         assert_eq!((1, 0), ws.curr().buf().pos());
 
-        shift_cursor(&mut ws, Shift::Left);
+        shift_cursor(&mut ws, Shift::Left(1));
         assert_eq!((0, 4), ws.curr().buf().pos());
 
-        shift_cursor(&mut ws, Shift::Right);
+        shift_cursor(&mut ws, Shift::Right(1));
         assert_eq!((1, 0), ws.curr().buf().pos());
 
-        shift_cursor(&mut ws, Shift::Right);
+        shift_cursor(&mut ws, Shift::Right(1));
         assert_eq!((2, 0), ws.curr().buf().pos());
 
         shift_cursor(&mut ws, Shift::LineEnd);
         assert_eq!((2, 3), ws.curr().buf().pos());
 
-        shift_cursor(&mut ws, Shift::Right);
+        shift_cursor(&mut ws, Shift::Right(1));
         assert_eq!((2, 4), ws.curr().buf().pos());
     }
+
+    #[test]
+    fn test_paragraphs() {
+        let mut buf = Buffer::default();
+        buf.text_mut()
+            .insert(0, "select\nfrom\n\n\nwhere\n\nlimit 1");
+
+        assert_eq!((2, 0), next_paragraph(1, &buf));
+        assert_eq!((5, 0), next_paragraph(2, &buf));
+        assert_eq!((6, 6), next_paragraph(3, &buf));
+
+        buf.set_pos((3, 0));
+        assert_eq!((5, 0), next_paragraph(1, &buf));
+        assert_eq!((0, 0), prev_paragraph(1, &buf));
+
+        buf.set_pos((6, 3));
+        assert_eq!((5, 0), prev_paragraph(1, &buf));
+        assert_eq!((3, 0), prev_paragraph(2, &buf));
+    }
+
+    #[test]
+    fn test_find_in_line() {
+        let mut buf = Buffer::default();
+        buf.text_mut().insert(0, "select a, 👍, c, d\nfrom t");
+        buf.set_pos((0, 7));
+
+        let find = |ch, forward, till| CharFind { ch, forward, till };
+
+        assert_eq!(
+            Some((0, 8)),
+            find_in_line(&buf, find(',', true, false), 1, false)
+        );
+        assert_eq!(
+            Some((0, 14)),
+            find_in_line(&buf, find(',', true, false), 3, false)
+        );
+        assert_eq!(None, find_in_line(&buf, find(',', true, false), 4, false));
+        assert_eq!(
+            Some((0, 10)),
+            find_in_line(&buf, find(',', true, true), 2, false)
+        );
+        assert_eq!(
+            Some((0, 4)),
+            find_in_line(&buf, find('e', false, true), 1, false)
+        );
+        assert_eq!(None, find_in_line(&buf, find('f', true, false), 1, false));
+
+        buf.set_pos((0, 10));
+        assert_eq!(
+            Some((0, 10)),
+            find_in_line(&buf, find(',', true, true), 1, false)
+        );
+        assert_eq!(
+            Some((0, 13)),
+            find_in_line(&buf, find(',', true, true), 1, true)
+        );
+        assert_eq!(
+            Some((0, 7)),
+            find_in_line(&buf, find(' ', false, true), 1, true)
+        );
+    }
+
+    #[test]
+    fn test_movement_unicode() {
+        let mut ws = Workspace::default();
+        ws.add_doc(Document::default());
+
+        {
+            let buf = ws.curr_mut().buf_mut();
+            buf.text_mut().insert(0, "я👍🏽e\u{301}x\nпривет");
+        }
+
+        shift_cursor(&mut ws, Shift::Right(1));
+        assert_eq!((0, 1), ws.curr().buf().pos());
+
+        shift_cursor(&mut ws, Shift::Right(1));
+        assert_eq!((0, 3), ws.curr().buf().pos());
+
+        shift_cursor(&mut ws, Shift::Right(1));
+        assert_eq!((0, 5), ws.curr().buf().pos());
+
+        shift_cursor(&mut ws, Shift::Left(1));
+        assert_eq!((0, 3), ws.curr().buf().pos());
+
+        shift_cursor(&mut ws, Shift::Down(1));
+        assert_eq!((1, 3), ws.curr().buf().pos());
+
+        shift_cursor(&mut ws, Shift::LineEnd);
+        assert_eq!((1, 5), ws.curr().buf().pos());
+
+        ws.curr_mut().buf_mut().set_pos((1, 4));
+        shift_cursor(&mut ws, Shift::Up(1));
+        assert_eq!((0, 5), ws.curr().buf().pos());
+
+        ws.curr_mut().buf_mut().set_pos((1, 2));
+        shift_cursor(&mut ws, Shift::Up(1));
+        assert_eq!((0, 1), ws.curr().buf().pos());
+    }
 }
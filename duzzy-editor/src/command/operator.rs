@@ -0,0 +1,431 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    buffer::{Buffer, CursorMode},
+    editor::{Message, Workspace},
+    register::Register,
+    transaction::TransactionResult,
+};
+
+use super::{
+    text_object::{self, Scope, TextObject},
+    CmdType, Command, Context,
+};
+
+/// Command applied to the text covered by a motion or a selection.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Dedent,
+    Lowercase,
+    Uppercase,
+}
+
+/// How the text between the cursor and the end of a motion is covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionKind {
+    /// Up to the motion end, without the grapheme under it.
+    Exclusive,
+    /// Up to the motion end, the grapheme under it included.
+    Inclusive,
+    /// Whole lines between the cursor and the motion end.
+    Linewise,
+    /// Inclusive moving forwards and exclusive moving backwards, like `f` and `F`.
+    InclusiveForward,
+}
+
+pub enum Motion {
+    /// The cursor line and the lines below it, for a doubled operator like `dd`.
+    Line,
+    Move(Arc<Command>, MotionKind),
+    Object(TextObject, Scope),
+}
+
+/// Char range an operator acts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub range: Range<usize>,
+    /// Covers whole lines, line breaks included.
+    pub linewise: bool,
+}
+
+impl Span {
+    /// Whole lines `first..=last`.
+    pub fn lines(buf: &Buffer, first: usize, last: usize) -> Self {
+        let end = buf.line_char(last) + buf.text().line(last).len_chars();
+
+        Self {
+            range: buf.line_char(first)..end,
+            linewise: true,
+        }
+    }
+}
+
+/// Applies `operator` to the text covered by `motion` from the cursor,
+/// repeated `ctx.count()` times.
+pub(super) fn operate(ws: &mut Workspace, operator: Operator, motion: &Motion, ctx: Context) {
+    let span = match motion {
+        Motion::Line => {
+            let buf = ws.curr().buf();
+            let last = (buf.index().saturating_add(ctx.count()) - 1).min(buf.len_lines() - 1);
+            Span::lines(buf, buf.index(), last)
+        }
+        Motion::Move(command, kind) => {
+            let origin = ws.curr().buf().pos();
+            command.call(ws, ctx);
+
+            let buf = ws.curr_mut().buf_mut();
+            let target = buf.as_char_pos();
+            buf.set_pos(origin);
+
+            // like vim, `dj` on the last line fails instead of acting on it,
+            // while `dG` there still takes the line it's on
+            let jumps = matches!(
+                command.type_(),
+                CmdType::GoToTopLine | CmdType::GoToBottomLine
+            );
+            if *kind == MotionKind::Linewise && !jumps && buf.pos() == buf.as_curs_pos(target) {
+                return;
+            }
+
+            motion_span(buf, target, *kind)
+        }
+        Motion::Object(object, scope) => {
+            let word_chars = &ws.options().word_chars;
+            let buf = ws.curr().buf();
+
+            match text_object::span(buf, *object, *scope, ctx.count(), word_chars) {
+                Some(span) => span,
+                None => return,
+            }
+        }
+    };
+
+    apply(ws, operator, span, ctx.register());
+}
+
+/// Span between the cursor and char index `target` of a motion of `kind`.
+fn motion_span(buf: &Buffer, target: usize, kind: MotionKind) -> Span {
+    let pos = buf.as_char_pos();
+    let (start, end) = (pos.min(target), pos.max(target));
+
+    match kind {
+        MotionKind::Exclusive => {
+            // ending at the start of a later line, like `dw` on the last word of
+            // a line, stops at the end of the line before
+            let text = buf.text();
+            let line = text.char_to_line(end);
+            let end = match line > text.char_to_line(start)
+                && end - buf.line_char(line) <= buf.first_non_blank(line)
+            {
+                true => buf.line_char(line - 1) + buf.line_len(line - 1),
+                false => end,
+            };
+
+            Span {
+                range: start..end,
+                linewise: false,
+            }
+        }
+        MotionKind::Inclusive => {
+            // an inclusive motion never takes the line break it ends on
+            let end = match buf.text().get_char(end) {
+                Some('\n' | '\r') | None => end,
+                Some(_) => buf.next_grapheme(end),
+            };
+
+            Span {
+                range: start..end,
+                linewise: false,
+            }
+        }
+        MotionKind::Linewise => {
+            let text = buf.text();
+            Span::lines(buf, text.char_to_line(start), text.char_to_line(end))
+        }
+        MotionKind::InclusiveForward => match target > pos {
+            true => motion_span(buf, target, MotionKind::Inclusive),
+            false => motion_span(buf, target, MotionKind::Exclusive),
+        },
+    }
+}
+
+/// Applies `operator` to `span`, leaving visual mode if it was on.
+/// Deleted and yanked text goes to register `name`.
+pub(super) fn apply(ws: &mut Workspace, operator: Operator, span: Span, name: Option<char>) {
+    match operator {
+        Operator::Delete => delete(ws, span, false, name),
+        Operator::Change => delete(ws, span, true, name),
+        Operator::Yank => yank(ws, &span, name),
+        Operator::Indent => shift_lines(ws, &span, true),
+        Operator::Dedent => shift_lines(ws, &span, false),
+        Operator::Lowercase => map_case(ws, &span, false),
+        Operator::Uppercase => map_case(ws, &span, true),
+    }
+}
+
+fn finish(buf: &mut Buffer, pos: (usize, usize)) {
+    buf.clear_selection();
+    buf.set_mode(CursorMode::Normal);
+
+    let (idx, ofs) = pos;
+    buf.set_pos((idx, buf.clamp_offset(idx, ofs)));
+}
+
+/// Text of `span`, whole lines always ending with a line break.
+fn span_register(buf: &Buffer, span: &Span) -> Register {
+    let mut text = buf.text().slice(span.range.clone()).to_string();
+    if span.linewise && !text.ends_with('\n') {
+        text.push('\n');
+    }
+
+    Register::new(text, span.linewise)
+}
+
+fn yank(ws: &mut Workspace, span: &Span, name: Option<char>) {
+    let buf = ws.curr_mut().buf_mut();
+    let register = span_register(buf, span);
+
+    finish(buf, buf.as_curs_pos(span.range.start));
+    if let Err(e) = ws.registers_mut().yank(name, register) {
+        ws.set_message(Message::Error(format!("{e:#}")));
+    }
+}
+
+fn delete(ws: &mut Workspace, span: Span, change: bool, name: Option<char>) {
+    let buf = ws.curr().buf();
+    let register = span_register(buf, &span);
+    let mut range = span.range;
+
+    if span.linewise {
+        let last = buf
+            .text()
+            .char_to_line(range.end.saturating_sub(1).max(range.start));
+
+        if change {
+            // keep an empty line to type into
+            range.end = buf.line_char(last) + buf.line_len(last);
+        } else if range.end == buf.len_chars() && range.start > 0 {
+            // the last line has no break of its own, take the preceding one
+            range.start = buf.prev_grapheme(range.start);
+        }
+    }
+
+    let text = buf.text().slice(range.clone()).to_string();
+    if let Err(e) = ws.registers_mut().delete(name, register) {
+        return ws.set_message(Message::Error(format!("{e:#}")));
+    }
+
+    ws.curr_mut().with_transaction(|tx, buf| {
+        tx.shift(range.start);
+        tx.delete_str(range.end, &text);
+        tx.apply(buf.text_mut());
+
+        let (idx, ofs) = buf.as_curs_pos(range.start);
+        let pos = match (span.linewise, change) {
+            (true, true) => (idx, 0),
+            (true, false) => (idx, buf.first_non_blank(idx)),
+            (false, _) => (idx, ofs),
+        };
+
+        if change {
+            buf.clear_selection();
+            buf.set_mode(CursorMode::Insert);
+            buf.set_pos(pos);
+            return TransactionResult::Keep;
+        }
+
+        finish(buf, pos);
+        TransactionResult::Commit
+    });
+}
+
+fn shift_lines(ws: &mut Workspace, span: &Span, right: bool) {
+    let tab_width = ws.options().tab_width;
+
+    ws.curr_mut().with_transaction(|tx, buf| {
+        let text = buf.text();
+        let first = text.char_to_line(span.range.start);
+        let last = text.char_to_line(span.range.end.saturating_sub(1).max(span.range.start));
+
+        tx.shift(buf.line_char(first));
+
+        for index in (first..=last).rev() {
+            let start = buf.line_char(index);
+
+            if right {
+                if buf.line_len(index) == 0 {
+                    continue;
+                }
+                tx.insert_char(start, '\t');
+            } else {
+                let len = indent_len(buf, index, tab_width);
+                if len == 0 {
+                    continue;
+                }
+
+                let indent = buf.text().slice(start..start + len).to_string();
+                tx.delete_str(start + len, &indent);
+            }
+
+            tx.shift(start);
+        }
+
+        tx.apply(buf.text_mut());
+        finish(buf, (first, buf.first_non_blank(first)));
+
+        TransactionResult::Commit
+    });
+}
+
+/// Length of one indentation level at the start of line `index`:
+/// a tab or up to `tab_width` spaces.
+fn indent_len(buf: &Buffer, index: usize, tab_width: usize) -> usize {
+    let mut chars = buf.text().line(index).chars();
+
+    match chars.next() {
+        Some('\t') => 1,
+        Some(' ') => {
+            1 + chars
+                .take(tab_width - 1)
+                .take_while(|ch| *ch == ' ')
+                .count()
+        }
+        _ => 0,
+    }
+}
+
+fn map_case(ws: &mut Workspace, span: &Span, upper: bool) {
+    ws.curr_mut().with_transaction(|tx, buf| {
+        let range = span.range.clone();
+        let text = buf.text().slice(range.clone()).to_string();
+
+        let mapped = match upper {
+            true => text.to_uppercase(),
+            false => text.to_lowercase(),
+        };
+
+        let pos = buf.as_curs_pos(range.start);
+
+        if mapped == text {
+            finish(buf, pos);
+            return TransactionResult::Abort;
+        }
+
+        tx.shift(range.start);
+        tx.delete_str(range.end, &text);
+        tx.insert_str(range.start, &mapped);
+        tx.shift(range.start);
+        tx.apply(buf.text_mut());
+
+        finish(buf, pos);
+        TransactionResult::Commit
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        command::CommandRegistry,
+        test_utils::{text, workspace},
+    };
+
+    use super::*;
+
+    fn span(ws: &Workspace, target: (usize, usize), kind: MotionKind) -> Span {
+        let buf = ws.curr().buf();
+        let target = buf.line_char(target.0) + target.1;
+        motion_span(buf, target, kind)
+    }
+
+    #[test]
+    fn test_motion_span() {
+        let mut ws = workspace("select a\nfrom t\nwhere");
+        ws.curr_mut().buf_mut().set_pos((1, 2));
+
+        assert_eq!(11..15, span(&ws, (1, 6), MotionKind::Inclusive).range);
+        assert_eq!(9..11, span(&ws, (1, 0), MotionKind::Exclusive).range);
+        assert_eq!(11..12, span(&ws, (1, 2), MotionKind::Inclusive).range);
+
+        let lines = span(&ws, (0, 3), MotionKind::Linewise);
+        assert_eq!(0..16, lines.range);
+        assert!(lines.linewise);
+    }
+
+    #[test]
+    fn test_delete_lines() {
+        let mut ws = workspace("select a\n  from t\nwhere");
+        ws.curr_mut().buf_mut().set_pos((2, 3));
+
+        let span = Span::lines(ws.curr().buf(), 1, 2);
+        apply(&mut ws, Operator::Delete, span, None);
+
+        assert_eq!("select a", text(&ws));
+        assert_eq!((0, 0), ws.curr().buf().pos());
+        assert_eq!(
+            Some(&Register::new("  from t\nwhere\n".into(), true)),
+            ws.registers().get('"')
+        );
+
+        ws.curr_mut().undo();
+        assert_eq!("select a\n  from t\nwhere", text(&ws));
+
+        let span = Span::lines(ws.curr().buf(), 0, 1);
+        apply(&mut ws, Operator::Delete, span, None);
+
+        assert_eq!("where", text(&ws));
+    }
+
+    #[test]
+    fn test_linewise_motion_fails() {
+        let registry = CommandRegistry::register();
+        let motion = |command| Motion::Move(registry.get(&command).unwrap(), MotionKind::Linewise);
+
+        let mut ws = workspace("select a\nfrom t");
+        ws.curr_mut().buf_mut().set_pos((1, 2));
+        operate(
+            &mut ws,
+            Operator::Delete,
+            &motion(CmdType::MoveDown),
+            Context::default(),
+        );
+        assert_eq!("select a\nfrom t", text(&ws));
+
+        ws.curr_mut().buf_mut().set_pos((0, 2));
+        operate(
+            &mut ws,
+            Operator::Delete,
+            &motion(CmdType::MoveUp),
+            Context::default(),
+        );
+        assert_eq!("select a\nfrom t", text(&ws));
+        assert_eq!(None, ws.registers().get('"'));
+
+        ws.curr_mut().buf_mut().set_pos((1, 2));
+        let bottom = motion(CmdType::GoToBottomLine);
+        operate(&mut ws, Operator::Delete, &bottom, Context::default());
+        assert_eq!("select a", text(&ws));
+    }
+
+    #[test]
+    fn test_map_case() {
+        let mut ws = workspace("select a\nfrom Таблица");
+        ws.curr_mut().buf_mut().set_pos((1, 5));
+
+        let span = span(&ws, (1, 11), MotionKind::Inclusive);
+        apply(&mut ws, Operator::Uppercase, span, None);
+
+        assert_eq!("select a\nfrom ТАБЛИЦА", text(&ws));
+        assert_eq!((1, 5), ws.curr().buf().pos());
+
+        let span = Span::lines(ws.curr().buf(), 0, 1);
+        apply(&mut ws, Operator::Lowercase, span, None);
+        assert_eq!("select a\nfrom таблица", text(&ws));
+
+        ws.curr_mut().undo();
+        assert_eq!("select a\nfrom ТАБЛИЦА", text(&ws));
+    }
+}
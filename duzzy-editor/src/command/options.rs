@@ -0,0 +1,13 @@
+use crate::editor::Workspace;
+
+pub(super) fn set(ws: &mut Workspace, args: &str) -> anyhow::Result<()> {
+    if args.is_empty() {
+        anyhow::bail!("Argument required");
+    }
+
+    for option in args.split_whitespace() {
+        ws.options_mut().set(option)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use crate::{
+    editor::{Message, Workspace},
+    transaction::TransactionResult,
+};
+
+use super::Context;
+
+/// Puts a register below the cursor line, or after the cursor for text within lines.
+pub(super) fn put_after(ws: &mut Workspace, ctx: Context) {
+    put(ws, ctx, true);
+}
+
+/// Puts a register above the cursor line, or before the cursor for text within lines.
+pub(super) fn put_before(ws: &mut Workspace, ctx: Context) {
+    put(ws, ctx, false);
+}
+
+/// Puts the picked register, or the unnamed one, `count` times.
+fn put(ws: &mut Workspace, ctx: Context, after: bool) {
+    let name = ctx.register().unwrap_or('"');
+
+    let register = match ws.registers_mut().read(name) {
+        Ok(register) => register,
+        Err(e) => return ws.set_message(Message::Error(format!("{e:#}"))),
+    };
+
+    // an empty register puts nothing however many times
+    let count = match register.text.is_empty() {
+        true => 1,
+        false => ctx.count(),
+    };
+    if register.text.len().checked_mul(count).is_none() {
+        return ws.set_message(Message::Error(format!("Cannot put {count} times")));
+    }
+
+    let text = register.text;
+    let linewise = register.linewise;
+
+    ws.curr_mut().with_transaction(|tx, buf| {
+        let index = buf.index();
+
+        let (pos, text, cursor) = match (linewise, after) {
+            (true, false) => (buf.line_char(index), text.clone(), None),
+            (true, true) if index + 1 < buf.len_lines() => {
+                (buf.line_char(index + 1), text.clone(), None)
+            }
+            // the last line has no break to put the lines after
+            (true, true) => {
+                let lines = text.strip_suffix('\n').unwrap_or(&text);
+                (buf.len_chars(), format!("\n{lines}"), None)
+            }
+            (false, _) => {
+                let pos = buf.as_char_pos();
+                let pos = match after && pos < buf.line_char(index) + buf.line_len(index) {
+                    true => buf.next_grapheme(pos),
+                    false => pos,
+                };
+
+                (pos, text.clone(), Some(pos))
+            }
+        };
+
+        // copies joined one after another, recorded as a single insert
+        let len = text.chars().count();
+        tx.shift(pos);
+        for copy in 0..count {
+            tx.insert_str(pos + copy * len, &text);
+        }
+        tx.apply(buf.text_mut());
+
+        match cursor {
+            // on the last char put, or on the first one when lines were put
+            Some(pos) if !text.contains('\n') => {
+                let end = pos + len * count;
+                buf.set_pos(buf.as_curs_pos(buf.prev_grapheme(end)));
+            }
+            Some(pos) => buf.set_pos(buf.as_curs_pos(pos)),
+            None => {
+                let line = match after {
+                    true => index + 1,
+                    false => index,
+                };
+                buf.set_pos((line, buf.first_non_blank(line)));
+            }
+        }
+
+        TransactionResult::Commit
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        register::Register,
+        test_utils::{text, workspace},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_put_lines() {
+        let mut ws = workspace("select a\nfrom t");
+        ws.curr_mut().buf_mut().set_pos((0, 3));
+        ws.registers_mut()
+            .yank(None, Register::new("  b,\n".into(), true))
+            .unwrap();
+
+        put_after(&mut ws, Context::default());
+        assert_eq!("select a\n  b,\nfrom t", text(&ws));
+        assert_eq!((1, 2), ws.curr().buf().pos());
+
+        put_before(&mut ws, Context::default());
+        assert_eq!("select a\n  b,\n  b,\nfrom t", text(&ws));
+        assert_eq!((1, 2), ws.curr().buf().pos());
+
+        ws.curr_mut().buf_mut().set_pos((3, 0));
+        put_after(&mut ws, Context::default());
+        assert_eq!("select a\n  b,\n  b,\nfrom t\n  b,", text(&ws));
+        assert_eq!((4, 2), ws.curr().buf().pos());
+
+        ws.curr_mut().undo();
+        assert_eq!("select a\n  b,\n  b,\nfrom t", text(&ws));
+        ws.curr_mut().undo();
+        ws.curr_mut().undo();
+        assert_eq!("select a\nfrom t", text(&ws));
+    }
+
+    #[test]
+    fn test_put_chars() {
+        let mut ws = workspace("select a");
+        ws.curr_mut().buf_mut().set_pos((0, 7));
+        ws.registers_mut()
+            .yank(Some('x'), Register::new(", b".into(), false))
+            .unwrap();
+
+        let ctx = Context {
+            register: Some('x'),
+            count: Some(2),
+            ..Default::default()
+        };
+
+        put_after(&mut ws, ctx);
+        assert_eq!("select a, b, b", text(&ws));
+        assert_eq!((0, 13), ws.curr().buf().pos());
+
+        ws.curr_mut().buf_mut().set_pos((0, 0));
+        put_before(&mut ws, Context::default());
+        assert_eq!(", bselect a, b, b", text(&ws));
+        assert_eq!((0, 2), ws.curr().buf().pos());
+
+        let ctx = Context {
+            register: Some('y'),
+            ..Default::default()
+        };
+        put_after(&mut ws, ctx);
+        assert!(matches!(ws.message(), Some(Message::Error(_))));
+        assert_eq!(", bselect a, b, b", text(&ws));
+    }
+
+    #[test]
+    fn test_put_huge_count() {
+        let mut ws = workspace("select a");
+        ws.curr_mut().buf_mut().set_pos((0, 7));
+        ws.registers_mut()
+            .yank(None, Register::new("ab\n".into(), true))
+            .unwrap();
+
+        let ctx = Context {
+            count: Some(usize::MAX),
+            ..Default::default()
+        };
+        put_after(&mut ws, ctx);
+        assert!(matches!(ws.message(), Some(Message::Error(_))));
+        assert_eq!("select a", text(&ws));
+
+        let ctx = Context {
+            count: Some(3),
+            ..Default::default()
+        };
+        put_after(&mut ws, ctx);
+        assert_eq!("select a\nab\nab\nab", text(&ws));
+        assert_eq!((1, 0), ws.curr().buf().pos());
+    }
+}
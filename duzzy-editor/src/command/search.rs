@@ -0,0 +1,228 @@
+use regex::Regex;
+
+use crate::{
+    buffer::CursorMode,
+    editor::{Message, Workspace},
+    input::{Event, Input},
+    renderer::EventOutcome,
+    search::{self, Search, SearchPrompt},
+    word::Words,
+};
+
+use super::Context;
+
+pub(super) fn search_forward(ws: &mut Workspace, _ctx: Context) {
+    open(ws, true);
+}
+
+pub(super) fn search_backward(ws: &mut Workspace, _ctx: Context) {
+    open(ws, false);
+}
+
+/// Moves to the next match of the last search, in the direction it was typed.
+pub(super) fn search_next(ws: &mut Workspace, ctx: Context) {
+    jump(ws, ctx.count(), false);
+}
+
+/// Moves to the next match of the last search, against the direction it was typed.
+pub(super) fn search_prev(ws: &mut Workspace, ctx: Context) {
+    jump(ws, ctx.count(), true);
+}
+
+/// Searches forward for the word under or after the cursor.
+pub(super) fn search_word_forward(ws: &mut Workspace, ctx: Context) {
+    search_word(ws, ctx, true);
+}
+
+/// Searches backward for the word under or after the cursor.
+pub(super) fn search_word_backward(ws: &mut Workspace, ctx: Context) {
+    search_word(ws, ctx, false);
+}
+
+pub fn on_key(ws: &mut Workspace, input: Input) -> EventOutcome {
+    let Some(prompt) = ws.search_prompt() else {
+        return EventOutcome::Ignore;
+    };
+
+    let line = ws.search_line_mut();
+
+    match input.event {
+        Event::Esc => return cancel(ws, prompt),
+        Event::Enter => {
+            submit(ws, prompt);
+            return EventOutcome::Render;
+        }
+        Event::Backspace if line.input().is_empty() => return cancel(ws, prompt),
+        Event::Backspace => line.delete_char(),
+        Event::Delete => line.delete_char_inplace(),
+        Event::Left => line.move_left(),
+        Event::Right => line.move_right(),
+        Event::Home => line.move_start(),
+        Event::End => line.move_end(),
+        Event::Up => line.recall_prev(),
+        Event::Down => line.recall_next(),
+        Event::Char(ch) if !input.modifiers.ctr && !input.modifiers.alt => line.insert_char(ch),
+        // only the first line of pasted text, like typed
+        Event::Paste(text) => {
+            for ch in text.lines().next().unwrap_or_default().chars() {
+                line.insert_char(ch);
+            }
+        }
+        _ => return EventOutcome::Ignore,
+    }
+
+    preview(ws, prompt);
+    EventOutcome::Render
+}
+
+fn open(ws: &mut Workspace, forward: bool) {
+    let origin = ws.curr().buf().pos();
+
+    ws.search_line_mut().reset();
+    ws.set_search_prompt(Some(SearchPrompt { forward, origin }));
+    ws.curr_mut().buf_mut().set_mode(CursorMode::Command);
+}
+
+fn close(ws: &mut Workspace, prompt: SearchPrompt) {
+    ws.set_search_prompt(None);
+
+    let buf = ws.curr_mut().buf_mut();
+    buf.set_mode(CursorMode::Normal);
+    buf.set_pos(prompt.origin);
+}
+
+fn cancel(ws: &mut Workspace, prompt: SearchPrompt) -> EventOutcome {
+    ws.search_line_mut().reset();
+    close(ws, prompt);
+
+    EventOutcome::Render
+}
+
+/// Searches for the typed pattern, or the last one when nothing was typed.
+fn submit(ws: &mut Workspace, prompt: SearchPrompt) {
+    let pattern = ws.search_line_mut().submit();
+    close(ws, prompt);
+
+    let pattern = match (pattern.is_empty(), ws.search()) {
+        (false, _) => pattern,
+        (true, Some(search)) => search.pattern.clone(),
+        (true, None) => return no_previous(ws),
+    };
+
+    ws.set_search(Search {
+        pattern,
+        forward: prompt.forward,
+    });
+    jump(ws, 1, false);
+}
+
+/// Moves the cursor to the first match of the pattern typed so far,
+/// back to where the search started when there is none.
+fn preview(ws: &mut Workspace, prompt: SearchPrompt) {
+    let pattern = ws.prompt().input();
+    let regex = compile(ws, pattern).ok().filter(|_| !pattern.is_empty());
+
+    let found = regex.and_then(|regex| {
+        let buf = ws.curr().buf();
+        let (index, offset) = prompt.origin;
+        search::find(buf, &regex, buf.line_char(index) + offset, prompt.forward)
+    });
+
+    let buf = ws.curr_mut().buf_mut();
+    match found {
+        Some(found) => buf.set_pos(buf.as_curs_pos(found.range.start)),
+        None => buf.set_pos(prompt.origin),
+    }
+}
+
+/// Moves to the `count`th match of the last search, against its direction when `reverse`.
+fn jump(ws: &mut Workspace, count: usize, reverse: bool) {
+    let Some(search) = ws.search().cloned() else {
+        return no_previous(ws);
+    };
+
+    let regex = match compile(ws, &search.pattern) {
+        Ok(regex) => regex,
+        Err(e) => return ws.set_message(Message::Error(format!("{e:#}"))),
+    };
+
+    let forward = search.forward != reverse;
+    let buf = ws.curr().buf();
+    let mut pos = buf.as_char_pos();
+    let mut wrapped = false;
+    let (mut count, mut done, mut first) = (count, 0, None);
+
+    while done < count {
+        let Some(found) = search::find(buf, &regex, pos, forward) else {
+            let message = format!("Pattern not found: {}", search.pattern);
+            return ws.set_message(Message::Error(message));
+        };
+
+        pos = found.range.start;
+        wrapped |= found.wrapped;
+        done += 1;
+
+        // back on the first match after all of them, the rest of the count only goes around
+        match first {
+            None => first = Some(pos),
+            Some(first) if first == pos => count = done + (count - done) % (done - 1),
+            Some(_) => (),
+        }
+    }
+
+    let buf = ws.curr_mut().buf_mut();
+    buf.set_pos(buf.as_curs_pos(pos));
+
+    if wrapped {
+        let message = match forward {
+            true => "Search hit BOTTOM, continuing at TOP",
+            false => "Search hit TOP, continuing at BOTTOM",
+        };
+        ws.set_message(Message::Info(message.into()));
+    }
+}
+
+/// Searches for the word under or after the cursor as a whole word,
+/// starting from the word so `#` skips it.
+fn search_word(ws: &mut Workspace, ctx: Context, forward: bool) {
+    let buf = ws.curr().buf();
+    let pos = buf.as_char_pos();
+
+    let word = Words::new(&ws.options().word_chars, false)
+        .tokens(buf, buf.index())
+        .into_iter()
+        .find(|token| token.end > pos && !token.is_empty());
+
+    let Some(word) = word else {
+        return ws.set_message(Message::Error("No string under cursor".into()));
+    };
+
+    let text = buf.text().slice(word.clone()).to_string();
+    let is_word_char = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_');
+    let boundary = |ch| match is_word_char(ch) {
+        true => r"\b",
+        false => "",
+    };
+
+    let pattern = format!(
+        "{}{}{}",
+        boundary(text.chars().next()),
+        regex::escape(&text),
+        boundary(text.chars().next_back())
+    );
+
+    let buf = ws.curr_mut().buf_mut();
+    buf.set_pos(buf.as_curs_pos(word.start));
+
+    ws.set_search(Search { pattern, forward });
+    jump(ws, ctx.count(), false);
+}
+
+fn compile(ws: &Workspace, pattern: &str) -> anyhow::Result<Regex> {
+    let options = ws.options();
+    search::regex(pattern, options.ignore_case, options.smart_case)
+}
+
+fn no_previous(ws: &mut Workspace) {
+    ws.set_message(Message::Error("No previous search pattern".into()));
+}
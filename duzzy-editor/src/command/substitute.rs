@@ -0,0 +1,170 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    editor::{Message, Workspace},
+    input::{Event, Input},
+    renderer::EventOutcome,
+    search::{self, Search},
+    substitute::{Substitute, Substitution},
+    transaction::{Transaction, TransactionResult},
+};
+
+/// `:s/pattern/replacement/flags` over `lines`, replacing every match at once
+/// or asking for each one with the `c` flag. All replacements are undone together.
+pub(super) fn substitute(
+    ws: &mut Workspace,
+    lines: RangeInclusive<usize>,
+    args: &str,
+) -> anyhow::Result<()> {
+    let Substitute {
+        pattern,
+        replacement,
+        flags,
+    } = Substitute::parse(args)?;
+
+    let pattern = match (pattern.is_empty(), ws.search()) {
+        (false, _) => pattern,
+        (true, Some(search)) => search.pattern.clone(),
+        (true, None) => anyhow::bail!("No previous search pattern"),
+    };
+
+    let options = ws.options();
+    let regex = match flags.ignore_case {
+        Some(ignore_case) => search::regex(&pattern, ignore_case, false)?,
+        None => search::regex(&pattern, options.ignore_case, options.smart_case)?,
+    };
+
+    // the pattern is searched again by `n` and `N`, like after `/`
+    ws.set_search(Search {
+        pattern: pattern.clone(),
+        forward: true,
+    });
+
+    let origin = ws.curr().buf().pos();
+    let lines = (*lines.start(), *lines.end());
+    let mut substitution = Substitution::new(regex, replacement, flags.all, lines, origin);
+
+    if substitution.next(ws.curr().buf()).is_none() {
+        anyhow::bail!("Pattern not found: {pattern}");
+    }
+
+    match flags.confirm {
+        true => ask(ws, substitution),
+        false => replace_all(ws, substitution),
+    }
+
+    Ok(())
+}
+
+/// Answers the question asked for the pending match: `y` replaces it, `n` skips it,
+/// `a` replaces it and all the next ones, `l` replaces it and stops, `q` stops.
+pub fn on_key(ws: &mut Workspace, input: Input) -> EventOutcome {
+    let Some(mut substitution) = ws.take_substitution() else {
+        return EventOutcome::Ignore;
+    };
+
+    let answer = match input.event {
+        Event::Char(ch) if !input.modifiers.ctr && !input.modifiers.alt => ch,
+        Event::Esc => 'q',
+        _ => '\0',
+    };
+
+    match answer {
+        'y' => {
+            replace(ws, &mut substitution);
+            next(ws, substitution);
+        }
+        'n' => {
+            substitution.skip();
+            next(ws, substitution);
+        }
+        'a' => replace_all(ws, substitution),
+        'l' => {
+            replace(ws, &mut substitution);
+            finish(ws, &substitution);
+        }
+        'q' => finish(ws, &substitution),
+        _ => ask(ws, substitution),
+    }
+
+    EventOutcome::Render
+}
+
+/// Moves to the next match to ask for, or finishes past the last one.
+fn next(ws: &mut Workspace, mut substitution: Substitution) {
+    match substitution.next(ws.curr().buf()) {
+        Some(_) => ask(ws, substitution),
+        None => finish(ws, &substitution),
+    }
+}
+
+/// Shows the pending match and waits for an answer.
+fn ask(ws: &mut Workspace, substitution: Substitution) {
+    let Some(candidate) = substitution.pending() else {
+        return finish(ws, &substitution);
+    };
+
+    let buf = ws.curr_mut().buf_mut();
+    buf.set_pos(buf.as_curs_pos(candidate.range.start));
+
+    let message = format!(
+        "replace with {} (y/n/a/q/l)?",
+        candidate.text.escape_debug()
+    );
+    ws.set_message(Message::Info(message));
+    ws.set_substitution(Some(substitution));
+}
+
+fn replace_all(ws: &mut Workspace, mut substitution: Substitution) {
+    while substitution.pending().is_some() {
+        replace(ws, &mut substitution);
+        substitution.next(ws.curr().buf());
+    }
+
+    finish(ws, &substitution);
+}
+
+/// Replaces the pending match, in a transaction kept open until the substitution finishes.
+fn replace(ws: &mut Workspace, substitution: &mut Substitution) {
+    let Some(candidate) = substitution.pending() else {
+        return;
+    };
+
+    ws.curr_mut().with_transaction(|substitute_tx, buf| {
+        let mut tx = Transaction::new();
+
+        tx.shift(candidate.range.start);
+        tx.delete_str(candidate.range.end, &candidate.matched);
+        tx.insert_str(candidate.range.start, &candidate.text);
+        tx.apply(buf.text_mut());
+
+        substitute_tx.merge(tx);
+        TransactionResult::Keep
+    });
+
+    substitution.replaced(ws.curr().buf());
+}
+
+/// Commits the replacements made and leaves the cursor on the last replaced line.
+fn finish(ws: &mut Workspace, substitution: &Substitution) {
+    let doc = ws.curr_mut();
+    doc.commit();
+
+    let buf = doc.buf_mut();
+    match substitution.replaced_line() {
+        Some(line) => buf.set_pos((line, buf.first_non_blank(line))),
+        None => buf.set_pos(substitution.origin),
+    }
+
+    let plural = |count: usize, word: &str| match count {
+        1 => format!("1 {word}"),
+        count => format!("{count} {word}s"),
+    };
+
+    let message = format!(
+        "{} on {}",
+        plural(substitution.count, "substitution"),
+        plural(substitution.lines, "line")
+    );
+    ws.set_message(Message::Info(message));
+}
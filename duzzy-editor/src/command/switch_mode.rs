@@ -5,6 +5,8 @@ use crate::{
     transaction::TransactionResult,
 };
 
+use super::Context;
+
 enum Switch {
     Inplace,
     LineStart,
@@ -14,30 +16,42 @@ enum Switch {
 }
 
 pub(super) fn normal_mode_inplace(ws: &mut Workspace) {
+    let tab_width = ws.options().tab_width;
     let doc = ws.curr_mut();
-    doc.with_transaction(|_, buf| {
+
+    doc.with_transaction(|tx, buf| {
+        if let Some(block) = buf.take_block_insert() {
+            super::visual::repeat_block_insert(tx, buf, block, tab_width);
+        }
+
         buf.set_mode(CursorMode::Normal);
         TransactionResult::Commit
     });
 }
 
-pub(super) fn insert_mode_inplace(ws: &mut Workspace) {
+pub(super) fn command_line_mode(ws: &mut Workspace, _ctx: Context) {
+    ws.cmdline_mut().reset();
+    ws.set_search_prompt(None);
+    ws.curr_mut().buf_mut().set_mode(CursorMode::Command);
+}
+
+pub(super) fn insert_mode_inplace(ws: &mut Workspace, _ctx: Context) {
     switch_mode(ws, Switch::Inplace);
 }
 
-pub(super) fn insert_mode_line_end(ws: &mut Workspace) {
+pub(super) fn insert_mode_line_end(ws: &mut Workspace, _ctx: Context) {
     switch_mode(ws, Switch::LineEnd);
 }
 
-pub(super) fn insert_mode_line_start(ws: &mut Workspace) {
+pub(super) fn insert_mode_line_start(ws: &mut Workspace, _ctx: Context) {
     switch_mode(ws, Switch::LineStart);
 }
 
-pub(super) fn insert_mode_line_next(ws: &mut Workspace) {
+pub(super) fn insert_mode_line_next(ws: &mut Workspace, _ctx: Context) {
     switch_mode(ws, Switch::LineNext);
 }
 
-pub(super) fn insert_mode_line_prev(ws: &mut Workspace) {
+pub(super) fn insert_mode_line_prev(ws: &mut Workspace, _ctx: Context) {
     switch_mode(ws, Switch::LinePrev);
 }
 
@@ -57,18 +71,13 @@ fn switch_mode(ws: &mut Workspace, switch: Switch) {
 
 fn switch_line_end(buf: &mut Buffer) {
     let idx = buf.index();
-    buf.set_offset(buf.len_bytes(idx));
-
-    if idx < buf.len_lines() - 1 {
-        let ofs = buf.offset() - 1;
-        buf.set_offset(ofs);
-    }
+    buf.set_offset(buf.line_len(idx));
 }
 
 fn switch_line_next(doc: &mut Document) {
     let buf = doc.buf();
     let idx = buf.index() + 1;
-    let line_pos = buf.line_byte(idx);
+    let line_pos = buf.line_char(idx);
 
     switch_with_new_line(doc, line_pos);
 
@@ -80,7 +89,7 @@ fn switch_line_next(doc: &mut Document) {
 fn switch_line_prev(doc: &mut Document) {
     let buf = doc.buf();
     let idx = buf.index() + 1;
-    let line_pos = buf.line_byte(idx);
+    let line_pos = buf.line_char(idx);
 
     doc.buf_mut().set_offset(0);
     switch_with_new_line(doc, line_pos);
@@ -88,7 +97,7 @@ fn switch_line_prev(doc: &mut Document) {
 
 fn switch_with_new_line(doc: &mut Document, line_pos: usize) {
     doc.with_transaction(|tx, buf| {
-        tx.shift(buf.as_byte_pos());
+        tx.shift(buf.as_char_pos());
         tx.insert_char(line_pos, '\n');
         tx.shift(line_pos);
         tx.apply(buf.text_mut());
@@ -0,0 +1,82 @@
+use crate::{
+    document::Document,
+    editor::{Message, Workspace},
+};
+
+use super::Context;
+
+/// Goes to the next tab page, wrapping around, or to the one numbered by the count.
+pub(super) fn next_tab(ws: &mut Workspace, ctx: Context) {
+    let len = ws.tabs().len();
+    match ctx.raw_count() {
+        Some(count) => ws.go_to_tab(count.saturating_sub(1)),
+        None => ws.go_to_tab((ws.tab() + 1) % len),
+    }
+}
+
+/// Goes back by the count of tab pages, wrapping around.
+pub(super) fn prev_tab(ws: &mut Workspace, ctx: Context) {
+    let len = ws.tabs().len();
+    ws.go_to_tab((ws.tab() + len - ctx.count() % len) % len);
+}
+
+pub(super) fn close_tab(ws: &mut Workspace, _ctx: Context) {
+    if let Err(e) = ws.close_tab() {
+        ws.set_message(Message::Error(format!("{e:#}")));
+    }
+}
+
+pub(super) fn only_tab(ws: &mut Workspace, _ctx: Context) {
+    ws.only_tab();
+}
+
+/// `:tabnew`, opens a tab page showing `path`, or a new scratch without.
+pub(super) fn tab_new(ws: &mut Workspace, path: &str) -> anyhow::Result<()> {
+    ws.new_tab();
+
+    if path.is_empty() {
+        ws.add_doc(Document::default());
+        return Ok(());
+    }
+
+    if let Err(e) = ws.open(path) {
+        ws.close_tab()?;
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// `:tabnext N`, goes to the tab page numbered `args` from 1.
+pub(super) fn tab_next(ws: &mut Workspace, args: &str) -> anyhow::Result<()> {
+    match args.parse::<usize>() {
+        Ok(number) if (1..=ws.tabs().len()).contains(&number) => {
+            ws.go_to_tab(number - 1);
+            Ok(())
+        }
+        _ => anyhow::bail!("Invalid tab page: {args}"),
+    }
+}
+
+/// `:tabmove`, moves the current tab page to position `args` counted from 0,
+/// by `args` positions with a sign, or to the end without.
+pub(super) fn tab_move(ws: &mut Workspace, args: &str) -> anyhow::Result<()> {
+    let last = ws.tabs().len() - 1;
+
+    let index = match args.chars().next() {
+        None => last,
+        Some('+' | '-') => {
+            let Ok(delta) = args.parse::<isize>() else {
+                anyhow::bail!("Invalid tab position: {args}");
+            };
+            ws.tab().saturating_add_signed(delta)
+        }
+        Some(_) => match args.parse::<usize>() {
+            Ok(index) => index,
+            Err(_) => anyhow::bail!("Invalid tab position: {args}"),
+        },
+    };
+
+    ws.move_tab(index.min(last));
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use std::ops::Range;
+
+use crate::{
+    buffer::{Buffer, SelectionKind},
+    editor::Workspace,
+    options::WordChars,
+    sql,
+    word::Words,
+};
+
+use super::{operator::Span, Context};
+
+/// Structured range around the cursor, addressed like `iw` or `a(`.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd)]
+pub enum TextObject {
+    Word,
+    LongWord,
+    Quote(char),
+    /// Pair of open and close brackets.
+    Bracket(char, char),
+    /// SQL statement ended by `;`.
+    Statement,
+}
+
+/// Whether a text object takes only its contents or its delimiters and surrounding blanks too.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd)]
+pub enum Scope {
+    Inner,
+    Around,
+}
+
+/// Selects `object` around the cursor in visual mode.
+pub(super) fn select(ws: &mut Workspace, object: TextObject, scope: Scope, ctx: Context) {
+    let word_chars = ws.options().word_chars.clone();
+    let buf = ws.curr_mut().buf_mut();
+
+    let Some(span) = span(buf, object, scope, ctx.count(), &word_chars) else {
+        return;
+    };
+
+    if span.range.is_empty() {
+        return;
+    }
+
+    buf.set_pos(buf.as_curs_pos(span.range.start));
+    buf.select(SelectionKind::Char);
+    buf.set_pos(buf.as_curs_pos(buf.prev_grapheme(span.range.end)));
+}
+
+/// Chars covered by `object` around the cursor, `count` levels out for brackets.
+pub(super) fn span(
+    buf: &Buffer,
+    object: TextObject,
+    scope: Scope,
+    count: usize,
+    word_chars: &WordChars,
+) -> Option<Span> {
+    let range = match object {
+        TextObject::Word => word(buf, scope, Words::new(word_chars, false)),
+        TextObject::LongWord => word(buf, scope, Words::new(word_chars, true)),
+        TextObject::Quote(quote) => self::quote(buf, scope, quote),
+        TextObject::Bracket(open, close) => bracket(buf, scope, (open, close), count),
+        TextObject::Statement => statement(buf, scope),
+    }?;
+
+    Some(Span {
+        range,
+        linewise: false,
+    })
+}
+
+fn word(buf: &Buffer, scope: Scope, words: Words) -> Option<Range<usize>> {
+    let pos = buf.as_char_pos();
+    let index = buf.index();
+    let line_end = buf.line_char(index) + buf.line_len(index);
+
+    let tokens = words.tokens(buf, index);
+    let blanks_after = |end: usize| {
+        tokens
+            .iter()
+            .find(|token| token.start >= end)
+            .map_or(line_end, |token| token.start)
+    };
+
+    if let Some(token) = tokens.iter().find(|token| token.contains(&pos)) {
+        let mut range = token.clone();
+
+        if scope == Scope::Around {
+            range.end = blanks_after(token.end);
+
+            // without blanks after the word, take the ones before it
+            if range.end == token.end {
+                range.start = tokens
+                    .iter()
+                    .rev()
+                    .find(|prev| prev.end <= token.start)
+                    .map_or(token.start, |prev| prev.end);
+            }
+        }
+
+        return Some(range);
+    }
+
+    if pos >= line_end {
+        return None;
+    }
+
+    // on blanks, taken along with the following word
+    let start = tokens
+        .iter()
+        .rev()
+        .find(|token| token.end <= pos)
+        .map_or(buf.line_char(index), |token| token.end);
+    let end = blanks_after(pos);
+
+    match scope {
+        Scope::Inner => Some(start..end),
+        Scope::Around => {
+            let next = tokens.iter().find(|token| token.start >= end);
+            Some(start..next.map_or(end, |token| token.end))
+        }
+    }
+}
+
+/// Quoted text on the cursor line, quotes doubled inside being escaped ones.
+/// The cursor may be before the quotes too.
+fn quote(buf: &Buffer, scope: Scope, quote: char) -> Option<Range<usize>> {
+    let index = buf.index();
+    let start = buf.line_char(index);
+    let ofs = buf.offset();
+    let chars: Vec<_> = buf
+        .text()
+        .line(index)
+        .chars()
+        .take(buf.line_len(index))
+        .collect();
+
+    let mut pairs = vec![];
+    let mut open = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match (open, chars[i] == quote) {
+            (Some(_), true) if chars.get(i + 1) == Some(&quote) => i += 1,
+            (Some(from), true) => {
+                pairs.push((from, i));
+                open = None;
+            }
+            (None, true) => open = Some(i),
+            _ => (),
+        }
+        i += 1;
+    }
+
+    let (open, close) = pairs.into_iter().find(|(_, close)| ofs <= *close)?;
+
+    let range = match scope {
+        Scope::Inner => open + 1..close,
+        Scope::Around => {
+            let after = chars[close + 1..]
+                .iter()
+                .take_while(|ch| is_blank(**ch))
+                .count();
+            let before = match after {
+                0 => chars[..open]
+                    .iter()
+                    .rev()
+                    .take_while(|ch| is_blank(**ch))
+                    .count(),
+                _ => 0,
+            };
+
+            open - before..close + 1 + after
+        }
+    };
+
+    Some(start + range.start..start + range.end)
+}
+
+/// Brackets around the cursor `count` levels out, or the ones under it,
+/// skipping brackets in strings and comments.
+fn bracket(
+    buf: &Buffer,
+    scope: Scope,
+    (open, close): (char, char),
+    count: usize,
+) -> Option<Range<usize>> {
+    let text = buf.text();
+    let is_code = |pos: usize| sql::is_code(buf.literals(), pos);
+
+    let pos = buf.as_char_pos();
+    let mut from = match text.get_char(pos) {
+        Some(ch) if ch == close && is_code(pos) => pos,
+        Some(_) => pos + 1,
+        None => text.len_chars(),
+    };
+
+    let mut start = None;
+    for _ in 0..count {
+        let mut depth = 0;
+        let mut chars = text.chars_at(from);
+        let mut idx = from;
+
+        start = loop {
+            let ch = chars.prev()?;
+            idx -= 1;
+
+            if !is_code(idx) {
+                continue;
+            }
+            match ch {
+                ch if ch == close => depth += 1,
+                ch if ch == open && depth == 0 => break Some(idx),
+                ch if ch == open => depth -= 1,
+                _ => (),
+            }
+        };
+        from = start?;
+    }
+
+    let start = start?;
+    let mut depth = 0;
+    let end = (start + 1..text.len_chars())
+        .zip(text.chars_at(start + 1))
+        .filter(|(idx, _)| is_code(*idx))
+        .find_map(|(idx, ch)| match ch {
+            ch if ch == open => {
+                depth += 1;
+                None
+            }
+            ch if ch == close && depth == 0 => Some(idx),
+            ch if ch == close => {
+                depth -= 1;
+                None
+            }
+            _ => None,
+        })?;
+
+    match scope {
+        Scope::Inner => Some(start + 1..end),
+        Scope::Around => Some(start..end + 1),
+    }
+}
+
+/// Statement around the cursor, between the `;` before it and the one ending it.
+/// The inner statement leaves out the surrounding blanks and the `;`, around it
+/// takes the `;` and the rest of its line when that is blank.
+fn statement(buf: &Buffer, scope: Scope) -> Option<Range<usize>> {
+    let text = buf.text();
+    let is_end = |idx: usize| text.char(idx) == ';' && sql::is_code(buf.literals(), idx);
+
+    let pos = buf.as_char_pos().min(text.len_chars());
+    let len = text.len_chars();
+
+    let after_prev = (0..pos)
+        .rev()
+        .find(|idx| is_end(*idx))
+        .map_or(0, |idx| idx + 1);
+    let end = (pos..len).find(|idx| is_end(*idx));
+
+    let is_space = |idx: &usize| text.char(*idx).is_whitespace();
+    let start = (after_prev..end.unwrap_or(len))
+        .find(|idx| !is_space(idx))
+        .unwrap_or(after_prev);
+    let inner_end = (start..end.unwrap_or(len))
+        .rev()
+        .find(|idx| !is_space(idx))
+        .map_or(start, |idx| idx + 1);
+
+    match (scope, end) {
+        (Scope::Inner, _) => Some(start..inner_end),
+        (Scope::Around, Some(end)) => {
+            let mut end = end + 1;
+            while end < len && is_blank(text.char(end)) {
+                end += 1;
+            }
+            if end < len && text.char(end) == '\r' {
+                end += 1;
+            }
+            if end < len && text.char(end) == '\n' {
+                end += 1;
+            }
+
+            Some(start..end)
+        }
+        (Scope::Around, None) => Some(start..len),
+    }
+}
+
+const fn is_blank(ch: char) -> bool {
+    ch == ' ' || ch == '\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+
+    fn object(text: &str, pos: usize, object: TextObject, scope: Scope) -> Option<String> {
+        let mut buf = Buffer::default();
+        buf.set_text(Rope::from_str(text));
+        buf.set_pos(buf.as_curs_pos(pos));
+
+        let span = span(&buf, object, scope, 1, &WordChars::default())?;
+        Some(buf.text().slice(span.range).to_string())
+    }
+
+    #[test]
+    fn test_word_objects() {
+        let text = "select  order_id, \"a b\"\n";
+
+        let word = |pos, scope| object(text, pos, TextObject::Word, scope);
+        assert_eq!(Some("select".into()), word(2, Scope::Inner));
+        assert_eq!(Some("select  ".into()), word(2, Scope::Around));
+        assert_eq!(Some("  ".into()), word(6, Scope::Inner));
+        assert_eq!(Some("  order_id".into()), word(7, Scope::Around));
+        assert_eq!(Some("\"a b\"".into()), word(19, Scope::Inner));
+        assert_eq!(Some(" \"a b\"".into()), word(19, Scope::Around));
+
+        let big = object(text, 8, TextObject::LongWord, Scope::Inner);
+        assert_eq!(Some("order_id,".into()), big);
+    }
+
+    #[test]
+    fn test_quote_objects() {
+        let text = "where a = 'it''s' and b = 'x'";
+
+        let quote = |pos, scope| object(text, pos, TextObject::Quote('\''), scope);
+        assert_eq!(Some("it''s".into()), quote(12, Scope::Inner));
+        assert_eq!(Some("it''s".into()), quote(0, Scope::Inner));
+        assert_eq!(Some("'it''s' ".into()), quote(10, Scope::Around));
+        assert_eq!(Some(" 'x'".into()), quote(27, Scope::Around));
+        assert_eq!(None, object(text, 3, TextObject::Quote('"'), Scope::Inner));
+    }
+
+    #[test]
+    fn test_bracket_objects() {
+        let text = "count(coalesce(a, ')'), b) + (c)";
+        let paren = TextObject::Bracket('(', ')');
+
+        assert_eq!(Some("a, ')'".into()), object(text, 15, paren, Scope::Inner));
+        assert_eq!(
+            Some("(coalesce(a, ')'), b)".into()),
+            object(text, 23, paren, Scope::Around)
+        );
+        assert_eq!(
+            Some("coalesce(a, ')'), b".into()),
+            object(text, 5, paren, Scope::Inner)
+        );
+        assert_eq!(Some("c".into()), object(text, 31, paren, Scope::Inner));
+        assert_eq!(None, object(text, 0, paren, Scope::Inner));
+
+        let mut buf = Buffer::default();
+        buf.set_text(Rope::from_str(text));
+        buf.set_pos((0, 15));
+        let span = span(&buf, paren, Scope::Inner, 2, &WordChars::default()).unwrap();
+        assert_eq!(6..25, span.range);
+    }
+
+    #[test]
+    fn test_statement_objects() {
+        let text = "select 1;\n  select ';'\n  from t ;  \nselect 3";
+
+        let statement = |pos, scope| object(text, pos, TextObject::Statement, scope);
+        assert_eq!(Some("select 1".into()), statement(3, Scope::Inner));
+        assert_eq!(Some("select 1;\n".into()), statement(8, Scope::Around));
+        assert_eq!(
+            Some("select ';'\n  from t".into()),
+            statement(10, Scope::Inner)
+        );
+        assert_eq!(
+            Some("select ';'\n  from t ;  \n".into()),
+            statement(20, Scope::Around)
+        );
+        assert_eq!(Some("select 3".into()), statement(40, Scope::Around));
+    }
+}
@@ -0,0 +1,491 @@
+use std::ops::Range;
+
+use crate::{
+    buffer::{BlockInsert, Buffer, CursorMode, SelectionKind},
+    editor::{Message, Workspace},
+    register::Register,
+    transaction::{Transaction, TransactionResult},
+};
+
+use super::{
+    operator::{self, Operator, Span},
+    Context,
+};
+
+enum Edge {
+    Start,
+    End,
+}
+
+pub(super) fn visual_mode(ws: &mut Workspace, _ctx: Context) {
+    toggle_visual(ws.curr_mut().buf_mut(), SelectionKind::Char);
+}
+
+pub(super) fn visual_line_mode(ws: &mut Workspace, _ctx: Context) {
+    toggle_visual(ws.curr_mut().buf_mut(), SelectionKind::Line);
+}
+
+pub(super) fn visual_block_mode(ws: &mut Workspace, _ctx: Context) {
+    toggle_visual(ws.curr_mut().buf_mut(), SelectionKind::Block);
+}
+
+pub(super) fn normal_mode(ws: &mut Workspace, _ctx: Context) {
+    exit_visual(ws.curr_mut().buf_mut());
+}
+
+/// Leaves visual mode for the command line, with the selected lines as its range.
+pub(super) fn visual_command_mode(ws: &mut Workspace, ctx: Context) {
+    exit_visual(ws.curr_mut().buf_mut());
+    super::switch_mode::command_line_mode(ws, ctx);
+
+    for ch in "'<,'>".chars() {
+        ws.cmdline_mut().insert_char(ch);
+    }
+}
+
+pub(super) fn visual_yank(ws: &mut Workspace, ctx: Context) {
+    let tab_width = ws.options().tab_width;
+    let buf = ws.curr_mut().buf_mut();
+
+    let (Some(register), Some(lines), Some(cols)) = (
+        block_register(buf, tab_width),
+        buf.selected_lines(),
+        buf.block_cols(tab_width),
+    ) else {
+        return apply_selection(ws, Operator::Yank, ctx);
+    };
+
+    let first = *lines.start();
+    buf.set_pos((first, buf.block_offsets(first, &cols, tab_width).start));
+
+    exit_visual(buf);
+    if let Err(e) = ws.registers_mut().yank(ctx.register(), register) {
+        ws.set_message(Message::Error(format!("{e:#}")));
+    }
+}
+
+pub(super) fn visual_delete(ws: &mut Workspace, ctx: Context) {
+    match ws.curr().buf().block_cols(ws.options().tab_width) {
+        Some(cols) => delete_block(ws, cols, false, ctx),
+        None => apply_selection(ws, Operator::Delete, ctx),
+    }
+}
+
+pub(super) fn visual_change(ws: &mut Workspace, ctx: Context) {
+    match ws.curr().buf().block_cols(ws.options().tab_width) {
+        Some(cols) => delete_block(ws, cols, true, ctx),
+        None => apply_selection(ws, Operator::Change, ctx),
+    }
+}
+
+/// Inserts on every selected line at the block start, or at the line start
+/// for other selections.
+pub(super) fn visual_block_insert(ws: &mut Workspace, _ctx: Context) {
+    block_insert(ws, Edge::Start);
+}
+
+/// Appends on every selected line after the block, or at the line end
+/// for other selections.
+pub(super) fn visual_block_append(ws: &mut Workspace, _ctx: Context) {
+    block_insert(ws, Edge::End);
+}
+
+pub(super) fn visual_indent(ws: &mut Workspace, _ctx: Context) {
+    apply_lines(ws, Operator::Indent);
+}
+
+pub(super) fn visual_dedent(ws: &mut Workspace, _ctx: Context) {
+    apply_lines(ws, Operator::Dedent);
+}
+
+/// Applies `operator` to a characterwise or linewise selection.
+fn apply_selection(ws: &mut Workspace, operator: Operator, ctx: Context) {
+    let buf = ws.curr().buf();
+
+    let (Some(range), Some(selection)) = (buf.selection_range(), buf.selection()) else {
+        return;
+    };
+
+    let linewise = selection.kind == SelectionKind::Line;
+    operator::apply(ws, operator, Span { range, linewise }, ctx.register());
+}
+
+/// Applies `operator` to the lines touched by a selection of any kind.
+fn apply_lines(ws: &mut Workspace, operator: Operator) {
+    let buf = ws.curr().buf();
+
+    let Some(lines) = buf.selected_lines() else {
+        return;
+    };
+
+    let span = Span::lines(buf, *lines.start(), *lines.end());
+    operator::apply(ws, operator, span, None);
+}
+
+/// Starts a selection of `kind`, switches the kind of the current one
+/// or leaves visual mode when it's already of that kind.
+fn toggle_visual(buf: &mut Buffer, kind: SelectionKind) {
+    match buf.selection() {
+        Some(selection) if selection.kind == kind => exit_visual(buf),
+        Some(_) => buf.set_selection_kind(kind),
+        None => {
+            buf.select(kind);
+            buf.set_mode(CursorMode::Visual);
+        }
+    }
+}
+
+fn exit_visual(buf: &mut Buffer) {
+    buf.clear_selection();
+    buf.set_mode(CursorMode::Normal);
+
+    let (idx, ofs) = buf.pos();
+    buf.set_offset(buf.clamp_offset(idx, ofs));
+}
+
+/// Rows of a block selection joined by line breaks.
+fn block_register(buf: &Buffer, tab_width: usize) -> Option<Register> {
+    let cols = buf.block_cols(tab_width)?;
+
+    let rows: Vec<_> = buf
+        .selected_lines()?
+        .map(|index| {
+            let offsets = buf.block_offsets(index, &cols, tab_width);
+            buf.text().line(index).slice(offsets).to_string()
+        })
+        .collect();
+
+    Some(Register::new(rows.join("\n"), false))
+}
+
+/// Deletes display columns `cols` of the selected lines, starting a block insert when `change`.
+fn delete_block(ws: &mut Workspace, cols: Range<usize>, change: bool, ctx: Context) {
+    let tab_width = ws.options().tab_width;
+    let buf = ws.curr().buf();
+
+    let (Some(register), Some(lines)) = (block_register(buf, tab_width), buf.selected_lines())
+    else {
+        return;
+    };
+    if let Err(e) = ws.registers_mut().delete(ctx.register(), register) {
+        return ws.set_message(Message::Error(format!("{e:#}")));
+    }
+
+    ws.curr_mut().with_transaction(|tx, buf| {
+        let first = *lines.start();
+        let offset = buf.block_offsets(first, &cols, tab_width).start;
+        tx.shift(buf.line_char(first) + offset);
+
+        for index in lines.clone().rev() {
+            let offsets = buf.block_offsets(index, &cols, tab_width);
+            if offsets.is_empty() {
+                continue;
+            }
+
+            let start = buf.line_char(index);
+            let text = buf.text().line(index).slice(offsets.clone()).to_string();
+
+            tx.delete_str(start + offsets.end, &text);
+            tx.shift(start + offsets.start);
+        }
+
+        tx.apply(buf.text_mut());
+        buf.set_pos((first, offset));
+
+        if !change {
+            exit_visual(buf);
+            return TransactionResult::Commit;
+        }
+
+        buf.clear_selection();
+        buf.set_mode(CursorMode::Insert);
+        buf.set_block_insert(BlockInsert {
+            lines: first + 1..=*lines.end(),
+            col: Some(cols.start),
+            start: buf.as_char_pos(),
+            pad: false,
+        });
+
+        TransactionResult::Keep
+    });
+}
+
+/// Starts inserting on the first selected line, repeated on the others
+/// when insert mode is left.
+fn block_insert(ws: &mut Workspace, edge: Edge) {
+    let tab_width = ws.options().tab_width;
+    let buf = ws.curr().buf();
+
+    let Some(lines) = buf.selected_lines() else {
+        return;
+    };
+
+    let cols = buf.block_cols(tab_width);
+    let pad = cols.is_some() && matches!(edge, Edge::End);
+
+    let col = match (edge, cols) {
+        (Edge::Start, Some(cols)) => Some(cols.start),
+        (Edge::End, Some(cols)) => Some(cols.end),
+        (Edge::Start, None) => Some(0),
+        (Edge::End, None) => None,
+    };
+
+    ws.curr_mut().with_transaction(|tx, buf| {
+        let first = *lines.start();
+        let len = buf.line_len(first);
+        let start = buf.line_char(first);
+
+        let offset = match col {
+            Some(col) => match buf.col_of(first, len, tab_width) {
+                width if width >= col => buf.offset_at_col(first, col, tab_width),
+                width if pad => {
+                    tx.shift(start + len);
+                    tx.insert_str(start + len, &" ".repeat(col - width));
+                    tx.apply(buf.text_mut());
+                    buf.line_len(first)
+                }
+                _ => len,
+            },
+            None => len,
+        };
+
+        buf.clear_selection();
+        buf.set_mode(CursorMode::Insert);
+        buf.set_pos((first, offset));
+        buf.set_block_insert(BlockInsert {
+            lines: first + 1..=*lines.end(),
+            col,
+            start: start + offset,
+            pad,
+        });
+
+        TransactionResult::Keep
+    });
+}
+
+/// Repeats the text inserted on the first line of `block` on its other lines.
+pub(super) fn repeat_block_insert(
+    insert_tx: &mut Transaction,
+    buf: &mut Buffer,
+    block: BlockInsert,
+    tab_width: usize,
+) {
+    let pos = buf.as_char_pos();
+    let same_line = buf.text().char_to_line(pos) == buf.text().char_to_line(block.start);
+
+    if !same_line || pos <= block.start {
+        return;
+    }
+
+    let text = buf.text().slice(block.start..pos).to_string();
+    let mut tx = Transaction::new();
+
+    for index in block.lines.rev() {
+        let len = buf.line_len(index);
+
+        let (offset, padding) = match block.col {
+            Some(col) => match buf.col_of(index, len, tab_width) {
+                width if width >= col => (buf.offset_at_col(index, col, tab_width), 0),
+                width if block.pad => (len, col - width),
+                _ => continue,
+            },
+            None => (len, 0),
+        };
+
+        let at = buf.line_char(index) + offset;
+        tx.shift(at);
+        tx.insert_str(at, &format!("{}{text}", " ".repeat(padding)));
+    }
+
+    tx.shift(block.start);
+    tx.apply(buf.text_mut());
+    insert_tx.merge(tx);
+
+    buf.set_pos(buf.as_curs_pos(block.start));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_utils::{text, workspace},
+        transaction::Transaction,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_visual_delete() {
+        let mut ws = workspace("select a,\n  b\nfrom t");
+
+        ws.curr_mut().buf_mut().set_pos((0, 7));
+        visual_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((1, 2));
+        visual_delete(&mut ws, Context::default());
+
+        assert_eq!("select \nfrom t", text(&ws));
+        assert_eq!((0, 7), ws.curr().buf().pos());
+        assert_eq!(CursorMode::Normal, ws.curr().buf().mode());
+        assert_eq!(
+            Some(&Register::new("a,\n  b".into(), false)),
+            ws.registers().get('"')
+        );
+
+        ws.curr_mut().undo();
+        assert_eq!("select a,\n  b\nfrom t", text(&ws));
+
+        ws.curr_mut().buf_mut().set_pos((2, 2));
+        visual_line_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((1, 0));
+        visual_delete(&mut ws, Context::default());
+
+        assert_eq!("select a,", text(&ws));
+        assert_eq!((0, 0), ws.curr().buf().pos());
+        assert_eq!(
+            Some(&Register::new("  b\nfrom t\n".into(), true)),
+            ws.registers().get('"')
+        );
+    }
+
+    #[test]
+    fn test_visual_change() {
+        let mut ws = workspace("select a,\n  b\nfrom t");
+
+        ws.curr_mut().buf_mut().set_pos((1, 2));
+        visual_line_mode(&mut ws, Context::default());
+        visual_change(&mut ws, Context::default());
+
+        assert_eq!("select a,\n\nfrom t", text(&ws));
+        assert_eq!((1, 0), ws.curr().buf().pos());
+        assert!(ws.curr().buf().is_insert());
+
+        ws.curr_mut().with_transaction(|insert_tx, buf| {
+            let mut tx = Transaction::new();
+            tx.insert_str(buf.as_char_pos(), "c");
+            tx.apply(buf.text_mut());
+            insert_tx.merge(tx);
+            TransactionResult::Commit
+        });
+        assert_eq!("select a,\nc\nfrom t", text(&ws));
+
+        ws.curr_mut().undo();
+        assert_eq!("select a,\n  b\nfrom t", text(&ws));
+    }
+
+    #[test]
+    fn test_visual_yank() {
+        let mut ws = workspace("select a,\n  b\nfrom t");
+
+        ws.curr_mut().buf_mut().set_pos((1, 1));
+        visual_mode(&mut ws, Context::default());
+        visual_line_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((0, 3));
+        visual_yank(&mut ws, Context::default());
+
+        assert_eq!(
+            Some(&Register::new("select a,\n  b\n".into(), true)),
+            ws.registers().get('"')
+        );
+        assert_eq!((0, 0), ws.curr().buf().pos());
+        assert_eq!(None, ws.curr().buf().selection_range());
+    }
+
+    fn type_text(ws: &mut Workspace, text: &str) {
+        for ch in text.chars() {
+            crate::command::adjustment::insert_char(ws, ch);
+        }
+        crate::command::switch_mode::normal_mode_inplace(ws);
+    }
+
+    #[test]
+    fn test_block_delete() {
+        let mut ws = workspace("(1, 'a', x),\n(22, 'b', y),\n(3)");
+
+        ws.curr_mut().buf_mut().set_pos((0, 3));
+        visual_block_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((1, 7));
+        visual_delete(&mut ws, Context::default());
+
+        assert_eq!("(1, x),\n(22, y),\n(3)", text(&ws));
+        assert_eq!((0, 3), ws.curr().buf().pos());
+        assert_eq!(
+            Some(&Register::new(" 'a',\n, 'b'".into(), false)),
+            ws.registers().get('"')
+        );
+
+        ws.curr_mut().undo();
+        assert_eq!("(1, 'a', x),\n(22, 'b', y),\n(3)", text(&ws));
+    }
+
+    #[test]
+    fn test_block_insert() {
+        let mut ws = workspace("a,\nbb,\n\nc");
+
+        ws.curr_mut().buf_mut().set_pos((0, 1));
+        visual_block_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((3, 1));
+        visual_block_insert(&mut ws, Context::default());
+        type_text(&mut ws, "::int");
+
+        assert_eq!("a::int,\nb::intb,\n\nc::int", text(&ws));
+        assert_eq!((0, 1), ws.curr().buf().pos());
+
+        ws.curr_mut().undo();
+        assert_eq!("a,\nbb,\n\nc", text(&ws));
+
+        ws.curr_mut().buf_mut().set_pos((0, 0));
+        visual_block_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((3, 0));
+        visual_block_append(&mut ws, Context::default());
+        type_text(&mut ws, "|");
+
+        assert_eq!("a|,\nb|b,\n |\nc|", text(&ws));
+
+        ws.curr_mut().undo();
+        ws.curr_mut().buf_mut().set_pos((1, 0));
+        visual_line_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((2, 0));
+        visual_block_append(&mut ws, Context::default());
+        type_text(&mut ws, " --");
+
+        assert_eq!("a,\nbb, --\n --\nc", text(&ws));
+    }
+
+    #[test]
+    fn test_block_change() {
+        let mut ws = workspace("id int,\nname text,\nx");
+
+        ws.curr_mut().buf_mut().set_pos((0, 0));
+        visual_block_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((2, 0));
+        visual_change(&mut ws, Context::default());
+        type_text(&mut ws, "t.");
+
+        assert_eq!("t.d int,\nt.ame text,\nt.", text(&ws));
+
+        ws.curr_mut().undo();
+        assert_eq!("id int,\nname text,\nx", text(&ws));
+    }
+
+    #[test]
+    fn test_visual_indent() {
+        let mut ws = workspace("select\n\n  a,\n      b");
+
+        visual_line_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((2, 0));
+        visual_indent(&mut ws, Context::default());
+
+        assert_eq!("\tselect\n\n\t  a,\n      b", text(&ws));
+        assert_eq!((0, 1), ws.curr().buf().pos());
+
+        ws.curr_mut().buf_mut().set_pos((3, 0));
+        visual_mode(&mut ws, Context::default());
+        ws.curr_mut().buf_mut().set_pos((0, 3));
+        visual_dedent(&mut ws, Context::default());
+
+        assert_eq!("select\n\n  a,\n  b", text(&ws));
+
+        ws.curr_mut().undo();
+        assert_eq!("\tselect\n\n\t  a,\n      b", text(&ws));
+        assert_eq!((0, 0), ws.curr().buf().pos());
+    }
+}
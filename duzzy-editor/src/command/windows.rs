@@ -0,0 +1,102 @@
+use crate::{
+    editor::{Message, Workspace},
+    window::{Direction, Split},
+};
+
+use super::Context;
+
+pub(super) fn split_window(ws: &mut Workspace, _ctx: Context) {
+    split(ws, Split::Horizontal);
+}
+
+pub(super) fn vsplit_window(ws: &mut Workspace, _ctx: Context) {
+    split(ws, Split::Vertical);
+}
+
+pub(super) fn close_window(ws: &mut Workspace, _ctx: Context) {
+    if let Err(e) = ws.close_window() {
+        ws.set_message(Message::Error(format!("{e:#}")));
+    }
+}
+
+/// Closes every window but the focused one, the documents staying open.
+pub(super) fn only_window(ws: &mut Workspace, _ctx: Context) {
+    ws.windows_mut().only();
+}
+
+/// Focuses the window after the current one, or the one numbered by the count.
+pub(super) fn next_window(ws: &mut Workspace, ctx: Context) {
+    let windows = ws.windows();
+    let id = match ctx.raw_count() {
+        Some(count) => windows
+            .areas()
+            .get(count.saturating_sub(1))
+            .map(|(id, _)| *id),
+        None => Some(windows.next()),
+    };
+
+    if let Some(id) = id {
+        ws.focus_window(id);
+    }
+}
+
+pub(super) fn focus_left_window(ws: &mut Workspace, ctx: Context) {
+    focus(ws, Direction::Left, ctx.count());
+}
+
+pub(super) fn focus_down_window(ws: &mut Workspace, ctx: Context) {
+    focus(ws, Direction::Down, ctx.count());
+}
+
+pub(super) fn focus_up_window(ws: &mut Workspace, ctx: Context) {
+    focus(ws, Direction::Up, ctx.count());
+}
+
+pub(super) fn focus_right_window(ws: &mut Workspace, ctx: Context) {
+    focus(ws, Direction::Right, ctx.count());
+}
+
+pub(super) fn increase_height(ws: &mut Workspace, ctx: Context) {
+    resize(ws, Split::Horizontal, cells(ctx));
+}
+
+pub(super) fn decrease_height(ws: &mut Workspace, ctx: Context) {
+    resize(ws, Split::Horizontal, -cells(ctx));
+}
+
+pub(super) fn increase_width(ws: &mut Workspace, ctx: Context) {
+    resize(ws, Split::Vertical, cells(ctx));
+}
+
+pub(super) fn decrease_width(ws: &mut Workspace, ctx: Context) {
+    resize(ws, Split::Vertical, -cells(ctx));
+}
+
+pub(super) fn equalize_windows(ws: &mut Workspace, _ctx: Context) {
+    ws.windows_mut().equalize();
+}
+
+fn split(ws: &mut Workspace, split: Split) {
+    if let Err(e) = ws.split(split) {
+        ws.set_message(Message::Error(format!("{e:#}")));
+    }
+}
+
+/// Moves the focus `count` windows towards `direction`, as far as there are windows.
+fn focus(ws: &mut Workspace, direction: Direction, count: usize) {
+    for _ in 0..count {
+        match ws.windows().neighbour(direction) {
+            Some(id) => ws.focus_window(id),
+            None => break,
+        }
+    }
+}
+
+/// Cells to resize by, a count too large for `i32` saturating like the other counts.
+fn cells(ctx: Context) -> i32 {
+    i32::try_from(ctx.count()).unwrap_or(i32::MAX)
+}
+
+fn resize(ws: &mut Workspace, split: Split, delta: i32) {
+    ws.windows_mut().resize(split, delta);
+}
@@ -1,11 +1,13 @@
 use std::{
-    fs::File,
-    io::BufReader,
+    borrow::Cow,
+    fs::{self, File, OpenOptions, Permissions},
+    io::{BufReader, BufWriter, Write},
     num::NonZeroUsize,
     path::{Path, PathBuf},
     sync::atomic::{AtomicUsize, Ordering},
 };
 
+use anyhow::Context;
 use ropey::Rope;
 
 use crate::{
@@ -18,7 +20,7 @@ use crate::{
 pub struct DocumentId(NonZeroUsize);
 
 impl DocumentId {
-    pub const MAX: Self = Self(unsafe { NonZeroUsize::new_unchecked(usize::MAX) });
+    pub const MAX: Self = Self(NonZeroUsize::new(usize::MAX).unwrap());
 
     pub fn next() -> Self {
         pub static IDS: AtomicUsize = AtomicUsize::new(1);
@@ -42,6 +44,15 @@ pub struct FileMeta {
     pub readonly: bool,
 }
 
+impl FileMeta {
+    pub fn name(&self) -> Cow<'_, str> {
+        match &self.path {
+            Some(path) => path.to_string_lossy(),
+            None => Cow::Borrowed("[scratch]"),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Document {
     id: DocumentId,
@@ -50,6 +61,8 @@ pub struct Document {
     buffer: Buffer,
     history: History,
     transaction: Option<Transaction>,
+    /// Changes applied to the text since they were last taken, for other views to follow.
+    changes: Vec<Transaction>,
 }
 
 impl Document {
@@ -83,11 +96,15 @@ impl Document {
         self.id
     }
 
+    pub const fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
     pub const fn buf(&self) -> &Buffer {
         &self.buffer
     }
 
-    pub fn buf_mut(&mut self) -> &mut Buffer {
+    pub const fn buf_mut(&mut self) -> &mut Buffer {
         &mut self.buffer
     }
 
@@ -101,7 +118,7 @@ impl Document {
         };
 
         match func(&mut tx, &mut self.buffer) {
-            TransactionResult::Commit => self.history.commit(tx),
+            TransactionResult::Commit => self.record(tx),
             TransactionResult::Keep => self.transaction = Some(tx),
             TransactionResult::Abort => (),
         }
@@ -109,15 +126,205 @@ impl Document {
 
     pub fn commit(&mut self) {
         if let Some(tx) = self.transaction.take() {
-            self.history.commit(tx);
+            self.record(tx);
         }
     }
 
     pub fn undo(&mut self) -> Option<usize> {
-        self.history.undo(self.buffer.text_mut())
+        let tx = self.history.undo()?;
+        let pos = tx.apply(self.buffer.text_mut());
+
+        self.changes.push(tx);
+        pos
     }
 
     pub fn redo(&mut self) -> Option<usize> {
-        self.history.redo(self.buffer.text_mut())
+        let tx = self.history.redo()?;
+        let pos = tx.apply(self.buffer.text_mut());
+
+        self.changes.push(tx);
+        pos
+    }
+
+    /// Changes applied to the text since the last call, in order.
+    pub fn take_changes(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.changes)
+    }
+
+    fn record(&mut self, tx: Transaction) {
+        if tx.has_changes() {
+            self.changes.push(tx.clone());
+        }
+        self.history.commit(tx);
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified()
+            || self
+                .transaction
+                .as_ref()
+                .is_some_and(Transaction::has_changes)
+    }
+
+    pub fn save(&mut self, force: bool) -> anyhow::Result<()> {
+        let path = self.meta.path.clone().context("No file name")?;
+
+        if self.meta.readonly && !force {
+            anyhow::bail!("{} is readonly (force to override)", path.display());
+        }
+
+        self.write(&path)
+    }
+
+    pub fn save_as(&mut self, path: impl AsRef<Path>, force: bool) -> anyhow::Result<()> {
+        let path = path.as_ref();
+
+        let readonly = path
+            .metadata()
+            .is_ok_and(|metadata| metadata.permissions().readonly());
+
+        if readonly && !force {
+            anyhow::bail!("{} is readonly (force to override)", path.display());
+        }
+
+        self.write(path)?;
+        self.meta = FileMeta {
+            path: Some(path.into()),
+            readonly,
+        };
+
+        Ok(())
+    }
+
+    fn write(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.commit();
+
+        // write through symlinks instead of replacing them with a regular file
+        let target = path.canonicalize().unwrap_or_else(|_| path.into());
+
+        let permissions = match target.metadata() {
+            Ok(metadata) if !metadata.is_file() => {
+                anyhow::bail!("Not a file: {}", path.display())
+            }
+            Ok(metadata) => Some(metadata.permissions()),
+            Err(_) => None,
+        };
+
+        write_atomic(self.buffer.text(), &target, permissions)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        self.history.mark_saved();
+        Ok(())
+    }
+}
+
+/// Writes `text` into a temporary file next to `path` and renames it over
+/// the original, so a failed write never leaves a truncated file behind.
+fn write_atomic(text: &Rope, path: &Path, permissions: Option<Permissions>) -> anyhow::Result<()> {
+    let name = path.file_name().context("Invalid file name")?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = dir.join(tmp_name);
+
+    let result = (|| -> anyhow::Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp)?;
+
+        let mut writer = BufWriter::new(file);
+        text.write_to(&mut writer)?;
+        writer.flush()?;
+        writer.into_inner()?.sync_all()?;
+
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&tmp, permissions)?;
+        }
+
+        fs::rename(&tmp, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        fs::remove_file(&tmp).ok();
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("duzzy-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        dir.join(name)
+    }
+
+    #[test]
+    fn test_save() {
+        let path = temp_path("save.sql");
+        fs::write(&path, "select 1;").unwrap();
+
+        let mut doc = Document::from_path(&path).unwrap();
+        assert!(!doc.is_modified());
+
+        doc.with_transaction(|tx, buf| {
+            tx.insert_str(0, "-- query\n");
+            tx.apply(buf.text_mut());
+            TransactionResult::Keep
+        });
+        assert!(doc.is_modified());
+
+        doc.save(false).unwrap();
+        assert!(!doc.is_modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "-- query\nselect 1;");
+
+        doc.undo();
+        assert!(doc.is_modified());
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_save_scratch() {
+        let mut doc = Document::default();
+        assert!(doc.save(false).is_err());
+
+        let path = temp_path("scratch.sql");
+        doc.buf_mut().text_mut().insert(0, "select 2;");
+        doc.save_as(&path, false).unwrap();
+
+        assert_eq!(doc.meta().path.as_deref(), Some(path.as_path()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "select 2;");
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_save_readonly() {
+        let path = temp_path("readonly.sql");
+        fs::write(&path, "select 3;").unwrap();
+
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        let mut doc = Document::from_path(&path).unwrap();
+        doc.buf_mut().text_mut().insert(0, "-- ");
+
+        assert!(doc.save(false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "select 3;");
+
+        doc.save(true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "-- select 3;");
+        assert!(fs::metadata(&path).unwrap().permissions().readonly());
+        fs::remove_dir_all(path.parent().unwrap()).ok();
     }
 }
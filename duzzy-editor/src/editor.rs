@@ -1,10 +1,22 @@
-use std::{collections::HashMap, path::Path};
+use std::{collections::BTreeMap, path::Path};
+
+use ratatui::layout::Rect;
 
 use crate::{
-    command::{insert_mode, CommandFinder},
+    buffer::{CursorMode, ViewState},
+    clipboard::ClipboardProvider,
+    command::{command_mode, documents, insert_mode, search, substitute, CharFind, CommandFinder},
     document::{Document, DocumentId},
+    input::{Event, Input},
     keymap::Keymaps,
+    options::Options,
+    picker::DocumentPicker,
+    prompt::Prompt,
+    register::Registers,
     renderer::{Cursor, EventOutcome, Renderer, Viewport},
+    search::{Search, SearchPrompt},
+    substitute::Substitution,
+    window::{Split, View, ViewId, Windows},
 };
 
 pub struct DuzzyEditor {
@@ -16,17 +28,20 @@ pub struct DuzzyEditor {
 
 impl DuzzyEditor {
     pub fn new(width: usize, height: usize) -> Self {
+        let viewport = Viewport::new(width, height);
+        let mut workspace = Workspace::new();
+        workspace.set_area(viewport.tabs_area());
+
         Self {
-            workspace: Workspace::new(),
+            workspace,
             keymaps: Keymaps::init(),
             command: CommandFinder::default(),
-            viewport: Viewport { width, height },
+            viewport,
         }
     }
 
     pub fn open_file(&mut self, filepath: impl AsRef<Path>) -> anyhow::Result<()> {
-        let doc = Document::from_path(filepath)?;
-        self.workspace.add_doc(doc);
+        self.workspace.open(filepath)?;
         Ok(())
     }
 
@@ -42,16 +57,83 @@ impl DuzzyEditor {
         (self.viewport.width, self.viewport.height)
     }
 
+    pub const fn options_mut(&mut self) -> &mut Options {
+        self.workspace.options_mut()
+    }
+
+    /// Clipboard behind the `+` and `*` registers.
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn ClipboardProvider>) {
+        self.workspace.registers_mut().set_clipboard(clipboard);
+    }
+
+    /// Escape sequences to write to the terminal after an event, like the ones
+    /// copying to the clipboard through it.
+    pub fn take_terminal_output(&mut self) -> Option<String> {
+        self.workspace.registers_mut().take_clipboard_output()
+    }
+
+    /// Width of the text area of the focused window, right to the gutter.
+    pub fn text_width(&self) -> usize {
+        let width = self.text_area().width as usize;
+        width.saturating_sub(self.gutter_width())
+    }
+
+    /// Width of the line number gutter drawn left to the text of the focused window.
+    pub fn gutter_width(&self) -> usize {
+        let width = self.text_area().width as usize;
+        self.window_gutter_width(self.workspace.curr().buf().len_lines(), width)
+    }
+
+    /// Width of the gutter of a window `width` columns wide showing `len_lines` lines.
+    pub(crate) fn window_gutter_width(&self, len_lines: usize, width: usize) -> usize {
+        let gutter = self.workspace.options().line_numbers.width(len_lines);
+
+        // keep at least one column for the text itself
+        gutter.min(width.saturating_sub(1))
+    }
+
+    /// Screen area of the focused window showing text, the gutter included.
+    fn text_area(&self) -> Rect {
+        let windows = self.workspace.windows();
+        windows.inner(windows.focused_area())
+    }
+
     pub fn cursor(&self) -> Cursor {
         let buf = self.workspace.curr().buf();
         let mode = buf.mode();
 
-        let (mut y, mut x) = buf.pos();
+        if buf.is_command() {
+            let x = self.workspace.prompt().cursor_width() + 1;
+
+            return Cursor {
+                x: x.min(self.viewport.width.saturating_sub(1)) as _,
+                y: (self.viewport.height + Viewport::RESERVED_ROWS - 1) as _,
+                mode,
+            };
+        }
+
+        let area = self.text_area();
+        let width = self.text_width();
+        let tab_width = self.workspace.options().tab_width;
+
+        let (mut y, mut x) = if self.workspace.options().wrap {
+            let (row, col) = buf.wrapped_pos(width, tab_width);
+            let above: usize = (buf.vscroll()..buf.index())
+                .map(|index| buf.line_rows(index, width, tab_width))
+                .sum();
 
-        x = x.min(self.viewport.width - 1);
-        y = y
-            .saturating_sub(buf.vscroll())
-            .min(self.viewport.height - 1);
+            (above + row, col)
+        } else {
+            let (index, offset) = buf.pos();
+            (
+                index.saturating_sub(buf.vscroll()),
+                buf.col_of(index, offset, tab_width)
+                    .saturating_sub(buf.hscroll()),
+            )
+        };
+
+        x = x.min(width.saturating_sub(1)) + self.gutter_width() + area.x as usize;
+        y = y.min((area.height as usize).saturating_sub(1)) + area.y as usize;
 
         Cursor {
             x: x as _,
@@ -60,44 +142,143 @@ impl DuzzyEditor {
         }
     }
 
+    pub(crate) fn pending_keys(&self) -> &[Input] {
+        self.command.pending()
+    }
+
     pub fn on_event(&mut self, event: crossterm::event::Event) -> EventOutcome {
         if let crossterm::event::Event::Resize(width, height) = event {
             self.viewport.update(width as _, height as _);
+            self.workspace.set_area(self.viewport.tabs_area());
             return EventOutcome::Render;
         }
 
-        let crossterm::event::Event::Key(e) = event else {
-            return EventOutcome::Ignore;
+        self.workspace.clear_message();
+        self.workspace.set_text_width(self.text_width());
+
+        let outcome = match event {
+            crossterm::event::Event::Key(_) | crossterm::event::Event::Paste(_) => {
+                self.on_key(event.into())
+            }
+            _ => return EventOutcome::Ignore,
         };
 
-        let input = e.into();
+        self.workspace.follow_changes();
+
+        if let Some(quit) = self.workspace.take_quit() {
+            return self.quit(quit);
+        }
+
+        if matches!(outcome, EventOutcome::Render) {
+            let height = self.text_area().height as usize;
+            let width = self.text_width();
+            let options = self.workspace.options();
+            let (wrap, tab_width) = (options.wrap, options.tab_width);
+            let buf = self.workspace.curr_mut().buf_mut();
+
+            if wrap {
+                buf.update_wrapped_vscroll(height, width, tab_width);
+            } else {
+                buf.update_vscroll(height);
+                buf.update_hscroll(width, tab_width);
+            }
+        }
+
+        outcome
+    }
+
+    fn on_key(&mut self, input: Input) -> EventOutcome {
+        if self.workspace.substitution().is_some() {
+            return substitute::on_key(&mut self.workspace, input);
+        }
+
+        if self.workspace.picker().is_some() {
+            return documents::on_picker_key(&mut self.workspace, input);
+        }
+
         let buf = self.workspace.curr().buf();
-        let command = self.command.find(self.keymaps, buf, input);
+        let action = self.command.find(self.keymaps, buf, &input);
 
-        let outcome = match command {
-            Some(command) => {
-                command.call(&mut self.workspace);
+        match action {
+            Some(action) => {
+                action.call(&mut self.workspace);
                 self.command.reset();
                 EventOutcome::Render
             }
             None if buf.is_insert() => insert_mode::on_key(&mut self.workspace, input),
+            // pasting in normal mode inserts the text as a change of its own
+            None if buf.mode() == CursorMode::Normal && matches!(input.event, Event::Paste(_)) => {
+                insert_mode::on_key(&mut self.workspace, input)
+            }
+            None if buf.is_command() && self.workspace.search_prompt().is_some() => {
+                search::on_key(&mut self.workspace, input)
+            }
+            None if buf.is_command() => {
+                command_mode::on_key(&mut self.workspace, self.command.registry(), input)
+            }
             _ => EventOutcome::Ignore,
-        };
+        }
+    }
 
-        if matches!(outcome, EventOutcome::Render) {
-            self.workspace
-                .curr_mut()
-                .buf_mut()
-                .update_vscroll(self.viewport.height);
+    fn quit(&mut self, quit: Quit) -> EventOutcome {
+        if quit == Quit::Force {
+            return EventOutcome::Exit;
         }
 
-        outcome
+        match self.workspace.modified() {
+            Some(doc) => {
+                let message = format!(
+                    "No write since last change for {} (force quit to override)",
+                    doc.meta().name()
+                );
+                self.workspace.set_message(Message::Error(message));
+                EventOutcome::Render
+            }
+            None => EventOutcome::Exit,
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quit {
+    Safe,
+    Force,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Info(String),
+    Error(String),
+}
+
 pub struct Workspace {
-    documents: HashMap<DocumentId, Document>,
+    /// Open documents, in the order they were opened as ids only grow.
+    documents: BTreeMap<DocumentId, Document>,
     current: DocumentId,
+    /// Document current before the last switch, gone back to by `ga`.
+    alternate: Option<DocumentId>,
+    picker: Option<DocumentPicker>,
+    /// Tab pages, each with windows splitting the screen between views of the documents.
+    tabs: Vec<Windows>,
+    /// Current tab page, whose focused window shows the current document.
+    tab: usize,
+    /// Screen area of the tab pages, the tab line included.
+    area: Rect,
+    message: Option<Message>,
+    quit: Option<Quit>,
+    cmdline: Prompt,
+    /// Search typed after `/` or `?`, with a history apart from the command line.
+    search_line: Prompt,
+    search_prompt: Option<SearchPrompt>,
+    /// Last search, repeated by `n` and `N`.
+    search: Option<Search>,
+    /// Substitution waiting for each replacement to be confirmed.
+    substitution: Option<Substitution>,
+    options: Options,
+    registers: Registers,
+    /// Last `f`, `t`, `F` or `T`, repeated by `;` and `,`.
+    char_find: Option<CharFind>,
+    text_width: usize,
 }
 
 impl Default for Workspace {
@@ -109,15 +290,289 @@ impl Default for Workspace {
 impl Workspace {
     pub fn new() -> Self {
         Self {
-            documents: HashMap::new(),
+            documents: BTreeMap::new(),
             current: DocumentId::MAX,
+            alternate: None,
+            picker: None,
+            tabs: vec![Windows::new(DocumentId::MAX)],
+            tab: 0,
+            area: Rect::default(),
+            message: None,
+            quit: None,
+            cmdline: Prompt::default(),
+            search_line: Prompt::default(),
+            search_prompt: None,
+            search: None,
+            substitution: None,
+            options: Options::default(),
+            registers: Registers::default(),
+            char_find: None,
+            text_width: usize::MAX,
         }
     }
 
     pub(super) fn add_doc(&mut self, doc: Document) {
         let id = doc.id();
         self.documents.insert(id, doc);
+        self.switch(id);
+    }
+
+    /// Opens `path`, switching to it if it's already open.
+    pub fn open(&mut self, path: impl AsRef<Path>) -> anyhow::Result<DocumentId> {
+        let path = path.as_ref();
+
+        let opened = self
+            .documents
+            .values()
+            .find(|doc| doc.meta().path.as_deref() == Some(path))
+            .map(Document::id);
+
+        let id = match opened {
+            Some(id) => id,
+            None => {
+                let doc = Document::from_path(path)?;
+                let id = doc.id();
+                self.add_doc(doc);
+                id
+            }
+        };
+
+        self.switch(id);
+        Ok(id)
+    }
+
+    /// Makes document `id` the current one shown in the focused window,
+    /// the one it replaces becoming the alternate.
+    pub fn switch(&mut self, id: DocumentId) {
+        if id == self.current || !self.documents.contains_key(&id) {
+            return;
+        }
+
+        if self.documents.contains_key(&self.current) {
+            self.alternate = Some(self.current);
+        }
         self.current = id;
+        self.windows_mut().focused_mut().doc = id;
+    }
+
+    /// Closes document `id`, switching to the alternate one when it's the current one,
+    /// or to the nearest one in order, or to a new scratch when it was the last.
+    pub fn close(&mut self, id: DocumentId) {
+        if self.documents.remove(&id).is_none() {
+            return;
+        }
+
+        if self.alternate == Some(id) {
+            self.alternate = None;
+        }
+
+        if id == self.current {
+            let fallback = self.alternate.take().or_else(|| {
+                let next = self.documents.range(id..).next();
+                let prev = self.documents.range(..id).next_back();
+                next.or(prev).map(|(id, _)| *id)
+            });
+
+            match fallback {
+                Some(fallback) => self.current = fallback,
+                None => self.add_doc(Document::default()),
+            }
+        }
+
+        // windows showing the closed document show the current one from the top
+        let views = self.tabs.iter_mut().flat_map(Windows::views_mut);
+        for view in views.filter(|view| view.doc == id) {
+            *view = View {
+                doc: self.current,
+                state: ViewState::default(),
+            };
+        }
+    }
+
+    /// Windows of the current tab page.
+    pub fn windows(&self) -> &Windows {
+        &self.tabs[self.tab]
+    }
+
+    pub fn windows_mut(&mut self) -> &mut Windows {
+        &mut self.tabs[self.tab]
+    }
+
+    /// Splits the focused window, the new one showing the same view and taking the focus.
+    pub fn split(&mut self, split: Split) -> anyhow::Result<()> {
+        self.save_view();
+        self.windows_mut().split(split)?;
+        Ok(())
+    }
+
+    /// Closes the focused window, the focus going to a neighbour.
+    pub fn close_window(&mut self) -> anyhow::Result<()> {
+        let focus = self.windows().focus();
+        self.windows_mut().close(focus)?;
+        self.load_view();
+        Ok(())
+    }
+
+    /// Moves the focus to window `id`, keeping the view of the window left.
+    pub fn focus_window(&mut self, id: ViewId) {
+        if id == self.windows().focus() || self.windows().view(id).is_none() {
+            return;
+        }
+
+        self.save_view();
+        self.windows_mut().set_focus(id);
+        self.load_view();
+    }
+
+    pub fn tabs(&self) -> &[Windows] {
+        &self.tabs
+    }
+
+    /// Index of the current tab page, from 0.
+    pub const fn tab(&self) -> usize {
+        self.tab
+    }
+
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+        self.layout();
+    }
+
+    /// Opens a tab page after the current one, its window showing the view of the focused one.
+    pub fn new_tab(&mut self) {
+        self.save_view();
+
+        let mut windows = Windows::new(self.current);
+        windows.focused_mut().state = self.curr().buf().view_state();
+
+        self.tab += 1;
+        self.tabs.insert(self.tab, windows);
+        self.layout();
+    }
+
+    /// Closes the current tab page, the next one becoming current, or the previous for the last.
+    pub fn close_tab(&mut self) -> anyhow::Result<()> {
+        if self.tabs.len() == 1 {
+            anyhow::bail!("Cannot close last tab page");
+        }
+
+        self.tabs.remove(self.tab);
+        self.tab = self.tab.min(self.tabs.len() - 1);
+        self.layout();
+        self.load_view();
+
+        Ok(())
+    }
+
+    /// Closes every tab page but the current one, the documents staying open.
+    pub fn only_tab(&mut self) {
+        let windows = self.tabs.swap_remove(self.tab);
+        self.tabs = vec![windows];
+        self.tab = 0;
+        self.layout();
+    }
+
+    /// Makes tab page `index` the current one, keeping the view of the one left.
+    pub fn go_to_tab(&mut self, index: usize) {
+        if index == self.tab || index >= self.tabs.len() {
+            return;
+        }
+
+        self.save_view();
+        self.tab = index;
+        self.load_view();
+    }
+
+    /// Moves the current tab page to `index`, the last position when past the end.
+    pub fn move_tab(&mut self, index: usize) {
+        let index = index.min(self.tabs.len() - 1);
+        let windows = self.tabs.remove(self.tab);
+
+        self.tabs.insert(index, windows);
+        self.tab = index;
+    }
+
+    /// Gives the windows of every tab page the area under the tab line,
+    /// shown while there's more than one tab page.
+    fn layout(&mut self) {
+        let area = match self.tabs.len() {
+            1 => self.area,
+            _ => Rect {
+                y: self.area.y + 1,
+                height: self.area.height.saturating_sub(1),
+                ..self.area
+            },
+        };
+
+        for windows in &mut self.tabs {
+            windows.set_area(area);
+        }
+    }
+
+    /// Moves the views kept by the windows along with the changes made to their documents,
+    /// the focused one following them in the buffer itself.
+    pub fn follow_changes(&mut self) {
+        for document in self.documents.values_mut() {
+            let changes = document.take_changes();
+            if changes.is_empty() {
+                continue;
+            }
+
+            let views = self.tabs.iter_mut().flat_map(Windows::views_mut);
+            for view in views.filter(|view| view.doc == document.id()) {
+                for tx in &changes {
+                    view.state.map(tx);
+                }
+            }
+        }
+    }
+
+    /// Keeps the view shown in the buffer of the current document in the focused window,
+    /// ending the pending change so the views of other windows can follow it.
+    fn save_view(&mut self) {
+        self.curr_mut().commit();
+        self.follow_changes();
+
+        let state = self.curr().buf().view_state();
+        self.windows_mut().focused_mut().state = state;
+    }
+
+    /// Shows the view of the focused window in the buffer of its document.
+    fn load_view(&mut self) {
+        let View { doc, state } = self.windows().focused().clone();
+        if let Some(document) = self.documents.get_mut(&doc) {
+            document.buf_mut().set_view_state(state);
+            self.current = doc;
+        }
+    }
+
+    pub fn documents(&self) -> impl Iterator<Item = &Document> {
+        self.documents.values()
+    }
+
+    pub fn document(&self, id: DocumentId) -> Option<&Document> {
+        self.documents.get(&id)
+    }
+
+    pub const fn current(&self) -> DocumentId {
+        self.current
+    }
+
+    pub const fn alternate(&self) -> Option<DocumentId> {
+        self.alternate
+    }
+
+    /// Document picker shown over the text, taking the keys while it's open.
+    pub const fn picker(&self) -> Option<&DocumentPicker> {
+        self.picker.as_ref()
+    }
+
+    pub const fn picker_mut(&mut self) -> Option<&mut DocumentPicker> {
+        self.picker.as_mut()
+    }
+
+    pub fn set_picker(&mut self, picker: Option<DocumentPicker>) {
+        self.picker = picker;
     }
 
     pub fn curr(&self) -> &Document {
@@ -129,4 +584,893 @@ impl Workspace {
             .get_mut(&self.current)
             .expect("current mut doc")
     }
+
+    pub fn modified(&self) -> Option<&Document> {
+        self.documents.values().find(|doc| doc.is_modified())
+    }
+
+    pub const fn request_quit(&mut self, quit: Quit) {
+        self.quit = Some(quit);
+    }
+
+    pub const fn take_quit(&mut self) -> Option<Quit> {
+        self.quit.take()
+    }
+
+    pub const fn cmdline(&self) -> &Prompt {
+        &self.cmdline
+    }
+
+    pub const fn cmdline_mut(&mut self) -> &mut Prompt {
+        &mut self.cmdline
+    }
+
+    pub const fn search_line_mut(&mut self) -> &mut Prompt {
+        &mut self.search_line
+    }
+
+    /// Search being typed, `None` while the command line is open instead.
+    pub const fn search_prompt(&self) -> Option<SearchPrompt> {
+        self.search_prompt
+    }
+
+    pub const fn set_search_prompt(&mut self, prompt: Option<SearchPrompt>) {
+        self.search_prompt = prompt;
+    }
+
+    /// Prompt shown at the bottom in command mode.
+    pub const fn prompt(&self) -> &Prompt {
+        match self.search_prompt {
+            Some(_) => &self.search_line,
+            None => &self.cmdline,
+        }
+    }
+
+    pub const fn search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    pub fn set_search(&mut self, search: Search) {
+        self.search = Some(search);
+    }
+
+    pub const fn substitution(&self) -> Option<&Substitution> {
+        self.substitution.as_ref()
+    }
+
+    pub fn set_substitution(&mut self, substitution: Option<Substitution>) {
+        self.substitution = substitution;
+    }
+
+    pub const fn take_substitution(&mut self) -> Option<Substitution> {
+        self.substitution.take()
+    }
+
+    pub const fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub const fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    pub const fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub const fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
+    pub const fn char_find(&self) -> Option<CharFind> {
+        self.char_find
+    }
+
+    pub const fn set_char_find(&mut self, find: CharFind) {
+        self.char_find = Some(find);
+    }
+
+    /// Width of the text area of the current document, used to move over wrapped lines.
+    pub const fn text_width(&self) -> usize {
+        self.text_width
+    }
+
+    pub const fn set_text_width(&mut self, width: usize) {
+        self.text_width = width;
+    }
+
+    pub const fn message(&self) -> Option<&Message> {
+        self.message.as_ref()
+    }
+
+    pub fn set_message(&mut self, message: Message) {
+        self.message = Some(message);
+    }
+
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{Event, KeyCode, KeyModifiers};
+
+    use crate::{
+        clipboard::{ClipboardKind, FakeClipboard},
+        register::Register,
+        test_utils::{key, keys},
+    };
+
+    use super::*;
+
+    fn command(editor: &mut DuzzyEditor, line: &str) -> EventOutcome {
+        key(editor, KeyCode::Char(':'), KeyModifiers::NONE);
+        for ch in line.chars() {
+            key(editor, KeyCode::Char(ch), KeyModifiers::NONE);
+        }
+        key(editor, KeyCode::Enter, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_quit_modified() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        let outcome = key(&mut editor, KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert_eq!(EventOutcome::Exit, outcome);
+
+        key(&mut editor, KeyCode::Char('i'), KeyModifiers::NONE);
+        key(&mut editor, KeyCode::Char('x'), KeyModifiers::NONE);
+
+        let outcome = key(&mut editor, KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert_eq!(EventOutcome::Render, outcome);
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+
+        key(&mut editor, KeyCode::Esc, KeyModifiers::NONE);
+        key(&mut editor, KeyCode::Char('u'), KeyModifiers::NONE);
+
+        let outcome = key(&mut editor, KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert_eq!(EventOutcome::Exit, outcome);
+    }
+
+    #[test]
+    fn test_force_quit() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        key(&mut editor, KeyCode::Char('o'), KeyModifiers::NONE);
+        key(&mut editor, KeyCode::Esc, KeyModifiers::NONE);
+
+        key(&mut editor, KeyCode::Char('Z'), KeyModifiers::SHIFT);
+        let outcome = key(&mut editor, KeyCode::Char('Q'), KeyModifiers::SHIFT);
+        assert_eq!(EventOutcome::Exit, outcome);
+    }
+
+    fn text(editor: &DuzzyEditor) -> String {
+        editor.workspace.curr().buf().text().to_string()
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(&mut editor, "iselect a\rfrom t\rwhere x\x1bgg");
+
+        keys(&mut editor, "dd");
+        assert_eq!("from t\nwhere x", text(&editor));
+
+        keys(&mut editor, "gUgl");
+        assert_eq!("FROM T\nwhere x", text(&editor));
+
+        keys(&mut editor, "jguu");
+        assert_eq!("FROM T\nwhere x", text(&editor));
+
+        keys(&mut editor, "d");
+        assert_eq!("d", editor.pending_keys()[0].to_string());
+        keys(&mut editor, "\x1b");
+        assert!(editor.pending_keys().is_empty());
+
+        keys(&mut editor, "dk");
+        assert_eq!("", text(&editor));
+
+        keys(&mut editor, "u");
+        assert_eq!("FROM T\nwhere x", text(&editor));
+
+        keys(&mut editor, "ggllcglOM t\x1b");
+        assert_eq!("FROM t\nwhere x", text(&editor));
+
+        keys(&mut editor, "u");
+        assert_eq!("FROM T\nwhere x", text(&editor));
+
+        keys(&mut editor, "jyk>>");
+        assert_eq!("\tFROM T\nwhere x", text(&editor));
+        assert_eq!(
+            Some(&Register::new("FROM T\nwhere x\n".into(), true)),
+            editor.workspace.registers().get('"')
+        );
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(&mut editor, "ia\rb\rc\rd\re\rf\rselect name from t\x1bgg");
+
+        keys(&mut editor, "5j");
+        assert_eq!((5, 0), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "2gg");
+        assert_eq!((1, 0), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "3");
+        assert_eq!("3", editor.pending_keys()[0].to_string());
+        keys(&mut editor, "dd");
+        assert_eq!("a\ne\nf\nselect name from t", text(&editor));
+        assert!(editor.pending_keys().is_empty());
+
+        keys(&mut editor, "d2j");
+        assert_eq!("a", text(&editor));
+
+        keys(&mut editor, "uG10x");
+        assert_eq!("a\ne\nf\ne from t", text(&editor));
+
+        keys(&mut editor, "2u");
+        assert_eq!("a\nb\nc\nd\ne\nf\nselect name from t", text(&editor));
+
+        keys(&mut editor, "G3l2d2l");
+        assert_eq!("a\nb\nc\nd\ne\nf\nselname from t", text(&editor));
+
+        keys(&mut editor, "20x");
+        assert_eq!("a\nb\nc\nd\ne\nf\nsel", text(&editor));
+    }
+
+    #[test]
+    fn test_huge_count() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+        keys(&mut editor, "ia\rb\rc\rd\x1bgg");
+
+        // counts saturate instead of overflowing past the cursor line
+        let count = "99999999999999999999999";
+
+        keys(&mut editor, &format!("j{count}j"));
+        assert_eq!((3, 0), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, &format!("ggj{count}>>"));
+        assert_eq!("a\n\tb\n\tc\n\td", text(&editor));
+
+        keys(&mut editor, &format!("{count}yyP"));
+        assert_eq!("a\n\tb\n\tc\n\td\n\tb\n\tc\n\td", text(&editor));
+
+        keys(&mut editor, &format!("ggj{count}dd"));
+        assert_eq!("a", text(&editor));
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(
+            &mut editor,
+            "iselect t.id, \"order id\"\rfrom t\r\rwhere x\x1bgg",
+        );
+
+        keys(&mut editor, "3w");
+        assert_eq!((0, 9), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "2wgeB");
+        assert_eq!((0, 7), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "Wdw");
+        assert_eq!("select t.id, \nfrom t\n\nwhere x", text(&editor));
+
+        keys(&mut editor, "ggcwupdate\x1b");
+        assert_eq!("update t.id, \nfrom t\n\nwhere x", text(&editor));
+
+        keys(&mut editor, "jghd}");
+        assert_eq!("update t.id, \n\n\nwhere x", text(&editor));
+
+        keys(&mut editor, "}");
+        assert_eq!((3, 6), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "{{d2e");
+        assert_eq!(".id, \n\n\nwhere x", text(&editor));
+    }
+
+    #[test]
+    fn test_char_find() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(&mut editor, "iselect a, b, count(col), d\x1bgh");
+
+        keys(&mut editor, "2f,");
+        assert_eq!((0, 11), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, ";,");
+        assert_eq!((0, 11), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "t");
+        assert_eq!("t", editor.pending_keys()[0].to_string());
+        keys(&mut editor, "(");
+        assert_eq!((0, 17), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "f(lct)x\x1b");
+        assert_eq!("select a, b, count(x), d", text(&editor));
+
+        keys(&mut editor, "dF,");
+        assert_eq!("select a, b), d", text(&editor));
+
+        keys(&mut editor, "ghd2f,");
+        assert_eq!(" d", text(&editor));
+    }
+
+    #[test]
+    fn test_text_objects() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(
+            &mut editor,
+            "iselect count(a, b) from t;\rselect 'x y' from u;\x1b",
+        );
+
+        keys(&mut editor, "ggwwci(*\x1b");
+        assert_eq!(
+            "select count(*) from t;\nselect 'x y' from u;",
+            text(&editor)
+        );
+
+        keys(&mut editor, "wdaw");
+        assert_eq!("select count(*) t;\nselect 'x y' from u;", text(&editor));
+
+        keys(&mut editor, "jghvi'y");
+        assert_eq!(
+            Some(&Register::new("x y".into(), false)),
+            editor.workspace.registers().get('"')
+        );
+
+        keys(&mut editor, "ggdas");
+        assert_eq!("select 'x y' from u;", text(&editor));
+
+        keys(&mut editor, "f;hciwv\x1b");
+        assert_eq!("select 'x y' from v;", text(&editor));
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(&mut editor, "iselect a\rfrom t\rwhere x\x1bgg");
+
+        keys(&mut editor, "\"ayyj\"Aye");
+        assert_eq!(
+            Some(&Register::new("select a\nfrom\n".into(), true)),
+            editor.workspace.registers().get('a')
+        );
+
+        keys(&mut editor, "Gdd");
+        assert_eq!("select a\nfrom t", text(&editor));
+
+        keys(&mut editor, "ggP");
+        assert_eq!("where x\nselect a\nfrom t", text(&editor));
+
+        keys(&mut editor, "x\"_ddp");
+        assert_eq!("swelect a\nfrom t", text(&editor));
+        assert_eq!((0, 1), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "u\"1p");
+        assert_eq!("select a\nwhere x\nfrom t", text(&editor));
+
+        keys(&mut editor, "Gx2p");
+        assert_eq!("select a\nwhere x\nrffom t", text(&editor));
+        assert_eq!(
+            Some(&Register::new("f".into(), false)),
+            editor.workspace.registers().get('-')
+        );
+    }
+
+    #[test]
+    fn test_clipboard_registers() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(&mut editor, "iselect a\rfrom t\x1bgg\"+yy");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+
+        let mut clipboard = FakeClipboard::default();
+        clipboard
+            .contents
+            .insert(ClipboardKind::Selection, "where x\n".into());
+        editor.set_clipboard(Box::new(clipboard));
+
+        keys(&mut editor, "\"+yyG\"+p\"*p");
+        assert_eq!("select a\nfrom t\nselect a\nwhere x", text(&editor));
+        assert_eq!(
+            Some(&Register::new("select a\n".into(), true)),
+            editor.workspace.registers().get('"')
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(
+            &mut editor,
+            "iselect Id\rfrom t\rwhere id = 1 and pid = 2\x1bgg",
+        );
+        let pos = |editor: &DuzzyEditor| editor.workspace.curr().buf().pos();
+
+        keys(&mut editor, "/fr");
+        assert_eq!((1, 0), pos(&editor));
+        assert_eq!("fr", editor.workspace.prompt().input());
+        keys(&mut editor, "\x1b");
+        assert_eq!((0, 0), pos(&editor));
+        assert!(!editor.workspace.curr().buf().is_command());
+
+        keys(&mut editor, "/id\r");
+        assert_eq!((2, 6), pos(&editor));
+        keys(&mut editor, "n");
+        assert_eq!((2, 18), pos(&editor));
+        keys(&mut editor, "n");
+        assert_eq!((2, 6), pos(&editor));
+        assert!(matches!(editor.workspace.message(), Some(Message::Info(_))));
+        keys(&mut editor, "N");
+        assert_eq!((2, 18), pos(&editor));
+        // a huge count goes around the matches only as far as it lands
+        keys(&mut editor, "1000000000000000000n");
+        assert_eq!((2, 18), pos(&editor));
+        keys(&mut editor, "99999999999999999999n");
+        assert_eq!((2, 6), pos(&editor));
+        keys(&mut editor, "n");
+
+        editor.options_mut().set("ignorecase").unwrap();
+        keys(&mut editor, "?\r");
+        assert_eq!((2, 6), pos(&editor));
+        keys(&mut editor, "2N");
+        assert_eq!((0, 7), pos(&editor));
+
+        editor.options_mut().set("smartcase").unwrap();
+        keys(&mut editor, "/I\r");
+        assert_eq!((0, 7), pos(&editor));
+
+        keys(&mut editor, "G*");
+        assert_eq!((2, 0), pos(&editor));
+        keys(&mut editor, "w*");
+        assert_eq!((0, 7), pos(&editor));
+        keys(&mut editor, "n");
+        assert_eq!((2, 6), pos(&editor));
+
+        keys(&mut editor, "gg#");
+        assert_eq!((0, 0), pos(&editor));
+        assert!(matches!(editor.workspace.message(), Some(Message::Info(_))));
+
+        keys(&mut editor, "/nothing\r");
+        assert_eq!((0, 0), pos(&editor));
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+
+        keys(&mut editor, "gg/id\rdn");
+        assert_eq!("select id = 1 and pid = 2", text(&editor));
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(
+            &mut editor,
+            "iselect a, a\rfrom t\rwhere a = 1 and a = 2\x1bgg",
+        );
+
+        command(&mut editor, "s/a/b/");
+        assert_eq!("select b, a\nfrom t\nwhere a = 1 and a = 2", text(&editor));
+        assert_eq!(
+            Some(&Message::Info("1 substitution on 1 line".into())),
+            editor.workspace.message()
+        );
+
+        keys(&mut editor, "u");
+        command(&mut editor, r"%s/\ba\b/t.&/g");
+        assert_eq!(
+            "select t.a, t.a\nfrom t\nwhere t.a = 1 and t.a = 2",
+            text(&editor)
+        );
+        assert_eq!((2, 0), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "u");
+        assert_eq!("select a, a\nfrom t\nwhere a = 1 and a = 2", text(&editor));
+
+        keys(&mut editor, "jVj");
+        command(&mut editor, r"s/(\w+) = (\d)/\2 = \1/g");
+        assert_eq!("select a, a\nfrom t\nwhere 1 = a and 2 = a", text(&editor));
+        keys(&mut editor, "u");
+
+        command(&mut editor, "1,$s/a/x/gc");
+        assert_eq!((0, 7), editor.workspace.curr().buf().pos());
+        assert!(editor.workspace.substitution().is_some());
+
+        keys(&mut editor, "yn");
+        assert_eq!((2, 6), editor.workspace.curr().buf().pos());
+        keys(&mut editor, "a");
+        assert!(editor.workspace.substitution().is_none());
+        assert_eq!("select x, a\nfrom t\nwhere x = 1 xnd x = 2", text(&editor));
+        assert_eq!(
+            Some(&Message::Info("4 substitutions on 2 lines".into())),
+            editor.workspace.message()
+        );
+
+        keys(&mut editor, "u");
+        assert_eq!("select a, a\nfrom t\nwhere a = 1 and a = 2", text(&editor));
+
+        command(&mut editor, "%s/a/x/c");
+        keys(&mut editor, "nl");
+        assert_eq!("select a, a\nfrom t\nwhere x = 1 and a = 2", text(&editor));
+
+        command(&mut editor, "%s/a/x/c");
+        keys(&mut editor, "\x1b");
+        assert!(editor.workspace.substitution().is_none());
+        assert_eq!("select a, a\nfrom t\nwhere x = 1 and a = 2", text(&editor));
+
+        command(&mut editor, "s/nothing/x/");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+        command(&mut editor, "5s/a/x/");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+    }
+
+    fn name(editor: &DuzzyEditor) -> String {
+        editor.workspace.curr().meta().name().into_owned()
+    }
+
+    #[test]
+    fn test_documents() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        for path in ["a.sql", "b.sql", "c.sql"] {
+            editor.open_file(path).unwrap();
+        }
+        assert_eq!("c.sql", name(&editor));
+
+        keys(&mut editor, "gn");
+        assert_eq!("a.sql", name(&editor));
+        keys(&mut editor, "gp");
+        assert_eq!("c.sql", name(&editor));
+        keys(&mut editor, "2gp");
+        assert_eq!("a.sql", name(&editor));
+        keys(&mut editor, "ga");
+        assert_eq!("c.sql", name(&editor));
+        keys(&mut editor, "ga");
+        assert_eq!("a.sql", name(&editor));
+
+        // counts past isize wrap around like small ones
+        keys(&mut editor, "9223372036854775807gn");
+        assert_eq!("b.sql", name(&editor));
+        keys(&mut editor, "9223372036854775808gp");
+        assert_eq!("c.sql", name(&editor));
+
+        command(&mut editor, "b 2");
+        assert_eq!("b.sql", name(&editor));
+        command(&mut editor, "b c.");
+        assert_eq!("c.sql", name(&editor));
+        command(&mut editor, "b#");
+        assert_eq!("b.sql", name(&editor));
+        command(&mut editor, "b .sql");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+
+        keys(&mut editor, "gb");
+        assert_eq!(Some(1), editor.workspace.picker().map(|p| p.selected()));
+        keys(&mut editor, "jj");
+        assert_eq!("b.sql", name(&editor));
+        keys(&mut editor, "\r");
+        assert!(editor.workspace.picker().is_none());
+        assert_eq!("a.sql", name(&editor));
+
+        keys(&mut editor, "gbk\x1b");
+        assert_eq!("a.sql", name(&editor));
+
+        keys(&mut editor, "iselect\x1b");
+        command(&mut editor, "bd");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+        assert_eq!("a.sql", name(&editor));
+
+        command(&mut editor, "bd!");
+        assert_eq!("b.sql", name(&editor));
+        assert_eq!(2, editor.workspace.documents().count());
+
+        command(&mut editor, "bd 2");
+        assert_eq!("b.sql", name(&editor));
+        command(&mut editor, "bd");
+        assert_eq!("[scratch]", name(&editor));
+        assert_eq!(1, editor.workspace.documents().count());
+
+        keys(&mut editor, "ga");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+    }
+
+    #[test]
+    fn test_windows() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+        keys(&mut editor, "iselect\rfrom t\rwhere id = 1\x1b");
+
+        let window = |editor: &mut DuzzyEditor, ch: char| {
+            key(editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+            key(editor, KeyCode::Char(ch), KeyModifiers::NONE);
+        };
+        let rects = |editor: &DuzzyEditor| {
+            let areas = editor.workspace.windows().areas();
+            areas.into_iter().map(|(_, rect)| rect).collect::<Vec<_>>()
+        };
+
+        window(&mut editor, 's');
+        assert_eq!(
+            vec![Rect::new(0, 0, 80, 12), Rect::new(0, 12, 80, 11)],
+            rects(&editor)
+        );
+        assert_eq!((2, 12), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "gg");
+        assert_eq!((12, 4), (editor.cursor().y, editor.cursor().x));
+
+        window(&mut editor, 'k');
+        assert_eq!((2, 11), editor.workspace.curr().buf().pos());
+        window(&mut editor, 'j');
+        assert_eq!((0, 0), editor.workspace.curr().buf().pos());
+
+        command(&mut editor, "vs");
+        assert_eq!(Rect::new(40, 12, 40, 11), rects(&editor)[2]);
+        editor.open_file("a.sql").unwrap();
+        assert_eq!((12, 44), (editor.cursor().y, editor.cursor().x));
+
+        window(&mut editor, 'h');
+        assert_eq!("[scratch]", name(&editor));
+        keys(&mut editor, "3");
+        window(&mut editor, '+');
+        assert_eq!(Rect::new(0, 9, 40, 14), rects(&editor)[1]);
+        window(&mut editor, '=');
+        assert_eq!(Rect::new(0, 12, 40, 11), rects(&editor)[1]);
+        keys(&mut editor, "2147483648");
+        window(&mut editor, '-');
+        assert_eq!(Rect::new(0, 21, 40, 2), rects(&editor)[1]);
+        keys(&mut editor, "99999999999999999999");
+        window(&mut editor, '+');
+        assert_eq!(Rect::new(0, 2, 40, 21), rects(&editor)[1]);
+        window(&mut editor, '=');
+
+        window(&mut editor, 'w');
+        assert_eq!("a.sql", name(&editor));
+        command(&mut editor, "bd");
+        assert_eq!("[scratch]", name(&editor));
+        assert!(editor
+            .workspace
+            .documents()
+            .all(|doc| doc.meta().name() != "a.sql"));
+
+        command(&mut editor, "clo");
+        assert_eq!(2, editor.workspace.windows().len());
+        window(&mut editor, 'w');
+        assert_eq!((2, 11), editor.workspace.curr().buf().pos());
+
+        window(&mut editor, 'o');
+        assert_eq!(vec![Rect::new(0, 0, 80, 23)], rects(&editor));
+        command(&mut editor, "close");
+        assert_eq!(
+            Some(&Message::Error("Cannot close last window".into())),
+            editor.workspace.message()
+        );
+    }
+
+    #[test]
+    fn test_close_shown_document() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        for path in ["a.sql", "b.sql"] {
+            editor.open_file(path).unwrap();
+        }
+
+        let shown = |editor: &DuzzyEditor| {
+            let workspace = &editor.workspace;
+            let mut views = workspace.tabs().iter().flat_map(Windows::views);
+            views.all(|view| workspace.document(view.doc).is_some())
+        };
+
+        key(&mut editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        keys(&mut editor, "s");
+        command(&mut editor, "b 1");
+        command(&mut editor, "bd 2");
+        assert!(shown(&editor));
+
+        key(&mut editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        keys(&mut editor, "wiX\x1b");
+        assert_eq!("a.sql", name(&editor));
+        assert_eq!("X", text(&editor));
+
+        command(&mut editor, "tabnew c.sql");
+        keys(&mut editor, "gt");
+        command(&mut editor, "bd c.sql");
+        assert!(shown(&editor));
+
+        keys(&mut editor, "gt");
+        assert_eq!(1, editor.workspace.tab());
+        assert_eq!("a.sql", name(&editor));
+    }
+
+    #[test]
+    fn test_windows_follow_changes() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+        keys(&mut editor, "i1\r2\r3\r4\r5\x1b");
+
+        let window = |editor: &mut DuzzyEditor| {
+            key(editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+            key(editor, KeyCode::Char('w'), KeyModifiers::NONE);
+        };
+
+        key(&mut editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        keys(&mut editor, "sgg4j");
+        window(&mut editor);
+        keys(&mut editor, "ggOa\rb\rc\x1b");
+        window(&mut editor);
+        assert_eq!((7, 0), editor.workspace.curr().buf().pos());
+
+        window(&mut editor);
+        keys(&mut editor, "u");
+        window(&mut editor);
+        assert_eq!((4, 0), editor.workspace.curr().buf().pos());
+
+        window(&mut editor);
+        keys(&mut editor, "Gdd");
+        window(&mut editor);
+        assert_eq!((3, 0), editor.workspace.curr().buf().pos());
+    }
+
+    #[test]
+    fn test_tabs() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_file("schema.sql").unwrap();
+        keys(&mut editor, "icreate table t\x1b");
+
+        let rects = |editor: &DuzzyEditor| {
+            let areas = editor.workspace.windows().areas();
+            areas.into_iter().map(|(_, rect)| rect).collect::<Vec<_>>()
+        };
+
+        command(&mut editor, "tabnew migration.sql");
+        assert_eq!(
+            (1, 2),
+            (editor.workspace.tab(), editor.workspace.tabs().len())
+        );
+        assert_eq!("migration.sql", name(&editor));
+        assert_eq!(vec![Rect::new(0, 1, 80, 22)], rects(&editor));
+        keys(&mut editor, "ialter table t\x1b");
+
+        keys(&mut editor, "gt");
+        assert_eq!("schema.sql", name(&editor));
+        assert_eq!((0, 13), editor.workspace.curr().buf().pos());
+        keys(&mut editor, "gT");
+        assert_eq!("migration.sql", name(&editor));
+        keys(&mut editor, "1gt");
+        assert_eq!(0, editor.workspace.tab());
+        keys(&mut editor, "9223372036854775807gT");
+        assert_eq!(1, editor.workspace.tab());
+        keys(&mut editor, "9223372036854775808gT");
+        assert_eq!(1, editor.workspace.tab());
+        keys(&mut editor, "1gt");
+
+        key(&mut editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        keys(&mut editor, "v");
+        assert_eq!(2, editor.workspace.windows().len());
+
+        command(&mut editor, "tabm");
+        assert_eq!(1, editor.workspace.tab());
+        assert_eq!(2, editor.workspace.windows().len());
+        command(&mut editor, "tabm -1");
+        assert_eq!(0, editor.workspace.tab());
+        command(&mut editor, "tabm x");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+
+        command(&mut editor, "tabnew");
+        assert_eq!(
+            (1, 3),
+            (editor.workspace.tab(), editor.workspace.tabs().len())
+        );
+        assert_eq!("[scratch]", name(&editor));
+        command(&mut editor, "tabc");
+        assert_eq!("migration.sql", name(&editor));
+        command(&mut editor, "tabnext 1");
+        assert_eq!("schema.sql", name(&editor));
+
+        command(&mut editor, "tabo");
+        assert_eq!(1, editor.workspace.tabs().len());
+        assert_eq!(Rect::new(0, 0, 40, 23), rects(&editor)[0]);
+        command(&mut editor, "tabclose");
+        assert_eq!(
+            Some(&Message::Error("Cannot close last tab page".into())),
+            editor.workspace.message()
+        );
+    }
+
+    #[test]
+    fn test_paste() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        keys(&mut editor, "iselect ");
+        let outcome = editor.on_event(Event::Paste("a,\r\n  b\rfrom t".into()));
+        assert!(matches!(outcome, EventOutcome::Render));
+        assert_eq!((2, 6), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, ";\x1b");
+        assert_eq!("select a,\n  b\nfrom t;", text(&editor));
+
+        keys(&mut editor, "u");
+        assert_eq!("", text(&editor));
+        keys(&mut editor, "U");
+
+        keys(&mut editor, "gg");
+        editor.on_event(Event::Paste("-- q\n".into()));
+        assert_eq!("-- q\nselect a,\n  b\nfrom t;", text(&editor));
+        assert_eq!((0, 4), editor.workspace.curr().buf().pos());
+
+        keys(&mut editor, "u");
+        assert_eq!("select a,\n  b\nfrom t;", text(&editor));
+
+        keys(&mut editor, ":");
+        editor.on_event(Event::Paste("set wrap\nignored".into()));
+        assert_eq!("set wrap", editor.workspace.cmdline().input());
+    }
+
+    #[test]
+    fn test_command_line() {
+        let mut editor = DuzzyEditor::new(80, 24);
+        editor.open_scratch();
+
+        command(&mut editor, "unknown");
+        assert!(matches!(
+            editor.workspace.message(),
+            Some(Message::Error(_))
+        ));
+        assert!(!editor.workspace.curr().buf().is_command());
+
+        key(&mut editor, KeyCode::Char('o'), KeyModifiers::NONE);
+        key(&mut editor, KeyCode::Esc, KeyModifiers::NONE);
+
+        command(&mut editor, "1");
+        assert_eq!((0, 0), editor.workspace.curr().buf().pos());
+
+        assert_eq!(EventOutcome::Render, command(&mut editor, "q"));
+        assert_eq!(EventOutcome::Exit, command(&mut editor, "q!"));
+    }
 }
@@ -0,0 +1,188 @@
+use std::borrow::Cow;
+
+use ropey::{str_utils::byte_to_char_idx, RopeSlice};
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+use unicode_width::UnicodeWidthStr;
+
+/// Grapheme cluster of a line placed at a display column.
+#[derive(Debug, Clone, Copy)]
+pub struct Grapheme<'a> {
+    pub offset: usize,
+    pub col: usize,
+    pub width: usize,
+    pub slice: RopeSlice<'a>,
+}
+
+impl<'a> Grapheme<'a> {
+    pub fn is_tab(&self) -> bool {
+        self.slice.char(0) == '\t'
+    }
+}
+
+/// Iterator over the graphemes of a line, expanding tabs to `tab_width` stops.
+pub struct Graphemes<'a> {
+    line: RopeSlice<'a>,
+    offset: usize,
+    col: usize,
+    tab_width: usize,
+}
+
+impl<'a> Graphemes<'a> {
+    /// Starts at char `offset` of `line` placed at display column `col`.
+    pub fn new(line: RopeSlice<'a>, (offset, col): (usize, usize), tab_width: usize) -> Self {
+        Self {
+            line,
+            offset,
+            col,
+            tab_width: tab_width.max(1),
+        }
+    }
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = Grapheme<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.line.len_chars() {
+            return None;
+        }
+
+        let end = next_grapheme_boundary(self.line, self.offset);
+        let slice = self.line.slice(self.offset..end);
+
+        let width = if slice.char(0) == '\t' {
+            self.tab_width - self.col % self.tab_width
+        } else {
+            Cow::<str>::from(slice).width()
+        };
+
+        let grapheme = Grapheme {
+            offset: self.offset,
+            col: self.col,
+            width,
+            slice,
+        };
+
+        self.offset = end;
+        self.col += width;
+
+        Some(grapheme)
+    }
+}
+
+/// Char index of the grapheme boundary preceding `char_idx`.
+pub fn prev_grapheme_boundary(slice: RopeSlice, char_idx: usize) -> usize {
+    let byte_idx = slice.char_to_byte(char_idx);
+
+    let (mut chunk, mut chunk_byte_idx, mut chunk_char_idx, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+
+    loop {
+        match cursor.prev_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return 0,
+            Ok(Some(n)) => return chunk_char_idx + byte_to_char_idx(chunk, n - chunk_byte_idx),
+            Err(GraphemeIncomplete::PrevChunk) => {
+                (chunk, chunk_byte_idx, chunk_char_idx, _) =
+                    slice.chunk_at_byte(chunk_byte_idx - 1);
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let context = slice.chunk_at_byte(n - 1).0;
+                cursor.provide_context(context, n - context.len());
+            }
+            Err(_) => unreachable!("unexpected grapheme cursor state"),
+        }
+    }
+}
+
+/// Char index of the grapheme boundary following `char_idx`.
+pub fn next_grapheme_boundary(slice: RopeSlice, char_idx: usize) -> usize {
+    let byte_idx = slice.char_to_byte(char_idx);
+
+    let (mut chunk, mut chunk_byte_idx, mut chunk_char_idx, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+
+    loop {
+        match cursor.next_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return slice.len_chars(),
+            Ok(Some(n)) => return chunk_char_idx + byte_to_char_idx(chunk, n - chunk_byte_idx),
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_byte_idx += chunk.len();
+                (chunk, _, chunk_char_idx, _) = slice.chunk_at_byte(chunk_byte_idx);
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let context = slice.chunk_at_byte(n - 1).0;
+                cursor.provide_context(context, n - context.len());
+            }
+            Err(_) => unreachable!("unexpected grapheme cursor state"),
+        }
+    }
+}
+
+/// Whether `char_idx` lies between two grapheme clusters.
+pub fn is_grapheme_boundary(slice: RopeSlice, char_idx: usize) -> bool {
+    let byte_idx = slice.char_to_byte(char_idx);
+
+    let (chunk, chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+
+    loop {
+        match cursor.is_boundary(chunk, chunk_byte_idx) {
+            Ok(n) => return n,
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let context = slice.chunk_at_byte(n - 1).0;
+                cursor.provide_context(context, n - context.len());
+            }
+            Err(_) => unreachable!("unexpected grapheme cursor state"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+
+    #[test]
+    fn test_grapheme_boundaries() {
+        // "e" followed by a combining acute accent, a family emoji and a CRLF
+        let text = Rope::from("ae\u{301}b👨‍👩‍👧\r\n");
+        let slice = text.slice(..);
+
+        assert_eq!(1, next_grapheme_boundary(slice, 0));
+        assert_eq!(3, next_grapheme_boundary(slice, 1));
+        assert_eq!(4, next_grapheme_boundary(slice, 3));
+        assert_eq!(9, next_grapheme_boundary(slice, 4));
+        assert_eq!(11, next_grapheme_boundary(slice, 9));
+        assert_eq!(11, next_grapheme_boundary(slice, 11));
+
+        assert_eq!(9, prev_grapheme_boundary(slice, 11));
+        assert_eq!(4, prev_grapheme_boundary(slice, 9));
+        assert_eq!(1, prev_grapheme_boundary(slice, 3));
+        assert_eq!(0, prev_grapheme_boundary(slice, 0));
+
+        assert!(is_grapheme_boundary(slice, 1));
+        assert!(!is_grapheme_boundary(slice, 2));
+        assert!(!is_grapheme_boundary(slice, 6));
+        assert!(!is_grapheme_boundary(slice, 10));
+    }
+
+    #[test]
+    fn test_graphemes() {
+        let text = Rope::from("a\tбы\t表e\u{301}");
+        let layout: Vec<_> = Graphemes::new(text.slice(..), (0, 0), 4)
+            .map(|g| (g.offset, g.col, g.width))
+            .collect();
+
+        let expected = vec![
+            (0, 0, 1),
+            (1, 1, 3),
+            (2, 4, 1),
+            (3, 5, 1),
+            (4, 6, 2),
+            (5, 8, 2),
+            (6, 10, 1),
+        ];
+        assert_eq!(expected, layout);
+    }
+}
@@ -5,6 +5,7 @@ use crate::transaction::Transaction;
 #[derive(Debug)]
 pub struct History {
     head: usize,
+    saved: Option<usize>,
     max_items: usize,
     commits: VecDeque<Transaction>,
 }
@@ -21,41 +22,62 @@ impl History {
     pub fn new(max_items: usize) -> Self {
         Self {
             head: 0,
+            saved: Some(0),
             max_items,
             commits: VecDeque::with_capacity(max_items),
         }
     }
 
     pub fn commit(&mut self, tx: Transaction) {
+        if !tx.has_changes() {
+            return;
+        }
+
         if self.commits.len() == self.max_items {
             self.commits.pop_front();
             self.head = self.head.saturating_sub(1);
+            self.saved = self.saved.and_then(|saved| saved.checked_sub(1));
         }
 
         if self.head < self.commits.len() {
             self.commits.truncate(self.head);
+
+            // the saved state was on the discarded branch and can't be reached anymore
+            if self.saved.is_some_and(|saved| saved > self.head) {
+                self.saved = None;
+            }
         }
 
         self.commits.push_back(tx);
         self.head += 1;
     }
 
-    pub fn undo(&mut self, text: &mut ropey::Rope) -> Option<usize> {
+    /// Change undoing the last commit, to apply to the text.
+    pub fn undo(&mut self) -> Option<Transaction> {
         self.head = self.head.checked_sub(1)?;
-        let commit = &mut self.commits[self.head];
+        let commit = &self.commits[self.head];
 
-        commit.inverse().apply(text)
+        Some(commit.inverse())
     }
 
-    pub fn redo(&mut self, text: &mut ropey::Rope) -> Option<usize> {
+    /// Change redoing the last undone commit, to apply to the text.
+    pub fn redo(&mut self) -> Option<Transaction> {
         if self.head == self.commits.len() {
             return None;
         }
 
-        let commit = &mut self.commits[self.head];
+        let commit = &self.commits[self.head];
         self.head += 1;
 
-        commit.apply(text)
+        Some(commit.clone())
+    }
+
+    pub const fn mark_saved(&mut self) {
+        self.saved = Some(self.head);
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.saved != Some(self.head)
     }
 }
 
@@ -82,11 +104,11 @@ mod tests {
         history.commit(tx);
         assert_eq!(&text.to_string(), "");
 
-        let pos = history.undo(&mut text);
+        let pos = history.undo().and_then(|tx| tx.apply(&mut text));
         assert_eq!(Some(4), pos);
         assert_eq!(&text.to_string(), "test");
 
-        let pos = history.redo(&mut text);
+        let pos = history.redo().and_then(|tx| tx.apply(&mut text));
         assert_eq!(Some(0), pos);
         assert_eq!(&text.to_string(), "");
     }
@@ -108,11 +130,11 @@ mod tests {
 
         let expected = text.to_string();
 
-        let pos = history.undo(&mut text);
+        let pos = history.undo().and_then(|tx| tx.apply(&mut text));
         assert_eq!(Some(0), pos);
         assert_eq!(&expected, "");
 
-        let pos = history.redo(&mut text);
+        let pos = history.redo().and_then(|tx| tx.apply(&mut text));
         assert_eq!(Some(0), pos);
         assert_eq!(&expected, "");
     }
@@ -130,12 +152,47 @@ mod tests {
         tx.apply(&mut text);
         history.commit(tx);
 
-        let pos = history.undo(&mut text);
+        let pos = history.undo().and_then(|tx| tx.apply(&mut text));
         assert_eq!(Some(2), pos);
         assert_eq!(&text.to_string(), "test");
 
-        let pos = history.redo(&mut text);
+        let pos = history.redo().and_then(|tx| tx.apply(&mut text));
         assert_eq!(Some(4), pos);
         assert_eq!(&text.to_string(), "test\ntest");
     }
+
+    #[test]
+    fn test_history_modified() {
+        let mut history = History::default();
+        let mut text = ropey::Rope::new();
+        assert!(!history.is_modified());
+
+        let mut tx = Transaction::new();
+        tx.shift(0);
+        history.commit(tx);
+        assert!(!history.is_modified());
+
+        let mut tx = Transaction::new();
+        tx.insert_str(0, "test");
+        tx.apply(&mut text);
+        history.commit(tx);
+        assert!(history.is_modified());
+
+        history.mark_saved();
+        assert!(!history.is_modified());
+
+        history.undo().and_then(|tx| tx.apply(&mut text));
+        assert!(history.is_modified());
+
+        history.redo().and_then(|tx| tx.apply(&mut text));
+        assert!(!history.is_modified());
+
+        history.undo().and_then(|tx| tx.apply(&mut text));
+        let mut tx = Transaction::new();
+        tx.insert_str(0, "other");
+        tx.apply(&mut text);
+        history.commit(tx);
+        history.undo().and_then(|tx| tx.apply(&mut text));
+        assert!(history.is_modified());
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use anyhow::Context;
 use crossterm::event::{
     Event as CrosstermEvent, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind,
@@ -59,7 +61,7 @@ impl Modifiers {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Event {
     Char(char),
     Backspace,
@@ -79,10 +81,38 @@ pub enum Event {
     MouseScrollDown,
     MouseScrollUp,
 
+    /// Bracketed paste, the text arriving at once instead of a key per char.
+    Paste(String),
+
     #[default]
     Null,
 }
 
+impl Event {
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Char(_) => "char",
+            Self::Backspace => "backspace",
+            Self::Enter => "enter",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::Tab => "tab",
+            Self::Delete => "delete",
+            Self::Home => "home",
+            Self::End => "end",
+            Self::PageUp => "pageup",
+            Self::PageDown => "pagedown",
+            Self::Esc => "esc",
+            Self::MouseScrollDown => "scrolldown",
+            Self::MouseScrollUp => "scrollup",
+            Self::Paste(_) => "paste",
+            Self::Null => "null",
+        }
+    }
+}
+
 impl TryFrom<&str> for Event {
     type Error = anyhow::Error;
 
@@ -113,17 +143,44 @@ impl TryFrom<&str> for Event {
     }
 }
 
-#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Input {
     pub event: Event,
     pub modifiers: Modifiers,
 }
 
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers {
+            ctr,
+            alt,
+            sup,
+            hyper,
+            meta,
+        } = self.modifiers;
+
+        for (name, enabled) in Modifiers::NAMES.iter().zip([ctr, alt, sup, hyper, meta]) {
+            if enabled {
+                write!(f, "<{name}>")?;
+            }
+        }
+
+        match &self.event {
+            Event::Char(ch) => write!(f, "{ch}"),
+            event => write!(f, "<{}>", event.name()),
+        }
+    }
+}
+
 impl From<CrosstermEvent> for Input {
     fn from(event: CrosstermEvent) -> Self {
         match event {
             CrosstermEvent::Key(key) => Self::from(key),
             CrosstermEvent::Mouse(mouse) => Self::from(mouse),
+            CrosstermEvent::Paste(text) => Self {
+                event: Event::Paste(text),
+                modifiers: Modifiers::default(),
+            },
             _ => Self::default(),
         }
     }
@@ -1,11 +1,8 @@
-use std::{
-    collections::{BTreeMap, HashMap},
-    ops::Deref,
-};
+use std::collections::{BTreeMap, HashMap};
 
 use crate::{
     buffer::CursorMode,
-    command::CmdType,
+    command::{CmdType, Operator, Scope, TextObject},
     input::{Event, Input, Modifiers},
 };
 
@@ -14,8 +11,8 @@ use crate::{
 pub struct Bindings(BTreeMap<Input, Keymap>);
 
 impl Bindings {
-    pub fn get(&self, input: Input) -> Option<&Keymap> {
-        self.0.get(&input)
+    pub fn get(&self, input: &Input) -> Option<&Keymap> {
+        self.0.get(input)
     }
 }
 
@@ -38,11 +35,19 @@ pub enum Keymap {
 }
 
 #[derive(Debug, Default)]
-pub struct Keymaps(HashMap<CursorMode, Bindings>);
+pub struct Keymaps {
+    modes: HashMap<CursorMode, Bindings>,
+    /// Motions awaited by an operator.
+    operator_pending: Bindings,
+}
 
 impl Keymaps {
     pub fn get(&self, mode: &CursorMode) -> Option<&Bindings> {
-        self.0.get(mode)
+        self.modes.get(mode)
+    }
+
+    pub const fn operator_pending(&self) -> &Bindings {
+        &self.operator_pending
     }
 }
 
@@ -51,8 +56,12 @@ impl Keymaps {
         let mut map = HashMap::<CursorMode, Bindings>::new();
 
         map.insert(CursorMode::Normal, Self::normal_mode());
+        map.insert(CursorMode::Visual, Self::visual_mode());
 
-        Box::leak(Box::new(Self(map)))
+        Box::leak(Box::new(Self {
+            modes: map,
+            operator_pending: Self::operator_pending_mode(),
+        }))
     }
 
     fn normal_mode() -> Bindings {
@@ -66,53 +75,244 @@ impl Keymaps {
             ("I", CmdType::InsertModeLineStart),
             ("o", CmdType::InsertModeLineNext),
             ("O", CmdType::InsertModeLinePrev),
-            ("d", CmdType::DeleteChar),
+            ("x", CmdType::DeleteChar),
+            ("d", CmdType::Operator(Operator::Delete)),
+            ("c", CmdType::Operator(Operator::Change)),
+            ("y", CmdType::Operator(Operator::Yank)),
+            (">", CmdType::Operator(Operator::Indent)),
+            ("<", CmdType::Operator(Operator::Dedent)),
+            ("gu", CmdType::Operator(Operator::Lowercase)),
+            ("gU", CmdType::Operator(Operator::Uppercase)),
             ("gg", CmdType::GoToTopLine),
-            ("ge", CmdType::GoToBottomLine),
+            ("G", CmdType::GoToBottomLine),
             ("gl", CmdType::GoToLineEnd),
             ("gh", CmdType::GoToLineStart),
+            ("%", CmdType::MatchBracket),
+            ("w", CmdType::MoveNextWordStart),
+            ("b", CmdType::MovePrevWordStart),
+            ("e", CmdType::MoveNextWordEnd),
+            ("ge", CmdType::MovePrevWordEnd),
+            ("W", CmdType::MoveNextLongWordStart),
+            ("B", CmdType::MovePrevLongWordStart),
+            ("E", CmdType::MoveNextLongWordEnd),
+            ("gE", CmdType::MovePrevLongWordEnd),
+            ("}", CmdType::GoToNextParagraph),
+            ("{", CmdType::GoToPrevParagraph),
+            ("f", CmdType::FindNextChar),
+            ("F", CmdType::FindPrevChar),
+            ("t", CmdType::TillNextChar),
+            ("T", CmdType::TillPrevChar),
+            (";", CmdType::RepeatFind),
+            (",", CmdType::ReverseFind),
+            ("/", CmdType::SearchForward),
+            ("?", CmdType::SearchBackward),
+            ("n", CmdType::SearchNext),
+            ("N", CmdType::SearchPrev),
+            ("*", CmdType::SearchWordForward),
+            ("#", CmdType::SearchWordBackward),
+            ("p", CmdType::PutAfter),
+            ("P", CmdType::PutBefore),
+            ("gn", CmdType::NextDocument),
+            ("gp", CmdType::PrevDocument),
+            ("ga", CmdType::AlternateDocument),
+            ("gb", CmdType::DocumentPicker),
+            ("gt", CmdType::NextTab),
+            ("gT", CmdType::PrevTab),
+            ("<ctr>ws", CmdType::SplitWindow),
+            ("<ctr>wv", CmdType::VSplitWindow),
+            ("<ctr>wq", CmdType::CloseWindow),
+            ("<ctr>wc", CmdType::CloseWindow),
+            ("<ctr>wo", CmdType::OnlyWindow),
+            ("<ctr>ww", CmdType::NextWindow),
+            ("<ctr>wh", CmdType::FocusLeftWindow),
+            ("<ctr>wj", CmdType::FocusDownWindow),
+            ("<ctr>wk", CmdType::FocusUpWindow),
+            ("<ctr>wl", CmdType::FocusRightWindow),
+            ("<ctr>w+", CmdType::IncreaseHeight),
+            ("<ctr>w-", CmdType::DecreaseHeight),
+            ("<ctr>w>", CmdType::IncreaseWidth),
+            ("<ctr>w<", CmdType::DecreaseWidth),
+            ("<ctr>w=", CmdType::EqualizeWindows),
             ("u", CmdType::Undo),
             ("U", CmdType::Redo),
+            ("<ctr>s", CmdType::Save),
+            ("<ctr><alt>s", CmdType::ForceSave),
+            ("<ctr>q", CmdType::Quit),
+            ("ZQ", CmdType::ForceQuit),
+            ("ZZ", CmdType::SaveQuit),
+            (":", CmdType::CommandMode),
+            ("v", CmdType::VisualMode),
+            ("V", CmdType::VisualLineMode),
+            ("<ctr>v", CmdType::VisualBlockMode),
         ];
 
         mappings.into()
     }
 
+    fn visual_mode() -> Bindings {
+        let mappings = vec![
+            ("h", CmdType::MoveLeft),
+            ("j", CmdType::MoveDown),
+            ("k", CmdType::MoveUp),
+            ("l", CmdType::MoveRight),
+            ("gg", CmdType::GoToTopLine),
+            ("G", CmdType::GoToBottomLine),
+            ("gl", CmdType::GoToLineEnd),
+            ("gh", CmdType::GoToLineStart),
+            ("%", CmdType::MatchBracket),
+            ("w", CmdType::MoveNextWordStart),
+            ("b", CmdType::MovePrevWordStart),
+            ("e", CmdType::MoveNextWordEnd),
+            ("ge", CmdType::MovePrevWordEnd),
+            ("W", CmdType::MoveNextLongWordStart),
+            ("B", CmdType::MovePrevLongWordStart),
+            ("E", CmdType::MoveNextLongWordEnd),
+            ("gE", CmdType::MovePrevLongWordEnd),
+            ("}", CmdType::GoToNextParagraph),
+            ("{", CmdType::GoToPrevParagraph),
+            ("f", CmdType::FindNextChar),
+            ("F", CmdType::FindPrevChar),
+            ("t", CmdType::TillNextChar),
+            ("T", CmdType::TillPrevChar),
+            (";", CmdType::RepeatFind),
+            (",", CmdType::ReverseFind),
+            ("n", CmdType::SearchNext),
+            ("N", CmdType::SearchPrev),
+            ("*", CmdType::SearchWordForward),
+            ("#", CmdType::SearchWordBackward),
+            ("v", CmdType::VisualMode),
+            ("V", CmdType::VisualLineMode),
+            ("<ctr>v", CmdType::VisualBlockMode),
+            ("<esc>", CmdType::NormalMode),
+            (":", CmdType::VisualCommandMode),
+            ("d", CmdType::VisualDelete),
+            ("x", CmdType::VisualDelete),
+            ("c", CmdType::VisualChange),
+            ("y", CmdType::VisualYank),
+            (">", CmdType::VisualIndent),
+            ("<", CmdType::VisualDedent),
+            ("I", CmdType::VisualBlockInsert),
+            ("A", CmdType::VisualBlockAppend),
+        ];
+
+        let mut bindings = mappings.into();
+        Self::bind_text_objects(&mut bindings);
+
+        bindings
+    }
+
+    /// Motions, and operators repeated to act on the cursor line, as in `dd` or `guu`.
+    fn operator_pending_mode() -> Bindings {
+        let mappings = vec![
+            ("h", CmdType::MoveLeft),
+            ("j", CmdType::MoveDown),
+            ("k", CmdType::MoveUp),
+            ("l", CmdType::MoveRight),
+            ("gg", CmdType::GoToTopLine),
+            ("G", CmdType::GoToBottomLine),
+            ("gl", CmdType::GoToLineEnd),
+            ("gh", CmdType::GoToLineStart),
+            ("%", CmdType::MatchBracket),
+            ("w", CmdType::MoveNextWordStart),
+            ("b", CmdType::MovePrevWordStart),
+            ("e", CmdType::MoveNextWordEnd),
+            ("ge", CmdType::MovePrevWordEnd),
+            ("W", CmdType::MoveNextLongWordStart),
+            ("B", CmdType::MovePrevLongWordStart),
+            ("E", CmdType::MoveNextLongWordEnd),
+            ("gE", CmdType::MovePrevLongWordEnd),
+            ("}", CmdType::GoToNextParagraph),
+            ("{", CmdType::GoToPrevParagraph),
+            ("f", CmdType::FindNextChar),
+            ("F", CmdType::FindPrevChar),
+            ("t", CmdType::TillNextChar),
+            ("T", CmdType::TillPrevChar),
+            (";", CmdType::RepeatFind),
+            (",", CmdType::ReverseFind),
+            ("n", CmdType::SearchNext),
+            ("N", CmdType::SearchPrev),
+            ("*", CmdType::SearchWordForward),
+            ("#", CmdType::SearchWordBackward),
+            ("d", CmdType::Operator(Operator::Delete)),
+            ("c", CmdType::Operator(Operator::Change)),
+            ("y", CmdType::Operator(Operator::Yank)),
+            (">", CmdType::Operator(Operator::Indent)),
+            ("<", CmdType::Operator(Operator::Dedent)),
+            ("gu", CmdType::Operator(Operator::Lowercase)),
+            ("u", CmdType::Operator(Operator::Lowercase)),
+            ("gU", CmdType::Operator(Operator::Uppercase)),
+            ("U", CmdType::Operator(Operator::Uppercase)),
+        ];
+
+        let mut bindings = mappings.into();
+        Self::bind_text_objects(&mut bindings);
+
+        bindings
+    }
+
+    /// `i` and `a` text objects, like `iw` or `a(`.
+    fn bind_text_objects(bindings: &mut Bindings) {
+        let paren = TextObject::Bracket('(', ')');
+        let bracket = TextObject::Bracket('[', ']');
+        let brace = TextObject::Bracket('{', '}');
+
+        let objects = [
+            ("w", TextObject::Word),
+            ("W", TextObject::LongWord),
+            ("\"", TextObject::Quote('"')),
+            ("'", TextObject::Quote('\'')),
+            ("`", TextObject::Quote('`')),
+            ("(", paren),
+            (")", paren),
+            ("b", paren),
+            ("[", bracket),
+            ("]", bracket),
+            ("{", brace),
+            ("}", brace),
+            ("B", brace),
+            ("s", TextObject::Statement),
+        ];
+
+        for (prefix, scope) in [("i", Scope::Inner), ("a", Scope::Around)] {
+            for (key, object) in objects {
+                let sequence = format!("{prefix}{key}");
+                Self::parse(bindings, &sequence, CmdType::TextObject(object, scope));
+            }
+        }
+    }
+
+    /// Adds `sequence` to `root`, a modifier like `<ctr>` applying to the key after it
+    /// as in `<ctr>wv`, and a special key named between angle brackets as in `<esc>`.
     fn parse(root: &mut Bindings, sequence: &str, command_type: CmdType) {
-        let re = regex::Regex::new(r"<(.*?)>").expect("valid pattern");
-
-        let mut specials: Vec<String> = re
-            .captures_iter(sequence)
-            .map(|capture| capture[1].to_string())
-            .collect();
-
-        let modifiers: Modifiers = specials.deref().into();
-        specials.retain(|x| !Modifiers::contain(&x.to_lowercase()));
-
-        let mut keys: Vec<String> = re
-            .replace_all(sequence, "")
-            .chars()
-            .map(|c| c.to_string())
-            .collect();
-
-        keys.extend(specials);
-        keys.sort_by(|a, b| {
-            let pos_a = sequence.find(a).unwrap_or(usize::MAX);
-            let pos_b = sequence.find(b).unwrap_or(usize::MAX);
-            pos_a.cmp(&pos_b)
-        });
-        keys.reverse();
+        let re = regex::Regex::new(r"<([^>]+)>|.").expect("valid pattern");
+
+        let mut keys = vec![];
+        let mut modifiers = Modifiers::default();
 
-        Self::parse_keys(root, modifiers, keys, command_type);
+        for capture in re.captures_iter(sequence) {
+            let key = capture
+                .get(1)
+                .map_or(&capture[0], |special| special.as_str());
+            let name = key.to_lowercase();
+
+            if Modifiers::contain(&name) {
+                modifiers.set_by(&name, true);
+            } else {
+                keys.push((key.to_string(), modifiers));
+                modifiers = Modifiers::default();
+            }
+        }
+
+        keys.reverse();
+        Self::parse_keys(root, keys, command_type);
     }
 
     fn parse_keys(
         parent: &mut Bindings,
-        modifiers: Modifiers,
-        mut keys: Vec<String>,
+        mut keys: Vec<(String, Modifiers)>,
         command_type: CmdType,
     ) {
-        let Some(key) = keys.pop() else {
+        let Some((key, modifiers)) = keys.pop() else {
             return;
         };
 
@@ -130,11 +330,11 @@ impl Keymaps {
             parent.0.insert(input, Keymap::Leaf(command_type));
         } else {
             if let Some(Keymap::Node(ref mut child)) = parent.0.get_mut(&input) {
-                return Self::parse_keys(child, modifiers, keys, command_type);
+                return Self::parse_keys(child, keys, command_type);
             }
 
             let mut child = Bindings::default();
-            Self::parse_keys(&mut child, modifiers, keys, command_type);
+            Self::parse_keys(&mut child, keys, command_type);
             parent.0.insert(input, Keymap::Node(child));
         }
     }
@@ -151,7 +351,7 @@ mod tests {
         let normal = keymap.get(&super::CursorMode::Normal).unwrap();
 
         let node = normal
-            .get(super::Input {
+            .get(&super::Input {
                 event: super::Event::Char('g'),
                 ..Default::default()
             })
@@ -162,13 +362,13 @@ mod tests {
         };
 
         let leaf = bindings
-            .get(super::Input {
+            .get(&super::Input {
                 event: super::Event::Char('e'),
                 ..Default::default()
             })
             .unwrap();
 
-        let expected = super::Keymap::Leaf(CmdType::GoToBottomLine);
+        let expected = super::Keymap::Leaf(CmdType::MovePrevWordEnd);
         assert_eq!(leaf, &expected);
     }
 }
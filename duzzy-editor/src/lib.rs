@@ -5,13 +5,26 @@
     clippy::use_self
 )]
 mod buffer;
+pub mod clipboard;
 mod command;
 mod document;
 pub mod editor;
+mod grapheme;
 mod history;
 mod input;
 mod keymap;
+pub mod options;
+mod picker;
+mod prompt;
+mod register;
 pub mod renderer;
+mod search;
+mod sql;
+mod substitute;
+#[cfg(test)]
+mod test_utils;
 mod transaction;
+mod window;
+mod word;
 
 pub type SmartString = smartstring::SmartString<smartstring::LazyCompact>;
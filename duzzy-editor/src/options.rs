@@ -0,0 +1,204 @@
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineNumbers {
+    Off,
+    #[default]
+    Absolute,
+    Relative,
+    /// Absolute number on the cursor line, relative numbers elsewhere.
+    Hybrid,
+}
+
+impl LineNumbers {
+    const MIN_DIGITS: usize = 3;
+
+    const fn from_flags(number: bool, relative: bool) -> Self {
+        match (number, relative) {
+            (false, false) => Self::Off,
+            (true, false) => Self::Absolute,
+            (false, true) => Self::Relative,
+            (true, true) => Self::Hybrid,
+        }
+    }
+
+    const fn flags(self) -> (bool, bool) {
+        match self {
+            Self::Off => (false, false),
+            Self::Absolute => (true, false),
+            Self::Relative => (false, true),
+            Self::Hybrid => (true, true),
+        }
+    }
+
+    /// Gutter width including the separating space, zero when numbers are off.
+    pub fn width(self, len_lines: usize) -> usize {
+        if self == Self::Off {
+            return 0;
+        }
+
+        let digits = len_lines.max(1).ilog10() as usize + 1;
+        digits.max(Self::MIN_DIGITS) + 1
+    }
+
+    /// Number shown next to line `index` while the cursor is on line `cursor`.
+    pub const fn number(self, index: usize, cursor: usize) -> usize {
+        match self {
+            Self::Relative => index.abs_diff(cursor),
+            Self::Hybrid if index != cursor => index.abs_diff(cursor),
+            _ => index + 1,
+        }
+    }
+}
+
+/// Chars word motions group into words besides letters and digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordChars {
+    pub extra: String,
+    /// Quotes around identifiers moved over as a single word, like `"order id"`.
+    pub quotes: String,
+}
+
+impl Default for WordChars {
+    fn default() -> Self {
+        Self {
+            extra: "_$".into(),
+            quotes: "\"`".into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub line_numbers: LineNumbers,
+    /// Break long lines into several screen rows instead of scrolling horizontally.
+    pub wrap: bool,
+    /// Move `j`/`k` by screen rows of wrapped lines.
+    pub visual_rows: bool,
+    /// Columns between tab stops.
+    pub tab_width: usize,
+    pub word_chars: WordChars,
+    /// Search without matching case.
+    pub ignore_case: bool,
+    /// Match case again when the pattern has an uppercase letter, with `ignore_case`.
+    pub smart_case: bool,
+    /// Keep highlighting the matches of the last search.
+    pub highlight_search: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            line_numbers: LineNumbers::default(),
+            wrap: false,
+            visual_rows: false,
+            tab_width: 4,
+            word_chars: WordChars::default(),
+            ignore_case: false,
+            smart_case: false,
+            highlight_search: false,
+        }
+    }
+}
+
+impl Options {
+    /// Applies a vim-like `:set` option such as `number`, `norelativenumber` or `tabstop=8`.
+    pub fn set(&mut self, option: &str) -> anyhow::Result<()> {
+        if let Some((name, value)) = option.split_once('=') {
+            return self.set_value(name, value);
+        }
+
+        let (name, enable) = match option.strip_prefix("no") {
+            Some(name) => (name, false),
+            None => (option, true),
+        };
+
+        let (mut number, mut relative) = self.line_numbers.flags();
+
+        match name {
+            "number" | "nu" => number = enable,
+            "relativenumber" | "rnu" => relative = enable,
+            "wrap" => self.wrap = enable,
+            "visualrows" => self.visual_rows = enable,
+            "ignorecase" | "ic" => self.ignore_case = enable,
+            "smartcase" | "scs" => self.smart_case = enable,
+            "hlsearch" | "hls" => self.highlight_search = enable,
+            _ => anyhow::bail!("Unknown option: {option}"),
+        }
+
+        self.line_numbers = LineNumbers::from_flags(number, relative);
+        Ok(())
+    }
+
+    fn set_value(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        match name {
+            "tabstop" | "ts" => {
+                let width: usize = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid number: {value}"))?;
+                anyhow::ensure!(width > 0, "Argument must be positive: {name}={value}");
+                self.tab_width = width;
+            }
+            "wordchars" => self.word_chars.extra = value.into(),
+            "quotechars" => self.word_chars.quotes = value.into(),
+            _ => anyhow::bail!("Unknown option: {name}"),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_numbers() {
+        assert_eq!(0, LineNumbers::Off.width(10));
+        assert_eq!(4, LineNumbers::Absolute.width(1));
+        assert_eq!(4, LineNumbers::Absolute.width(999));
+        assert_eq!(5, LineNumbers::Relative.width(1000));
+
+        assert_eq!(6, LineNumbers::Absolute.number(5, 2));
+        assert_eq!(3, LineNumbers::Relative.number(5, 2));
+        assert_eq!(0, LineNumbers::Relative.number(2, 2));
+        assert_eq!(3, LineNumbers::Hybrid.number(2, 5));
+        assert_eq!(6, LineNumbers::Hybrid.number(5, 5));
+    }
+
+    #[test]
+    fn test_options_set() {
+        let mut options = Options::default();
+
+        options.set("rnu").unwrap();
+        assert_eq!(LineNumbers::Hybrid, options.line_numbers);
+
+        options.set("nonumber").unwrap();
+        assert_eq!(LineNumbers::Relative, options.line_numbers);
+
+        options.set("norelativenumber").unwrap();
+        assert_eq!(LineNumbers::Off, options.line_numbers);
+
+        options.set("wrap").unwrap();
+        assert!(options.wrap);
+        options.set("nowrap").unwrap();
+        assert!(!options.wrap);
+
+        options.set("ic").unwrap();
+        options.set("smartcase").unwrap();
+        options.set("hls").unwrap();
+        assert!(options.ignore_case && options.smart_case && options.highlight_search);
+        options.set("noignorecase").unwrap();
+        assert!(!options.ignore_case);
+
+        options.set("ts=8").unwrap();
+        assert_eq!(8, options.tab_width);
+        assert!(options.set("tabstop=0").is_err());
+        assert!(options.set("tabstop=x").is_err());
+
+        options.set("wordchars=_#").unwrap();
+        options.set("quotechars=").unwrap();
+        assert_eq!("_#", options.word_chars.extra);
+        assert!(options.word_chars.quotes.is_empty());
+
+        assert!(options.set("unknown").is_err());
+    }
+}
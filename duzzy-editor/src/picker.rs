@@ -0,0 +1,60 @@
+use crate::document::DocumentId;
+
+/// List of open documents to pick one from, in the order they were opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentPicker {
+    ids: Vec<DocumentId>,
+    selected: usize,
+}
+
+impl DocumentPicker {
+    pub fn new(ids: Vec<DocumentId>, selected: DocumentId) -> Self {
+        let selected = ids.iter().position(|id| *id == selected).unwrap_or(0);
+        Self { ids, selected }
+    }
+
+    pub fn ids(&self) -> &[DocumentId] {
+        &self.ids
+    }
+
+    pub const fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_id(&self) -> Option<DocumentId> {
+        self.ids.get(self.selected).copied()
+    }
+
+    /// Moves the selection down by `delta` entries, up when negative, wrapping around.
+    pub const fn select_by(&mut self, delta: isize) {
+        if self.ids.is_empty() {
+            return;
+        }
+
+        let len = self.ids.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_by() {
+        let ids: Vec<_> = (0..3).map(|_| DocumentId::next()).collect();
+        let mut picker = DocumentPicker::new(ids.clone(), ids[1]);
+        assert_eq!(Some(ids[1]), picker.selected_id());
+
+        picker.select_by(1);
+        assert_eq!(2, picker.selected());
+        picker.select_by(1);
+        assert_eq!(0, picker.selected());
+        picker.select_by(-1);
+        assert_eq!(Some(ids[2]), picker.selected_id());
+
+        let mut empty = DocumentPicker::new(vec![], ids[0]);
+        empty.select_by(1);
+        assert_eq!(None, empty.selected_id());
+    }
+}
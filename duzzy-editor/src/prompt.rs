@@ -0,0 +1,199 @@
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Default)]
+pub struct Prompt {
+    input: String,
+    cursor: usize,
+    history: Vec<String>,
+    recall: Option<(usize, String)>,
+}
+
+impl Prompt {
+    const HISTORY_CAPACITY: usize = 100;
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub const fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Display width of the input before the cursor.
+    pub fn cursor_width(&self) -> usize {
+        self.input[..self.byte_pos(self.cursor)].width()
+    }
+
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.cursor = 0;
+        self.recall = None;
+    }
+
+    pub fn insert_char(&mut self, ch: char) {
+        let pos = self.byte_pos(self.cursor);
+        self.input.insert(pos, ch);
+        self.cursor += 1;
+        self.recall = None;
+    }
+
+    pub fn delete_char(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.delete_char_inplace();
+        }
+    }
+
+    pub fn delete_char_inplace(&mut self) {
+        if self.cursor < self.input.chars().count() {
+            let pos = self.byte_pos(self.cursor);
+            self.input.remove(pos);
+            self.recall = None;
+        }
+    }
+
+    pub const fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.input.chars().count());
+    }
+
+    pub const fn move_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.input.chars().count();
+    }
+
+    /// Recalls an older history entry starting with the text typed before the recall began.
+    pub fn recall_prev(&mut self) {
+        let (index, prefix) = match self.recall.take() {
+            Some(recall) => recall,
+            None => (self.history.len(), self.input.clone()),
+        };
+
+        let found = self.history[..index]
+            .iter()
+            .rposition(|entry| entry.starts_with(&prefix));
+
+        match found {
+            Some(found) => self.set_recalled(found, prefix),
+            None => self.recall = Some((index, prefix)),
+        }
+    }
+
+    /// Recalls a newer history entry, restoring the typed text past the newest one.
+    pub fn recall_next(&mut self) {
+        let Some((index, prefix)) = self.recall.take() else {
+            return;
+        };
+
+        let found = self
+            .history
+            .iter()
+            .enumerate()
+            .skip(index + 1)
+            .find(|(_, entry)| entry.starts_with(&prefix))
+            .map(|(found, _)| found);
+
+        match found {
+            Some(found) => self.set_recalled(found, prefix),
+            None => {
+                self.input = prefix;
+                self.move_end();
+            }
+        }
+    }
+
+    fn set_recalled(&mut self, index: usize, prefix: String) {
+        self.input.clone_from(&self.history[index]);
+        self.move_end();
+        self.recall = Some((index, prefix));
+    }
+
+    /// Takes the current input, remembering it in history.
+    pub fn submit(&mut self) -> String {
+        let input = std::mem::take(&mut self.input);
+        self.reset();
+
+        if !input.is_empty() && self.history.last() != Some(&input) {
+            if self.history.len() == Self::HISTORY_CAPACITY {
+                self.history.remove(0);
+            }
+            self.history.push(input.clone());
+        }
+
+        input
+    }
+
+    fn byte_pos(&self, cursor: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.input.len(), |(pos, _)| pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submit(prompt: &mut Prompt, input: &str) {
+        input.chars().for_each(|ch| prompt.insert_char(ch));
+        prompt.submit();
+    }
+
+    #[test]
+    fn test_prompt_edit() {
+        let mut prompt = Prompt::default();
+
+        "wq".chars().for_each(|ch| prompt.insert_char(ch));
+        prompt.move_left();
+        prompt.delete_char();
+        prompt.insert_char('ф');
+        prompt.move_end();
+        prompt.insert_char('!');
+        assert_eq!("фq!", prompt.input());
+        assert_eq!(3, prompt.cursor());
+        assert_eq!(3, prompt.cursor_width());
+
+        prompt.move_start();
+        prompt.delete_char_inplace();
+        assert_eq!("q!", prompt.input());
+
+        assert_eq!("q!", prompt.submit());
+        assert_eq!("", prompt.input());
+        assert_eq!(0, prompt.cursor());
+    }
+
+    #[test]
+    fn test_prompt_history() {
+        let mut prompt = Prompt::default();
+        submit(&mut prompt, "e a.sql");
+        submit(&mut prompt, "w");
+        submit(&mut prompt, "e b.sql");
+
+        prompt.recall_prev();
+        assert_eq!("e b.sql", prompt.input());
+        prompt.recall_prev();
+        assert_eq!("w", prompt.input());
+        prompt.recall_next();
+        assert_eq!("e b.sql", prompt.input());
+        prompt.recall_next();
+        assert_eq!("", prompt.input());
+
+        prompt.insert_char('e');
+        prompt.recall_prev();
+        assert_eq!("e b.sql", prompt.input());
+        prompt.recall_prev();
+        assert_eq!("e a.sql", prompt.input());
+        prompt.recall_prev();
+        assert_eq!("e a.sql", prompt.input());
+        prompt.recall_next();
+        prompt.recall_next();
+        assert_eq!("e", prompt.input());
+    }
+}
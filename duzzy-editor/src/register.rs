@@ -0,0 +1,230 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::clipboard::{ClipboardKind, ClipboardProvider};
+
+/// Text kept by yank and delete commands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    /// Holds whole lines, line breaks included.
+    pub linewise: bool,
+}
+
+impl Register {
+    pub const fn new(text: String, linewise: bool) -> Self {
+        Self { text, linewise }
+    }
+
+    /// Adds `other` after the text, as new lines when either one is linewise.
+    fn append(&mut self, other: Self) {
+        match (self.linewise, other.linewise) {
+            (true, false) => {
+                self.text.push_str(&other.text);
+                self.text.push('\n');
+            }
+            (false, true) => {
+                self.text.push('\n');
+                self.text.push_str(&other.text);
+                self.linewise = true;
+            }
+            _ => self.text.push_str(&other.text),
+        }
+    }
+}
+
+/// Registers picked by name with `"`, like `"a` or `"1`.
+///
+/// - `"` the unnamed register, holding the last yanked or deleted text
+/// - `a` to `z` named registers, appended to through `A` to `Z`
+/// - `0` the last yank
+/// - `1` to `9` the last deletes of whole lines or across lines, newest first
+/// - `-` the last delete within a line
+/// - `_` the black hole register, keeping nothing
+/// - `+` and `*` the system clipboard and primary selection
+#[derive(Default)]
+pub struct Registers {
+    unnamed: Option<Register>,
+    named: HashMap<char, Register>,
+    yank: Option<Register>,
+    deletes: VecDeque<Register>,
+    small_delete: Option<Register>,
+    clipboard: Option<Box<dyn ClipboardProvider>>,
+}
+
+impl Registers {
+    const DELETES: usize = 9;
+
+    pub const fn is_valid(name: char) -> bool {
+        matches!(name, '"' | '-' | '_' | '+' | '*') || name.is_ascii_alphanumeric()
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn ClipboardProvider>) {
+        self.clipboard = Some(clipboard);
+    }
+
+    /// Output the clipboard left for the terminal, see [`ClipboardProvider::take_output`].
+    pub fn take_clipboard_output(&mut self) -> Option<String> {
+        self.clipboard.as_deref_mut()?.take_output()
+    }
+
+    /// Register `name` to put, asking the clipboard for `+` and `*`.
+    pub fn read(&mut self, name: char) -> anyhow::Result<Register> {
+        if let Some(kind) = ClipboardKind::from_register(name) {
+            let text = self.clipboard()?.get(kind)?;
+            // the clipboard keeps no kind, take text ending a line for lines
+            let linewise = text.ends_with('\n');
+
+            return Ok(Register::new(text, linewise));
+        }
+
+        match self.get(name) {
+            Some(register) => Ok(register.clone()),
+            None => anyhow::bail!("Nothing in register {name}"),
+        }
+    }
+
+    pub fn get(&self, name: char) -> Option<&Register> {
+        match name {
+            '"' => self.unnamed.as_ref(),
+            '-' => self.small_delete.as_ref(),
+            '0' => self.yank.as_ref(),
+            '1'..='9' => self.deletes.get(name as usize - '1' as usize),
+            'a'..='z' | 'A'..='Z' => self.named.get(&name.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+
+    /// Keeps yanked text in register `name`, or in `"0` without one.
+    pub fn yank(&mut self, name: Option<char>, register: Register) -> anyhow::Result<()> {
+        let register = match name {
+            Some('_') => return Ok(()),
+            Some(name @ ('+' | '*')) => self.copy(name, register)?,
+            Some(name) if name.is_ascii_alphabetic() => self.set_named(name, register),
+            _ => {
+                self.yank = Some(register.clone());
+                register
+            }
+        };
+
+        self.unnamed = Some(register);
+        Ok(())
+    }
+
+    /// Keeps deleted text in register `name`, or without one in `"1`
+    /// for lines and `"-` for text within a line.
+    pub fn delete(&mut self, name: Option<char>, register: Register) -> anyhow::Result<()> {
+        let register = match name {
+            Some('_') => return Ok(()),
+            Some(name @ ('+' | '*')) => self.copy(name, register)?,
+            Some(name) if name.is_ascii_alphabetic() => self.set_named(name, register),
+            _ if register.linewise || register.text.contains('\n') => {
+                self.deletes.push_front(register.clone());
+                self.deletes.truncate(Self::DELETES);
+                register
+            }
+            _ => {
+                self.small_delete = Some(register.clone());
+                register
+            }
+        };
+
+        self.unnamed = Some(register);
+        Ok(())
+    }
+
+    fn clipboard(&mut self) -> anyhow::Result<&mut dyn ClipboardProvider> {
+        match self.clipboard.as_deref_mut() {
+            Some(clipboard) => Ok(clipboard),
+            None => anyhow::bail!("No clipboard provider"),
+        }
+    }
+
+    fn copy(&mut self, name: char, register: Register) -> anyhow::Result<Register> {
+        let kind = ClipboardKind::from_register(name).expect("clipboard register");
+        self.clipboard()?.set(kind, &register.text)?;
+
+        Ok(register)
+    }
+
+    /// Sets a named register, appending to it for an uppercase `name`.
+    fn set_named(&mut self, name: char, register: Register) -> Register {
+        let lower = name.to_ascii_lowercase();
+
+        match self.named.get_mut(&lower) {
+            Some(existing) if name.is_ascii_uppercase() => existing.append(register),
+            _ => {
+                self.named.insert(lower, register);
+            }
+        }
+
+        self.named[&lower].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clipboard::FakeClipboard;
+
+    use super::*;
+
+    fn chars(text: &str) -> Register {
+        Register::new(text.into(), false)
+    }
+
+    fn lines(text: &str) -> Register {
+        Register::new(text.into(), true)
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut registers = Registers::default();
+
+        registers.yank(None, chars("select")).unwrap();
+        registers.delete(None, chars("from")).unwrap();
+        registers.delete(None, lines("where\n")).unwrap();
+        registers.delete(None, chars("a\nb")).unwrap();
+
+        assert_eq!(Some(&chars("a\nb")), registers.get('"'));
+        assert_eq!(Some(&chars("select")), registers.get('0'));
+        assert_eq!(Some(&chars("from")), registers.get('-'));
+        assert_eq!(Some(&chars("a\nb")), registers.get('1'));
+        assert_eq!(Some(&lines("where\n")), registers.get('2'));
+        assert_eq!(None, registers.get('3'));
+
+        registers.yank(Some('a'), chars("x")).unwrap();
+        registers.yank(Some('A'), chars("y")).unwrap();
+        assert_eq!(Some(&chars("xy")), registers.get('a'));
+
+        registers.delete(Some('A'), lines("z\n")).unwrap();
+        assert_eq!(Some(&lines("xy\nz\n")), registers.get('a'));
+        assert_eq!(Some(&lines("xy\nz\n")), registers.get('"'));
+        assert_eq!(Some(&chars("a\nb")), registers.get('1'));
+
+        registers.delete(Some('_'), chars("gone")).unwrap();
+        assert_eq!(Some(&lines("xy\nz\n")), registers.get('"'));
+
+        for i in 0..10 {
+            registers.delete(None, lines(&format!("{i}\n"))).unwrap();
+        }
+        assert_eq!(Some(&lines("9\n")), registers.get('1'));
+        assert_eq!(Some(&lines("1\n")), registers.get('9'));
+    }
+
+    #[test]
+    fn test_clipboard_registers() {
+        let mut registers = Registers::default();
+        assert!(registers.yank(Some('+'), chars("x")).is_err());
+        assert!(registers.read('*').is_err());
+        assert!(registers.read('a').is_err());
+
+        registers.set_clipboard(Box::new(FakeClipboard::default()));
+        registers.yank(Some('+'), lines("select 1;\n")).unwrap();
+        registers.delete(Some('*'), chars("from")).unwrap();
+
+        assert_eq!(lines("select 1;\n"), registers.read('+').unwrap());
+        assert_eq!(chars("from"), registers.read('*').unwrap());
+        assert_eq!(Some(&chars("from")), registers.get('"'));
+        assert_eq!(None, registers.get('0'));
+        assert_eq!(None, registers.get('-'));
+    }
+}
@@ -1,12 +1,26 @@
+use std::{
+    borrow::Cow,
+    ops::{Range, RangeInclusive},
+};
+
 use crossterm::cursor::SetCursorStyle;
 use ratatui::{
     buffer::Buffer,
-    layout::Rect,
-    text::Text,
-    widgets::{Paragraph, Widget},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
 };
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
 
-use crate::{buffer::CursorMode, editor::DuzzyEditor};
+use crate::{
+    buffer::{self, CursorMode},
+    document::Document,
+    editor::{DuzzyEditor, Message},
+    search, sql,
+    window::ViewId,
+};
 
 #[derive(Default)]
 pub(super) struct Viewport {
@@ -15,9 +29,23 @@ pub(super) struct Viewport {
 }
 
 impl Viewport {
-    pub fn update(&mut self, width: usize, height: usize) {
-        self.width = width;
-        self.height = height;
+    /// Rows under the text area taken by the status line and the command line.
+    pub const RESERVED_ROWS: usize = 2;
+
+    pub const fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height: height.saturating_sub(Self::RESERVED_ROWS),
+        }
+    }
+
+    pub const fn update(&mut self, width: usize, height: usize) {
+        *self = Self::new(width, height);
+    }
+
+    /// Screen area of the tab pages, the tab line and the status lines included.
+    pub fn tabs_area(&self) -> Rect {
+        Rect::new(0, 0, self.width as u16, self.height as u16 + 1)
     }
 }
 
@@ -37,12 +65,64 @@ pub enum EventOutcome {
 impl Cursor {
     pub const fn style(&self) -> SetCursorStyle {
         match self.mode {
-            CursorMode::Insert => SetCursorStyle::BlinkingBar,
+            CursorMode::Insert | CursorMode::Command => SetCursorStyle::BlinkingBar,
             CursorMode::Normal | CursorMode::Visual => SetCursorStyle::BlinkingBlock,
         }
     }
 }
 
+struct Row {
+    index: usize,
+    range: Range<usize>,
+    /// Display column shown at the left edge.
+    col: usize,
+    /// Continuation of a wrapped line.
+    wrapped: bool,
+}
+
+impl Row {
+    const fn new(index: usize, range: Range<usize>, col: usize, wrapped: bool) -> Self {
+        Self {
+            index,
+            range,
+            col,
+            wrapped,
+        }
+    }
+}
+
+enum Highlight {
+    Range(Range<usize>),
+    /// Display columns over a range of lines.
+    Block(RangeInclusive<usize>, Range<usize>),
+    None,
+}
+
+/// Spans of a screen row, merging neighbouring text of the same style.
+#[derive(Default)]
+struct Spans(Vec<(String, Style)>);
+
+impl Spans {
+    fn push(&mut self, text: impl Iterator<Item = char>, style: Style) {
+        match self.0.last_mut() {
+            Some((last, last_style)) if *last_style == style => last.extend(text),
+            _ => self.0.push((text.collect(), style)),
+        }
+    }
+}
+
+impl From<Spans> for Line<'static> {
+    fn from(spans: Spans) -> Self {
+        let spans: Vec<_> = spans
+            .0
+            .into_iter()
+            .map(|(text, style)| Span::styled(text, style))
+            .collect();
+
+        Self::from(spans)
+    }
+}
+
 pub struct Renderer<'a>(&'a DuzzyEditor);
 
 impl<'a> Renderer<'a> {
@@ -51,29 +131,894 @@ impl<'a> Renderer<'a> {
     }
 
     #[inline]
-    pub fn text(&self) -> Option<Text> {
-        let buf = self.0.workspace.curr().buf();
+    pub fn message(&self) -> Option<Line<'_>> {
+        let workspace = &self.0.workspace;
 
-        let text = buf.text();
-        let vscroll = buf.vscroll();
-
-        let start_byte = text.line_to_byte(vscroll);
+        if workspace.curr().buf().is_command() {
+            let prefix = match workspace.search_prompt() {
+                Some(prompt) if prompt.forward => '/',
+                Some(_) => '?',
+                None => ':',
+            };
+            return Some(Line::raw(format!("{prefix}{}", workspace.prompt().input())));
+        }
 
-        let end_index = vscroll + self.0.viewport().1 - 1;
-        let end_byte = text.line_to_byte(end_index.min(buf.len_lines()));
+        let line = match workspace.message()? {
+            Message::Info(text) => Line::raw(text.as_str()),
+            Message::Error(text) => Line::styled(text.as_str(), Style::new().fg(Color::Red)),
+        };
 
-        Some(Text::raw(text.slice(start_byte..end_byte)))
+        Some(line)
     }
 }
 
 impl<'a> Widget for Renderer<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        match self.text() {
-            Some(text) => {
-                let inner = Paragraph::new(text);
-                inner.render(area, buf);
+        let [windows_area, message_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+
+        let windows = self.0.workspace.windows();
+        let offset = |rect: Rect| {
+            Rect {
+                x: rect.x + area.x,
+                y: rect.y + area.y,
+                ..rect
+            }
+            .intersection(windows_area)
+        };
+
+        for (id, rect) in windows.areas() {
+            if let Some(window) = Window::new(self.0, id, rect) {
+                window.render(offset(rect), buf);
+            }
+        }
+
+        if self.0.workspace.tabs().len() > 1 {
+            let tabs_area = Rect { height: 1, ..area };
+            TabLine(self.0).render(tabs_area, buf);
+        }
+
+        if self.0.workspace.picker().is_some() {
+            let picker_area = windows.inner(windows.focused_area());
+            PickerList(self.0).render(offset(picker_area), buf);
+        }
+
+        if let Some(message) = self.message() {
+            Paragraph::new(message).render(message_area, buf);
+        }
+    }
+}
+
+/// Window as drawn, its view of a document above its status line.
+struct Window<'a> {
+    editor: &'a DuzzyEditor,
+    doc: &'a Document,
+    /// Buffer of the document, a copy moved to the view of the window unless it's focused.
+    buf: Cow<'a, buffer::Buffer>,
+    focused: bool,
+    /// Border drawn on the right, against the window beside it.
+    border: bool,
+    gutter_width: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Window<'a> {
+    const WRAP_INDICATOR: char = '↪';
+    const BORDER: &'static str = "│";
+    const SELECTION_STYLE: Style = Style::new().add_modifier(Modifier::REVERSED);
+    const MATCH_STYLE: Style =
+        Style::new().add_modifier(Modifier::BOLD.union(Modifier::UNDERLINED));
+    const SEARCH_STYLE: Style = Style::new().fg(Color::Black).bg(Color::Yellow);
+    const CONFIRM_STYLE: Style = Style::new().fg(Color::Black).bg(Color::LightRed);
+
+    /// Window `id` laid out over `rect`.
+    fn new(editor: &'a DuzzyEditor, id: ViewId, rect: Rect) -> Option<Self> {
+        let workspace = &editor.workspace;
+        let windows = workspace.windows();
+        let view = windows.view(id)?;
+        let doc = workspace.document(view.doc)?;
+        let focused = id == windows.focus();
+
+        let inner = windows.inner(rect);
+        let border = inner.width < rect.width;
+        let (width, height) = (inner.width as usize, inner.height as usize);
+        let gutter_width = editor.window_gutter_width(doc.buf().len_lines(), width);
+        let width = width - gutter_width;
+
+        let buf = match focused {
+            true => Cow::Borrowed(doc.buf()),
+            false => {
+                let mut buf = doc.buf().clone();
+                buf.set_view_state(view.state);
+
+                // the window may have been resized since it was left
+                let options = workspace.options();
+                if options.wrap {
+                    buf.update_wrapped_vscroll(height, width, options.tab_width);
+                } else {
+                    buf.update_vscroll(height);
+                    buf.update_hscroll(width, options.tab_width);
+                }
+
+                Cow::Owned(buf)
+            }
+        };
+
+        Some(Self {
+            editor,
+            doc,
+            buf,
+            focused,
+            border,
+            gutter_width,
+            width,
+            height,
+        })
+    }
+
+    /// Screen rows of the text area, each showing a char range of one buffer line.
+    fn rows(&self) -> Vec<Row> {
+        let buf = &self.buf;
+        let options = self.editor.workspace.options();
+
+        let width = self.width.max(1);
+        let height = self.height;
+
+        let mut rows = Vec::with_capacity(height);
+
+        for index in buf.vscroll()..buf.len_lines() {
+            let len = buf.line_len(index);
+
+            if !options.wrap {
+                rows.push(Row::new(index, 0..len, buf.hscroll(), false));
+            } else {
+                let starts = buf.row_starts(index, width, options.tab_width);
+                for (row, &(start, col)) in starts.iter().enumerate() {
+                    let end = starts.get(row + 1).map_or(len, |&(end, _)| end);
+                    rows.push(Row::new(index, start..end, col, row > 0));
+                }
+            }
+
+            if rows.len() >= height {
+                rows.truncate(height);
+                break;
+            }
+        }
+
+        rows
+    }
+
+    #[inline]
+    fn text(&self, rows: &[Row]) -> Text<'_> {
+        let buf = &self.buf;
+        let tab_width = self.editor.workspace.options().tab_width;
+
+        let highlight = match (buf.selection_range(), buf.block_cols(tab_width)) {
+            (Some(range), _) => Highlight::Range(range),
+            (None, Some(cols)) => Highlight::Block(buf.selected_lines().unwrap_or(0..=0), cols),
+            (None, None) => Highlight::None,
+        };
+
+        // the matching bracket is only looked for on the rows shown
+        let pos = buf.as_char_pos();
+        let bracket = match (buf.text().get_char(pos), rows.first(), rows.last()) {
+            (Some(ch), Some(first), Some(last))
+                if self.focused && !buf.is_command() && sql::is_bracket(ch) =>
+            {
+                let text = buf.text();
+                let end = text.line_to_char((last.index + 1).min(text.len_lines()));
+                let shown = text.line_to_char(first.index)..end;
+                sql::matching_bracket(text.slice(..), buf.literals(), pos, shown)
+            }
+            _ => None,
+        };
+
+        let found = match (self.search_regex(), rows.first(), rows.last()) {
+            (Some(regex), Some(first), Some(last)) => {
+                search::matches(buf, &regex, first.index..last.index + 1)
+            }
+            _ => vec![],
+        };
+
+        let confirm = self
+            .editor
+            .workspace
+            .substitution()
+            .filter(|_| self.focused)
+            .and_then(|substitution| substitution.pending())
+            .map(|candidate| candidate.range.clone());
+
+        let lines: Vec<_> = rows
+            .iter()
+            .map(|row| self.row_line(row, &highlight, bracket, &found, confirm.as_ref()))
+            .collect();
+
+        Text::from(lines)
+    }
+
+    /// Pattern of the search being typed, or of the last one with `hlsearch`.
+    fn search_regex(&self) -> Option<Regex> {
+        let workspace = &self.editor.workspace;
+        let options = workspace.options();
+
+        let pattern = match workspace.search_prompt() {
+            Some(_) => workspace.prompt().input(),
+            None if options.highlight_search => workspace.search()?.pattern.as_str(),
+            None => return None,
+        };
+
+        if pattern.is_empty() {
+            return None;
+        }
+
+        search::regex(pattern, options.ignore_case, options.smart_case).ok()
+    }
+
+    /// Text of `row` as drawn, with tabs expanded, wide graphemes cut by the
+    /// text area edges replaced by spaces, the selection, search matches
+    /// `found`, the match a substitution asks to `confirm` and the `bracket` matching
+    /// the one under the cursor highlighted.
+    fn row_line(
+        &self,
+        row: &Row,
+        highlight: &Highlight,
+        bracket: Option<usize>,
+        found: &[Range<usize>],
+        confirm: Option<&Range<usize>>,
+    ) -> Line<'static> {
+        let buf = &self.buf;
+        let tab_width = self.editor.workspace.options().tab_width;
+
+        let width = self.width.max(1);
+        let (left, right) = (row.col, row.col + width);
+
+        let start = match row.wrapped {
+            true => (row.range.start, row.col),
+            false => (0, 0),
+        };
+
+        let line_char = buf.line_char(row.index);
+        let selected = |offset: usize, cols: Range<usize>| match highlight {
+            Highlight::Range(range) => range.contains(&(line_char + offset)),
+            Highlight::Block(lines, block) => {
+                lines.contains(&row.index) && cols.start < block.end && cols.end > block.start
+            }
+            Highlight::None => false,
+        };
+
+        let mut spans = Spans::default();
+        let mut col = left;
+
+        for g in buf.graphemes(row.index, start, tab_width) {
+            if g.offset >= row.range.end || g.col >= right {
+                break;
+            }
+
+            let end = g.col + g.width;
+            if end <= left {
+                continue;
+            }
+
+            let style = match selected(g.offset, g.col..end) {
+                true => Self::SELECTION_STYLE,
+                false if confirm.is_some_and(|range| range.contains(&(line_char + g.offset))) => {
+                    Self::CONFIRM_STYLE
+                }
+                false if search::is_match(found, line_char + g.offset) => Self::SEARCH_STYLE,
+                false if bracket == Some(line_char + g.offset) => Self::MATCH_STYLE,
+                false => Style::new(),
+            };
+
+            if g.is_tab() || g.col < left || end > right {
+                let visible = end.min(right) - g.col.max(left);
+                spans.push(std::iter::repeat_n(' ', visible), style);
+            } else if g.width > 0 {
+                spans.push(g.slice.chars(), style);
+            }
+
+            col = end;
+        }
+
+        // a selected line break shows as one highlighted cell past the line end
+        let len = buf.line_len(row.index);
+        let has_break = row.index + 1 < buf.len_lines();
+        let break_selected =
+            matches!(highlight, Highlight::Range(range) if range.contains(&(line_char + len)));
+        if row.range.end == len && has_break && break_selected && col < right {
+            spans.push(std::iter::once(' '), Self::SELECTION_STYLE);
+        }
+
+        spans.into()
+    }
+
+    #[inline]
+    fn gutter(&self, rows: &[Row]) -> Text<'_> {
+        let buf = &self.buf;
+        let line_numbers = self.editor.workspace.options().line_numbers;
+
+        let width = self.gutter_width.saturating_sub(1);
+        let cursor = buf.index();
+
+        let lines: Vec<_> = rows
+            .iter()
+            .map(|row| {
+                let style = if row.index == cursor {
+                    Style::new().fg(Color::Yellow)
+                } else {
+                    Style::new().fg(Color::DarkGray)
+                };
+
+                if row.wrapped {
+                    return Line::styled(format!("{:>width$} ", Self::WRAP_INDICATOR), style);
+                }
+
+                let number = line_numbers.number(row.index, cursor);
+                Line::styled(format!("{number:>width$} "), style)
+            })
+            .collect();
+
+        Text::from(lines)
+    }
+}
+
+impl<'a> Widget for Window<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [text_area, status_area] = Layout::vertical([
+            Constraint::Length(self.height as u16),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let [gutter_area, text_area, border_area] = Layout::horizontal([
+            Constraint::Length(self.gutter_width as u16),
+            Constraint::Length(self.width as u16),
+            Constraint::Length(self.border.into()),
+        ])
+        .areas(text_area);
+
+        let rows = self.rows();
+
+        if self.gutter_width > 0 {
+            Paragraph::new(self.gutter(&rows)).render(gutter_area, buf);
+        }
+
+        Paragraph::new(self.text(&rows)).render(text_area, buf);
+
+        if self.border {
+            let style = Style::new().fg(Color::DarkGray);
+            for y in border_area.top()..border_area.bottom() {
+                buf.get_mut(border_area.x, y)
+                    .set_symbol(Self::BORDER)
+                    .set_style(style);
             }
-            None => log::warn!("nothing to render!"),
         }
+
+        StatusLine {
+            editor: self.editor,
+            doc: self.doc,
+            buf: &self.buf,
+            focused: self.focused,
+        }
+        .render(status_area, buf);
+    }
+}
+
+/// Status line of a window, with the mode and the pending keys when it's focused.
+struct StatusLine<'a> {
+    editor: &'a DuzzyEditor,
+    doc: &'a Document,
+    buf: &'a buffer::Buffer,
+    focused: bool,
+}
+
+impl<'a> StatusLine<'a> {
+    const fn mode_style(mode: CursorMode) -> Style {
+        let color = match mode {
+            CursorMode::Normal => Color::Blue,
+            CursorMode::Insert => Color::Green,
+            CursorMode::Visual => Color::Magenta,
+            CursorMode::Command => Color::Yellow,
+        };
+
+        Style::new()
+            .fg(Color::Black)
+            .bg(color)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    fn left(&self) -> Line<'a> {
+        let doc = self.doc;
+        let mode = self.buf.mode();
+
+        let mut spans = vec![];
+        if self.focused {
+            spans.push(Span::styled(
+                format!(" {} ", self.buf.mode_name()),
+                Self::mode_style(mode),
+            ));
+        }
+        spans.push(Span::raw(format!(" {}", doc.meta().name())));
+
+        if doc.is_modified() {
+            spans.push(Span::raw(" [+]"));
+        }
+
+        if doc.meta().readonly {
+            spans.push(Span::raw(" [RO]"));
+        }
+
+        Line::from(spans)
+    }
+
+    fn right(&self) -> Line<'a> {
+        let buf = self.buf;
+        let (index, offset) = buf.pos();
+
+        let pending: String = match self.focused {
+            true => self
+                .editor
+                .pending_keys()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            false => String::new(),
+        };
+
+        Line::raw(format!(
+            "{pending}  {}:{}  {}L ",
+            index + 1,
+            offset + 1,
+            buf.len_lines()
+        ))
+    }
+}
+
+impl<'a> Widget for StatusLine<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, Style::new().bg(Color::DarkGray));
+
+        Paragraph::new(self.left()).render(area, buf);
+        Paragraph::new(self.right().right_aligned()).render(area, buf);
+    }
+}
+
+/// Open documents listed over the bottom of the text area, the picked one highlighted.
+pub struct PickerList<'a>(&'a DuzzyEditor);
+
+impl<'a> PickerList<'a> {
+    fn lines(&self) -> Vec<Line<'a>> {
+        let workspace = &self.0.workspace;
+        let Some(picker) = workspace.picker() else {
+            return vec![];
+        };
+
+        picker
+            .ids()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| Some((i, *id, workspace.document(*id)?)))
+            .map(|(i, id, doc)| {
+                let marker = match id {
+                    id if id == workspace.current() => '%',
+                    id if Some(id) == workspace.alternate() => '#',
+                    _ => ' ',
+                };
+
+                let mut line = format!("{marker}{:>3} {}", i + 1, doc.meta().name());
+                if doc.is_modified() {
+                    line.push_str(" [+]");
+                }
+                if doc.meta().readonly {
+                    line.push_str(" [RO]");
+                }
+
+                match i == picker.selected() {
+                    true => Line::styled(line, Style::new().add_modifier(Modifier::REVERSED)),
+                    false => Line::raw(line),
+                }
+            })
+            .collect()
+    }
+}
+
+impl<'a> Widget for PickerList<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let lines = self.lines();
+        let selected = self.0.workspace.picker().map_or(0, |p| p.selected());
+
+        // two rows go to the borders
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let visible = height.saturating_sub(2) as usize;
+        let scroll = (selected + 1).saturating_sub(visible);
+
+        let area = Rect {
+            y: area.bottom() - height,
+            height,
+            ..area
+        };
+
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .scroll((scroll as u16, 0))
+            .block(Block::default().borders(Borders::ALL).title(" Documents "))
+            .render(area, buf);
+    }
+}
+
+/// Tab pages listed at the top, each named after the document of its focused window.
+struct TabLine<'a>(&'a DuzzyEditor);
+
+impl<'a> TabLine<'a> {
+    const STYLE: Style = Style::new().bg(Color::DarkGray);
+    const CURRENT_STYLE: Style = Style::new().bg(Color::Reset).add_modifier(Modifier::BOLD);
+
+    fn labels(&self) -> Vec<String> {
+        let workspace = &self.0.workspace;
+
+        workspace
+            .tabs()
+            .iter()
+            .enumerate()
+            .map(|(i, windows)| {
+                // the current tab page shows the current document, not yet saved in its view
+                let id = match i == workspace.tab() {
+                    true => workspace.current(),
+                    false => windows.focused().doc,
+                };
+
+                let mut label = format!(" {} ", i + 1);
+                if let Some(doc) = workspace.document(id) {
+                    label.push_str(&doc.meta().name());
+                    if doc.is_modified() {
+                        label.push_str(" [+]");
+                    }
+                }
+                label.push(' ');
+
+                label
+            })
+            .collect()
+    }
+}
+
+impl<'a> Widget for TabLine<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let labels = self.labels();
+        let current = self.0.workspace.tab();
+
+        // the first tab pages give way to the current one when they don't all fit
+        let width = |labels: &[String]| labels.iter().map(|l| l.width()).sum::<usize>();
+        let mut first = 0;
+        while first < current && width(&labels[first..=current]) > area.width as usize {
+            first += 1;
+        }
+
+        let spans: Vec<_> = labels
+            .into_iter()
+            .enumerate()
+            .skip(first)
+            .map(|(i, label)| match i == current {
+                true => Span::styled(label, Self::CURRENT_STYLE),
+                false => Span::raw(label),
+            })
+            .collect();
+
+        buf.set_style(area, Self::STYLE);
+        Paragraph::new(Line::from(spans)).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use crate::test_utils::{key, keys};
+
+    use super::*;
+
+    fn render(editor: &DuzzyEditor) -> Vec<String> {
+        let (width, height) = editor.viewport();
+        let area = Rect::new(0, 0, width as _, (height + Viewport::RESERVED_ROWS) as _);
+        let mut buf = Buffer::empty(area);
+        editor.widget().render(area, &mut buf);
+
+        // cells covered by a wide symbol are skipped like a terminal does
+        (0..area.height)
+            .map(|y| {
+                let mut line = String::new();
+                let mut x = 0;
+                while x < area.width {
+                    let symbol = buf.get(x, y).symbol();
+                    line.push_str(symbol);
+                    x += symbol.width().max(1) as u16;
+                }
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_document_picker() {
+        let mut editor = DuzzyEditor::new(30, 8);
+        for path in ["a.sql", "b.sql", "c.sql"] {
+            editor.open_file(path).unwrap();
+        }
+
+        keys(&mut editor, "gagb");
+        key(&mut editor, KeyCode::Down, KeyModifiers::NONE);
+
+        let lines = render(&editor);
+        assert_eq!("  1", lines[0].trim_end());
+        assert_eq!("┌ Documents ─────────────────┐", lines[1]);
+        assert_eq!("│   1 a.sql                  │", lines[2]);
+        assert_eq!("│%  2 b.sql                  │", lines[3]);
+        assert_eq!("│#  3 c.sql                  │", lines[4]);
+        assert_eq!("└────────────────────────────┘", lines[5]);
+    }
+
+    #[test]
+    fn test_split() {
+        let mut editor = DuzzyEditor::new(44, 8);
+        editor.open_scratch();
+
+        keys(&mut editor, "iselect\rfrom t\x1b");
+
+        key(&mut editor, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        keys(&mut editor, "vggw");
+
+        let lines = render(&editor);
+        assert_eq!("  1 select           │  1 select            ", lines[0]);
+        assert_eq!("  2 from t           │  2 from t            ", lines[1]);
+        assert_eq!("                     │                      ", lines[5]);
+        assert_eq!(" [scratch] [  2:6  2L  NORMAL  [sc  2:1  2L ", lines[6]);
+
+        let cursor = editor.cursor();
+        assert_eq!((26, 1), (cursor.x, cursor.y));
+    }
+
+    #[test]
+    fn test_tab_line() {
+        let mut editor = DuzzyEditor::new(40, 6);
+        editor.open_file("schema.sql").unwrap();
+
+        keys(&mut editor, ":tabnew migration.sql\ri--\x1b");
+
+        let lines = render(&editor);
+        assert_eq!(" 1 schema.sql  2 migration.sql [+]      ", lines[0]);
+        assert_eq!("  1 --", lines[1].trim_end());
+        assert_eq!(" NORMAL  migration.sql [+]      1:3  1L ", lines[4]);
+
+        let area = Rect::new(0, 0, 40, 6);
+        let mut buf = Buffer::empty(area);
+        editor.widget().render(area, &mut buf);
+        let bold = |x| buf.get(x, 0).modifier.contains(Modifier::BOLD);
+        assert!(!bold(1));
+        assert!(bold(15));
+
+        let cursor = editor.cursor();
+        assert_eq!((6, 1), (cursor.x, cursor.y));
+    }
+
+    #[test]
+    fn test_status_line() {
+        let mut editor = DuzzyEditor::new(40, 5);
+        editor.open_scratch();
+
+        keys(&mut editor, "oselect");
+
+        let lines = render(&editor);
+        assert_eq!("  1", lines[0].trim_end());
+        assert_eq!("  2 select", lines[1].trim_end());
+        assert_eq!(" INSERT  [scratch] [+]          2:7  2L ", lines[3]);
+
+        keys(&mut editor, "\x1bg");
+
+        let lines = render(&editor);
+        assert_eq!(" NORMAL  [scratch] [+]       g  2:7  2L ", lines[3]);
+    }
+
+    #[test]
+    fn test_hscroll() {
+        let mut editor = DuzzyEditor::new(12, 5);
+        editor.open_scratch();
+
+        keys(&mut editor, "iselect a, b\rfrom t");
+
+        key(&mut editor, KeyCode::Up, KeyModifiers::NONE);
+        for _ in 0..5 {
+            key(&mut editor, KeyCode::Right, KeyModifiers::NONE);
+        }
+
+        let lines = render(&editor);
+        assert_eq!("  1 ct a, b", lines[0].trim_end());
+        assert_eq!("  2  t", lines[1].trim_end());
+
+        let cursor = editor.cursor();
+        assert_eq!((11, 0), (cursor.x, cursor.y));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let mut editor = DuzzyEditor::new(10, 6);
+        editor.open_scratch();
+        editor.options_mut().set("wrap").unwrap();
+        editor.options_mut().set("visualrows").unwrap();
+
+        keys(&mut editor, "iselect abc, def\rfrom ");
+
+        let lines = render(&editor);
+        assert_eq!("  1 select", lines[0].trim_end());
+        assert_eq!("  ↪  abc, ", lines[1]);
+        assert_eq!("  ↪ def", lines[2].trim_end());
+        assert_eq!("  2 from", lines[3].trim_end());
+
+        let cursor = editor.cursor();
+        assert_eq!((9, 3), (cursor.x, cursor.y));
+
+        // the insert cursor after a full row goes on the row below, scrolled into view
+        keys(&mut editor, "t");
+        let lines = render(&editor);
+        assert_eq!("  2 from t", lines[0].trim_end());
+        let cursor = editor.cursor();
+        assert_eq!((4, 1), (cursor.x, cursor.y));
+
+        key(&mut editor, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(1, editor.workspace.curr().buf().index());
+
+        keys(&mut editor, "\x1bk");
+
+        assert_eq!((0, 15), editor.workspace.curr().buf().pos());
+        let cursor = editor.cursor();
+        assert_eq!((7, 2), (cursor.x, cursor.y));
+
+        keys(&mut editor, "k");
+        assert_eq!((0, 9), editor.workspace.curr().buf().pos());
+    }
+
+    #[test]
+    fn test_display_width() {
+        let mut editor = DuzzyEditor::new(12, 5);
+        editor.open_scratch();
+
+        keys(&mut editor, "i\tx\r表表表表");
+
+        let lines = render(&editor);
+        assert_eq!("  1    x", lines[0].trim_end());
+        assert_eq!("  2  表表表", lines[1].trim_end());
+
+        let cursor = editor.cursor();
+        assert_eq!((11, 1), (cursor.x, cursor.y));
+
+        keys(&mut editor, "\x1bh");
+
+        let cursor = editor.cursor();
+        assert_eq!((9, 1), (cursor.x, cursor.y));
+
+        editor.options_mut().set("ts=2").unwrap();
+        editor.options_mut().set("wrap").unwrap();
+
+        let lines = render(&editor);
+        assert_eq!("  1   x", lines[0].trim_end());
+        assert_eq!("  2 表表表表", lines[1]);
+        assert_eq!("", lines[2].trim_end());
+
+        keys(&mut editor, "k");
+        assert_eq!((0, 2), editor.workspace.curr().buf().pos());
+    }
+
+    #[test]
+    fn test_visual_highlight() {
+        let mut editor = DuzzyEditor::new(20, 5);
+        editor.open_scratch();
+
+        keys(&mut editor, "iab\rcd\x1bggvlV");
+
+        let area = Rect::new(0, 0, 20, 5);
+        let reversed = |buf: &Buffer, x, y| buf.get(x, y).modifier.contains(Modifier::REVERSED);
+
+        let mut buf = Buffer::empty(area);
+        editor.widget().render(area, &mut buf);
+
+        assert!(!reversed(&buf, 3, 0));
+        assert!((4..7).all(|x| reversed(&buf, x, 0)));
+        assert!(!reversed(&buf, 7, 0));
+        assert!(!reversed(&buf, 4, 1));
+        assert_eq!(
+            " V-LINE ",
+            (0..8).map(|x| buf.get(x, 3).symbol()).collect::<String>()
+        );
+
+        keys(&mut editor, "vj");
+
+        let mut buf = Buffer::empty(area);
+        editor.widget().render(area, &mut buf);
+
+        assert!((4..7).all(|x| reversed(&buf, x, 0)));
+        assert!((4..6).all(|x| reversed(&buf, x, 1)));
+        assert!(!reversed(&buf, 6, 1));
+
+        key(&mut editor, KeyCode::Char('v'), KeyModifiers::CONTROL);
+
+        let mut buf = Buffer::empty(area);
+        editor.widget().render(area, &mut buf);
+
+        assert!((4..6).all(|x| reversed(&buf, x, 0)));
+        assert!(!reversed(&buf, 6, 0));
+        assert!((4..6).all(|x| reversed(&buf, x, 1)));
+        assert_eq!(
+            " V-BLOCK ",
+            (0..9).map(|x| buf.get(x, 3).symbol()).collect::<String>()
+        );
+    }
+
+    #[test]
+    fn test_bracket_highlight() {
+        let mut editor = DuzzyEditor::new(20, 5);
+        editor.open_scratch();
+
+        keys(&mut editor, "icount((a))\x1bgh");
+
+        let area = Rect::new(0, 0, 20, 5);
+        let matched = |editor: &DuzzyEditor| {
+            let mut buf = Buffer::empty(area);
+            editor.widget().render(area, &mut buf);
+            (0..20)
+                .filter(|x| buf.get(*x, 0).modifier.contains(Modifier::UNDERLINED))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(matched(&editor).is_empty());
+
+        keys(&mut editor, "%");
+        assert_eq!((0, 9), editor.workspace.curr().buf().pos());
+        assert_eq!(vec![9], matched(&editor));
+    }
+
+    #[test]
+    fn test_search_highlight() {
+        let mut editor = DuzzyEditor::new(30, 5);
+        editor.open_scratch();
+
+        let area = Rect::new(0, 0, 30, 5);
+        let found = |editor: &DuzzyEditor| {
+            let mut buf = Buffer::empty(area);
+            editor.widget().render(area, &mut buf);
+            (0..30)
+                .map(|x| buf.get(x, 0))
+                .filter(|cell| cell.bg == Color::Yellow)
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+        };
+
+        keys(&mut editor, "iselect id, pid\x1bgg/i");
+        assert_eq!("ii", found(&editor));
+        assert_eq!("/i", render(&editor)[4].trim_end());
+
+        keys(&mut editor, "d,");
+        assert_eq!("id,", found(&editor));
+
+        keys(&mut editor, "\r");
+        assert_eq!("", found(&editor));
+
+        editor.options_mut().set("hlsearch").unwrap();
+        assert_eq!("id,", found(&editor));
+    }
+
+    #[test]
+    fn test_gutter() {
+        let mut editor = DuzzyEditor::new(40, 5);
+        editor.open_scratch();
+        editor.options_mut().set("relativenumber").unwrap();
+
+        keys(&mut editor, "oa\rb\rc");
+
+        let lines = render(&editor);
+        assert_eq!("  2 a", lines[0].trim_end());
+        assert_eq!("  1 b", lines[1].trim_end());
+        assert_eq!("  4 c", lines[2].trim_end());
+
+        let cursor = editor.cursor();
+        assert_eq!((5, 2), (cursor.x, cursor.y));
     }
 }
@@ -0,0 +1,158 @@
+use std::{borrow::Cow, ops::Range};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::buffer::Buffer;
+
+/// Pattern typed after `/` or `?`, repeated by `n` and `N`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Search {
+    pub pattern: String,
+    pub forward: bool,
+}
+
+/// Search being typed, with the cursor position to return to when it's cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchPrompt {
+    pub forward: bool,
+    pub origin: (usize, usize),
+}
+
+/// Match found by [`find`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub range: Range<usize>,
+    /// Reached past the end of the text, or past its start going backwards.
+    pub wrapped: bool,
+}
+
+/// Compiles `pattern`, ignoring case with `ignore_case` unless `smart_case`
+/// finds an uppercase letter in it.
+pub fn regex(pattern: &str, ignore_case: bool, smart_case: bool) -> anyhow::Result<Regex> {
+    let has_upper = pattern.chars().any(char::is_uppercase);
+
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case && !(smart_case && has_upper))
+        .build()
+        .map_err(|_| anyhow::anyhow!("Invalid pattern: {pattern}"))
+}
+
+/// Char ranges of the matches on line `index`, searched without its line break.
+/// Lines are borrowed from the rope and only copied when split across chunks.
+fn line_matches(buf: &Buffer, regex: &Regex, index: usize) -> Vec<Range<usize>> {
+    let line = buf.text().line(index).slice(..buf.line_len(index));
+    let start = buf.line_char(index);
+    let text: Cow<str> = line.into();
+
+    regex
+        .find_iter(&text)
+        .map(|m| start + line.byte_to_char(m.start())..start + line.byte_to_char(m.end()))
+        .collect()
+}
+
+/// Non-empty matches on `lines`, in order.
+pub fn matches(buf: &Buffer, regex: &Regex, lines: Range<usize>) -> Vec<Range<usize>> {
+    lines
+        .flat_map(|index| line_matches(buf, regex, index))
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+/// Whether char index `pos` is in one of the sorted `matches`.
+pub fn is_match(matches: &[Range<usize>], pos: usize) -> bool {
+    let idx = matches.partition_point(|range| range.end <= pos);
+    matches.get(idx).is_some_and(|range| range.start <= pos)
+}
+
+/// First match starting after char index `pos`, or the last one starting before it
+/// when going backwards, wrapping around the text.
+pub fn find(buf: &Buffer, regex: &Regex, pos: usize, forward: bool) -> Option<Found> {
+    let len_lines = buf.len_lines();
+    let first = buf.text().char_to_line(pos.min(buf.len_chars()));
+
+    // the cursor line is searched again at the end, for the part left behind
+    for step in 0..=len_lines {
+        let (index, wrapped) = match forward {
+            true => ((first + step) % len_lines, first + step >= len_lines),
+            false => (
+                (first + len_lines - step % len_lines) % len_lines,
+                step > first,
+            ),
+        };
+
+        let matches = line_matches(buf, regex, index);
+        let found = match (forward, step) {
+            (true, 0) => matches.into_iter().find(|range| range.start > pos),
+            (false, 0) => matches.into_iter().rev().find(|range| range.start < pos),
+            (true, _) => matches.into_iter().next(),
+            (false, _) => matches.into_iter().next_back(),
+        };
+
+        if let Some(range) = found {
+            return Some(Found { range, wrapped });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::buffer;
+
+    use super::*;
+
+    #[test]
+    fn test_regex_case() {
+        assert!(regex("select", false, false).unwrap().is_match("select"));
+        assert!(!regex("select", false, false).unwrap().is_match("SELECT"));
+        assert!(regex("select", true, false).unwrap().is_match("SELECT"));
+        assert!(regex("Select", true, false).unwrap().is_match("SELECT"));
+        assert!(!regex("Select", true, true).unwrap().is_match("SELECT"));
+        assert!(regex("select", true, true).unwrap().is_match("SELECT"));
+        assert!(regex("(", false, false).is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        let buf = buffer("select id\nfrom t\nwhere id = 'ид'\n");
+        let re = regex(r"id\b", false, false).unwrap();
+        let find = |pos, forward| find(&buf, &re, pos, forward);
+
+        let found = |range, wrapped| Some(Found { range, wrapped });
+
+        assert_eq!(found(7..9, false), find(0, true));
+        assert_eq!(found(23..25, false), find(7, true));
+        assert_eq!(found(7..9, true), find(23, true));
+        assert_eq!(found(23..25, true), find(7, false));
+        assert_eq!(found(7..9, false), find(23, false));
+        assert_eq!(found(7..9, false), find(8, false));
+
+        let cyrillic = regex("ид", false, false).unwrap();
+        assert_eq!(found(29..31, false), super::find(&buf, &cyrillic, 0, true));
+        assert_eq!(
+            None,
+            super::find(&buf, &regex("x", false, false).unwrap(), 0, true)
+        );
+
+        let only = buffer("a b a");
+        let a = regex("a", false, false).unwrap();
+        assert_eq!(found(0..1, true), super::find(&only, &a, 4, true));
+        assert_eq!(found(4..5, true), super::find(&only, &a, 0, false));
+        assert_eq!(found(0..1, false), super::find(&only, &a, 4, false));
+    }
+
+    #[test]
+    fn test_matches() {
+        let buf = buffer("a1 b22\nc333\n");
+        let re = regex(r"\d*", false, false).unwrap();
+
+        let matches = matches(&buf, &re, 0..buf.len_lines());
+        assert_eq!(vec![1..2, 4..6, 8..11], matches);
+
+        assert!(is_match(&matches, 5));
+        assert!(!is_match(&matches, 6));
+        assert!(!is_match(&matches, 0));
+        assert!(is_match(&matches, 10));
+    }
+}
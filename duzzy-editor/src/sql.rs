@@ -0,0 +1,157 @@
+use std::ops::Range;
+
+use ropey::RopeSlice;
+
+/// Char ranges of the string literals, quoted identifiers and comments in `text`,
+/// the parts of SQL that commands looking at the code skip.
+/// Unterminated ones run to the end of the text.
+pub fn literals(text: RopeSlice) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut chars = text.chars().enumerate().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        let end = match ch {
+            '\'' | '"' | '`' => loop {
+                match chars.next() {
+                    // a doubled quote is an escaped one
+                    Some((_, next)) if next == ch => match chars.peek() {
+                        Some((_, after)) if *after == ch => {
+                            chars.next();
+                        }
+                        _ => break None,
+                    },
+                    Some(_) => (),
+                    None => break Some(text.len_chars()),
+                }
+            },
+            '-' if chars.next_if(|(_, next)| *next == '-').is_some() => {
+                match chars.find(|(_, next)| *next == '\n') {
+                    Some((end, _)) => Some(end),
+                    None => Some(text.len_chars()),
+                }
+            }
+            '/' if chars.next_if(|(_, next)| *next == '*').is_some() => loop {
+                match chars.next() {
+                    Some((_, '*')) if chars.next_if(|(_, next)| *next == '/').is_some() => {
+                        break None
+                    }
+                    Some(_) => (),
+                    None => break Some(text.len_chars()),
+                }
+            },
+            _ => continue,
+        };
+
+        let end = end.unwrap_or_else(|| chars.peek().map_or(text.len_chars(), |(idx, _)| *idx));
+        ranges.push(start..end);
+    }
+
+    ranges
+}
+
+/// Whether char index `pos` is outside of the sorted `literals`.
+pub fn is_code(literals: &[Range<usize>], pos: usize) -> bool {
+    let idx = literals.partition_point(|range| range.end <= pos);
+    literals.get(idx).is_none_or(|range| range.start > pos)
+}
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+pub fn is_bracket(ch: char) -> bool {
+    BRACKETS
+        .iter()
+        .any(|(open, close)| ch == *open || ch == *close)
+}
+
+/// Char index of the bracket matching the one at `pos`, skipping the ones in `literals`
+/// and looking no further than the chars `within`.
+pub fn matching_bracket(
+    text: RopeSlice,
+    literals: &[Range<usize>],
+    pos: usize,
+    within: Range<usize>,
+) -> Option<usize> {
+    let ch = text.get_char(pos)?;
+    let &(open, close) = BRACKETS
+        .iter()
+        .find(|(open, close)| ch == *open || ch == *close)?;
+
+    if !is_code(literals, pos) {
+        return None;
+    }
+
+    let forward = ch == open;
+    let (same, other) = match forward {
+        true => (open, close),
+        false => (close, open),
+    };
+
+    let mut chars = text.chars_at(if forward { pos + 1 } else { pos });
+    let mut idx = pos;
+    let mut depth = 0;
+
+    loop {
+        let ch = match forward {
+            true => chars.next().filter(|_| idx + 1 < within.end)?,
+            false => chars.prev().filter(|_| idx > within.start)?,
+        };
+        idx = if forward { idx + 1 } else { idx - 1 };
+
+        if !is_code(literals, idx) {
+            continue;
+        }
+
+        match ch {
+            ch if ch == other && depth == 0 => return Some(idx),
+            ch if ch == other => depth -= 1,
+            ch if ch == same => depth += 1,
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+
+    #[test]
+    fn test_literals() {
+        let text = Rope::from_str("select 'it''s', \"a;b\" -- c;\nfrom t /* ; */ where x = ';");
+        let ranges = literals(text.slice(..));
+
+        let parts: Vec<_> = ranges
+            .iter()
+            .map(|range| text.slice(range.clone()).to_string())
+            .collect();
+        assert_eq!(vec!["'it''s'", "\"a;b\"", "-- c;", "/* ; */", "';"], parts);
+
+        assert!(is_code(&ranges, 0));
+        assert!(!is_code(&ranges, 7));
+        assert!(!is_code(&ranges, 13));
+        assert!(is_code(&ranges, 14));
+        assert!(!is_code(&ranges, text.len_chars() - 1));
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let text = Rope::from_str("select (a, ')' /* ( */, [b]) from (t\n)");
+        let ranges = literals(text.slice(..));
+        let matching = |pos| matching_bracket(text.slice(..), &ranges, pos, 0..text.len_chars());
+
+        assert_eq!(Some(27), matching(7));
+        assert_eq!(Some(7), matching(27));
+        assert_eq!(Some(26), matching(24));
+        assert_eq!(Some(37), matching(34));
+        assert_eq!(Some(34), matching(37));
+        assert_eq!(None, matching(12));
+        assert_eq!(None, matching(18));
+        assert_eq!(None, matching(0));
+
+        let within = |pos, range| matching_bracket(text.slice(..), &ranges, pos, range);
+        assert_eq!(Some(37), within(34, 30..38));
+        assert_eq!(None, within(34, 30..37));
+        assert_eq!(None, within(27, 8..30));
+    }
+}
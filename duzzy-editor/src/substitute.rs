@@ -0,0 +1,404 @@
+use std::{borrow::Cow, ops::Range};
+
+use regex::{Captures, Regex};
+
+use crate::buffer::Buffer;
+
+/// Arguments of `:s`, as in `/pattern/replacement/flags`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitute {
+    /// Pattern to replace, the last search pattern when empty.
+    pub pattern: String,
+    pub replacement: Replacement,
+    pub flags: Flags,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Flags {
+    /// `g`, replace every match on a line instead of the first one.
+    pub all: bool,
+    /// `c`, ask before each replacement.
+    pub confirm: bool,
+    /// `i` or `I`, ignore or match case whatever the options say.
+    pub ignore_case: Option<bool>,
+}
+
+impl Substitute {
+    /// Splits `args` on the char following `s`, a backslash escaping it.
+    pub fn parse(args: &str) -> anyhow::Result<Self> {
+        let mut chars = args.chars();
+
+        let delimiter = match chars.next() {
+            Some(ch) if !ch.is_alphanumeric() && !matches!(ch, '\\' | '"' | ' ') => ch,
+            Some(_) => anyhow::bail!("Invalid delimiter: {args}"),
+            None => anyhow::bail!("Missing pattern"),
+        };
+
+        let mut parts = vec![String::new()];
+        while let Some(ch) = chars.next() {
+            if ch == delimiter && parts.len() < 3 {
+                parts.push(String::new());
+                continue;
+            }
+
+            let part = parts.last_mut().expect("at least one part");
+            match ch {
+                '\\' => match chars.next() {
+                    Some(next) if next == delimiter => part.push(next),
+                    Some(next) => {
+                        part.push('\\');
+                        part.push(next);
+                    }
+                    None => part.push('\\'),
+                },
+                ch => part.push(ch),
+            }
+        }
+
+        let mut parts = parts.into_iter();
+        let pattern = parts.next().unwrap_or_default();
+        let replacement = Replacement::parse(&parts.next().unwrap_or_default());
+
+        let mut flags = Flags::default();
+        for flag in parts.next().unwrap_or_default().trim().chars() {
+            match flag {
+                'g' => flags.all = true,
+                'c' => flags.confirm = true,
+                'i' => flags.ignore_case = Some(true),
+                'I' => flags.ignore_case = Some(false),
+                _ => anyhow::bail!("Invalid flag: {flag}"),
+            }
+        }
+
+        Ok(Self {
+            pattern,
+            replacement,
+            flags,
+        })
+    }
+}
+
+/// Replacement text, with the whole match put by `&` or `\0`, groups by `\1` to `\9`
+/// and line breaks by `\r` or `\n`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replacement(Vec<Piece>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Group(usize),
+}
+
+impl Replacement {
+    fn parse(text: &str) -> Self {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut chars = text.chars();
+
+        while let Some(ch) = chars.next() {
+            let group = match ch {
+                '&' => 0,
+                '\\' => match chars.next() {
+                    Some(digit @ '0'..='9') => digit as usize - '0' as usize,
+                    Some('r' | 'n') => {
+                        literal.push('\n');
+                        continue;
+                    }
+                    Some('t') => {
+                        literal.push('\t');
+                        continue;
+                    }
+                    Some(next) => {
+                        literal.push(next);
+                        continue;
+                    }
+                    None => {
+                        literal.push('\\');
+                        continue;
+                    }
+                },
+                ch => {
+                    literal.push(ch);
+                    continue;
+                }
+            };
+
+            if !literal.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(&mut literal)));
+            }
+            pieces.push(Piece::Group(group));
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+
+        Self(pieces)
+    }
+
+    /// Text put for a match, groups that didn't take part in it put nothing.
+    pub fn expand(&self, captures: &Captures) -> String {
+        let mut text = String::new();
+
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(literal) => text.push_str(literal),
+                Piece::Group(group) => {
+                    text.push_str(captures.get(*group).map_or("", |m| m.as_str()));
+                }
+            }
+        }
+
+        text
+    }
+}
+
+/// Match waiting to be replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// Char range of the match.
+    pub range: Range<usize>,
+    pub matched: String,
+    /// Text replacing it.
+    pub text: String,
+}
+
+/// Substitution walking the matches of a range of lines, from the top down.
+/// Lines put or taken by replacements move the end of the range along.
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    regex: Regex,
+    replacement: Replacement,
+    all: bool,
+    /// Line searched, from char `offset`, and the last line of the range.
+    index: usize,
+    offset: usize,
+    last: usize,
+    /// End of the last replacement, where an empty match isn't taken again.
+    replaced_end: Option<usize>,
+    /// Line of the last replacement.
+    replaced_line: Option<usize>,
+    /// Match shown for confirmation.
+    pending: Option<Candidate>,
+    /// Cursor position the substitution started at.
+    pub origin: (usize, usize),
+    pub count: usize,
+    pub lines: usize,
+}
+
+impl Substitution {
+    pub const fn new(
+        regex: Regex,
+        replacement: Replacement,
+        all: bool,
+        lines: (usize, usize),
+        origin: (usize, usize),
+    ) -> Self {
+        Self {
+            regex,
+            replacement,
+            all,
+            index: lines.0,
+            offset: 0,
+            last: lines.1,
+            replaced_end: None,
+            replaced_line: None,
+            pending: None,
+            origin,
+            count: 0,
+            lines: 0,
+        }
+    }
+
+    pub const fn pending(&self) -> Option<&Candidate> {
+        self.pending.as_ref()
+    }
+
+    /// Finds the next match and keeps it pending, `None` past the range.
+    pub fn next(&mut self, buf: &Buffer) -> Option<&Candidate> {
+        self.pending = None;
+
+        while self.index <= self.last.min(buf.len_lines() - 1) {
+            let line = buf
+                .text()
+                .line(self.index)
+                .slice(..buf.line_len(self.index));
+            let start = buf.line_char(self.index);
+
+            if self.offset > line.len_chars() {
+                self.next_line();
+                continue;
+            }
+
+            let text: Cow<str> = line.into();
+            let captures = self
+                .regex
+                .captures_at(&text, line.char_to_byte(self.offset));
+
+            let Some(captures) = captures else {
+                self.next_line();
+                continue;
+            };
+
+            let m = captures.get(0).expect("whole match");
+            let range = start + line.byte_to_char(m.start())..start + line.byte_to_char(m.end());
+
+            // an empty match right after a replacement is where the last one ended
+            if range.is_empty() && self.replaced_end == Some(range.start) {
+                self.offset = range.start - start + 1;
+                continue;
+            }
+
+            self.offset = range.start - start;
+            self.pending = Some(Candidate {
+                range,
+                matched: m.as_str().to_string(),
+                text: self.replacement.expand(&captures),
+            });
+
+            return self.pending.as_ref();
+        }
+
+        None
+    }
+
+    /// Moves past the pending match without replacing it.
+    pub fn skip(&mut self) {
+        let Some(candidate) = self.pending.take() else {
+            return;
+        };
+
+        match self.all {
+            true => self.offset += candidate.range.len().max(1),
+            false => self.next_line(),
+        }
+    }
+
+    /// Moves past the pending match once it's been replaced in `buf`.
+    pub fn replaced(&mut self, buf: &Buffer) {
+        let Some(candidate) = self.pending.take() else {
+            return;
+        };
+
+        let end = candidate.range.start + candidate.text.chars().count();
+        let breaks = |text: &str| text.matches('\n').count();
+
+        self.last =
+            (self.last + breaks(&candidate.text)).saturating_sub(breaks(&candidate.matched));
+        self.index = buf.text().char_to_line(end);
+        self.offset = end - buf.line_char(self.index);
+        self.replaced_end = Some(end);
+
+        if self.replaced_line != Some(self.index) {
+            self.lines += 1;
+        }
+        self.replaced_line = Some(self.index);
+        self.count += 1;
+
+        if !self.all {
+            self.next_line();
+        }
+    }
+
+    /// Line of the last replacement.
+    pub const fn replaced_line(&self) -> Option<usize> {
+        self.replaced_line
+    }
+
+    const fn next_line(&mut self) {
+        self.index += 1;
+        self.offset = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::buffer;
+
+    use super::*;
+
+    /// Replaces every match the way `:s` does, without a transaction.
+    fn substitute(text: &str, args: &str) -> String {
+        let mut buf = buffer(text);
+        let substitute = Substitute::parse(args).unwrap();
+        let regex = Regex::new(&substitute.pattern).unwrap();
+        let lines = (0, buf.len_lines() - 1);
+
+        let mut substitution = Substitution::new(
+            regex,
+            substitute.replacement,
+            substitute.flags.all,
+            lines,
+            (0, 0),
+        );
+
+        while let Some(candidate) = substitution.next(&buf).cloned() {
+            buf.text_mut().remove(candidate.range.clone());
+            buf.text_mut()
+                .insert(candidate.range.start, &candidate.text);
+            substitution.replaced(&buf);
+        }
+
+        buf.text().to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        let substitute = Substitute::parse(r"/a\/b/c/gi").unwrap();
+        assert_eq!("a/b", substitute.pattern);
+        assert_eq!(
+            Replacement(vec![Piece::Text("c".into())]),
+            substitute.replacement
+        );
+        assert_eq!(
+            Flags {
+                all: true,
+                confirm: false,
+                ignore_case: Some(true)
+            },
+            substitute.flags
+        );
+
+        let substitute = Substitute::parse(r"#(\w+)\.#\1 & \&\\#c").unwrap();
+        assert_eq!(r"(\w+)\.", substitute.pattern);
+        assert_eq!(
+            Replacement(vec![
+                Piece::Group(1),
+                Piece::Text(" ".into()),
+                Piece::Group(0),
+                Piece::Text(r" &\".into()),
+            ]),
+            substitute.replacement
+        );
+        assert!(substitute.flags.confirm);
+
+        assert_eq!(
+            "",
+            Substitute::parse("/a")
+                .unwrap()
+                .replacement
+                .expand(&Regex::new("a").unwrap().captures("a").unwrap())
+        );
+        assert!(Substitute::parse("").is_err());
+        assert!(Substitute::parse("xaxbx").is_err());
+        assert!(Substitute::parse("/a/b/q").is_err());
+    }
+
+    #[test]
+    fn test_substitution() {
+        assert_eq!(
+            "select b, a\nfrom t",
+            substitute("select a, a\nfrom t", "/a/b/")
+        );
+        assert_eq!(
+            "select b, b\nfrom t",
+            substitute("select a, a\nfrom t", "/a/b/g")
+        );
+        assert_eq!(
+            "select t.id, t.name",
+            substitute("select id, name", r"/(\w+)(,|$)/t.\1\2/g")
+        );
+        assert_eq!("-a-b-", substitute("ab", "/x*/-/g"));
+        assert_eq!("a\nb\nc\nd", substitute("a,b\nc,d", r"/,/\r/"));
+    }
+}
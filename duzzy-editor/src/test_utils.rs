@@ -0,0 +1,52 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ropey::Rope;
+
+use crate::{
+    buffer::Buffer,
+    document::Document,
+    editor::{DuzzyEditor, Workspace},
+    renderer::EventOutcome,
+    transaction::TransactionResult,
+};
+
+pub fn buffer(text: &str) -> Buffer {
+    let mut buf = Buffer::default();
+    buf.set_text(Rope::from_str(text));
+    buf
+}
+
+/// Workspace with one document holding `text`, inserted as a change of its own.
+pub fn workspace(text: &str) -> Workspace {
+    let mut ws = Workspace::default();
+    ws.add_doc(Document::default());
+
+    ws.curr_mut().with_transaction(|tx, buf| {
+        tx.insert_str(0, text);
+        tx.apply(buf.text_mut());
+        TransactionResult::Commit
+    });
+
+    ws
+}
+
+/// Text of the current document.
+pub fn text(ws: &Workspace) -> String {
+    ws.curr().buf().text().to_string()
+}
+
+pub fn key(editor: &mut DuzzyEditor, code: KeyCode, modifiers: KeyModifiers) -> EventOutcome {
+    editor.on_event(Event::Key(KeyEvent::new(code, modifiers)))
+}
+
+/// Types `keys` into the editor, `\r` as enter, `\t` as tab and `\x1b` as escape.
+pub fn keys(editor: &mut DuzzyEditor, keys: &str) {
+    for ch in keys.chars() {
+        let code = match ch {
+            '\r' => KeyCode::Enter,
+            '\t' => KeyCode::Tab,
+            '\x1b' => KeyCode::Esc,
+            ch => KeyCode::Char(ch),
+        };
+        key(editor, code, KeyModifiers::NONE);
+    }
+}
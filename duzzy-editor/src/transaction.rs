@@ -1,28 +1,12 @@
 use crate::SmartString;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Action {
     Insert(Change),
     Delete(Change),
     Move(usize),
 }
 
-impl Action {
-    fn as_insert_mut(&mut self) -> Option<&mut Change> {
-        match self {
-            Self::Insert(change) => Some(change),
-            _ => None,
-        }
-    }
-
-    fn as_delete_mut(&mut self) -> Option<&mut Change> {
-        match self {
-            Self::Delete(change) => Some(change),
-            _ => None,
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 struct Change {
     content: SmartString,
@@ -45,7 +29,13 @@ impl Action {
     }
 }
 
-#[derive(Debug, Default)]
+/// Changes to a text in the order they're applied. A change joins the previous one
+/// only when it continues it, like chars typed one after another or deleted by
+/// backspaces, so changes made anywhere can be recorded in one transaction.
+///
+/// Each change is at a position in the text left by the ones before it, so changes
+/// to several lines go from the bottom up to keep the positions of the lines above.
+#[derive(Debug, Default, Clone)]
 pub struct Transaction {
     changes: Vec<Action>,
 }
@@ -55,6 +45,12 @@ impl Transaction {
         Self { changes: vec![] }
     }
 
+    pub fn has_changes(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| !matches!(change, Action::Move(_)))
+    }
+
     pub fn inverse(&self) -> Self {
         let changes = self.changes.iter().rev().map(Action::inverse).collect();
 
@@ -64,8 +60,8 @@ impl Transaction {
     pub fn merge(&mut self, tx: Self) {
         for change in tx.changes {
             match change {
-                Action::Insert(c) => self.insert_str(c.pos, &c.content),
-                Action::Delete(c) => self.delete_str(c.pos, &c.content),
+                Action::Insert(c) => self.insert_impl(c.pos, c.content),
+                Action::Delete(c) => self.delete_impl(c.pos, c.content),
                 Action::Move(pos) => self.shift(pos),
             }
         }
@@ -91,6 +87,22 @@ impl Transaction {
         last_pos
     }
 
+    /// Char index `pos` moved along with the text around it by the changes,
+    /// kept after text inserted right at it.
+    pub fn map_pos(&self, mut pos: usize) -> usize {
+        for change in &self.changes {
+            match change {
+                Action::Insert(c) if c.pos <= pos => pos += c.content.chars().count(),
+                Action::Delete(c) if c.pos < pos => {
+                    pos = pos.saturating_sub(c.content.chars().count()).max(c.pos);
+                }
+                _ => (),
+            }
+        }
+
+        pos
+    }
+
     pub fn shift(&mut self, pos: usize) {
         if let Some(Action::Move(p)) = self.changes.last_mut() {
             *p = pos;
@@ -100,64 +112,46 @@ impl Transaction {
     }
 
     pub fn insert_char(&mut self, pos: usize, ch: char) {
-        self.insert_impl(pos, |content| content.push(ch));
+        let mut content = SmartString::new_const();
+        content.push(ch);
+        self.insert_impl(pos, content);
     }
 
     pub fn insert_str(&mut self, pos: usize, slice: &str) {
-        self.insert_impl(pos, |content| content.push_str(slice));
+        self.insert_impl(pos, slice.into());
     }
 
-    fn insert_impl<F>(&mut self, pos: usize, func: F)
-    where
-        F: Fn(&mut SmartString),
-    {
-        let maybe_change = self.changes.last_mut().and_then(|c| c.as_insert_mut());
-
-        if let Some(change) = Self::merge_or_new(maybe_change, pos, func) {
-            self.changes.push(Action::Insert(change));
+    fn insert_impl(&mut self, pos: usize, content: SmartString) {
+        match self.changes.last_mut() {
+            Some(Action::Insert(change)) if change.pos + change.content.chars().count() == pos => {
+                change.content.push_str(&content);
+            }
+            _ => self.changes.push(Action::Insert(Change { content, pos })),
         }
     }
 
+    /// Deletes char `ch` at char index `pos`.
     pub fn delete_char(&mut self, pos: usize, ch: char) {
-        self.delete_impl(pos, |content| content.push(ch));
+        let mut content = SmartString::new_const();
+        content.push(ch);
+        self.delete_impl(pos, content);
     }
 
+    /// Deletes `slice` ending at char index `pos`.
     pub fn delete_str(&mut self, pos: usize, slice: &str) {
         let pos = pos.saturating_sub(slice.chars().count());
-        let slice: SmartString = slice.chars().rev().collect();
-        self.delete_impl(pos, |content| content.push_str(&slice));
-    }
-
-    fn delete_impl<F>(&mut self, pos: usize, func: F)
-    where
-        F: Fn(&mut SmartString),
-    {
-        let mut maybe_change = self.changes.last_mut().and_then(|c| c.as_delete_mut());
-
-        if let Some(change) = maybe_change.as_deref_mut() {
-            change.pos = pos;
-        };
-
-        if let Some(change) = Self::merge_or_new(maybe_change, pos, func) {
-            self.changes.push(Action::Delete(change));
-        }
+        let content = slice.chars().rev().collect();
+        self.delete_impl(pos, content);
     }
 
-    fn merge_or_new<F>(maybe_change: Option<&mut Change>, pos: usize, func: F) -> Option<Change>
-    where
-        F: Fn(&mut SmartString),
-    {
-        match maybe_change {
-            Some(change) => {
-                func(&mut change.content);
-                None
-            }
-            None => {
-                let mut content = SmartString::new_const();
-                func(&mut content);
-
-                Some(Change { content, pos })
+    /// Deletes the chars of `content`, kept reversed, from char index `pos`.
+    fn delete_impl(&mut self, pos: usize, content: SmartString) {
+        match self.changes.last_mut() {
+            Some(Action::Delete(change)) if pos + content.chars().count() == change.pos => {
+                change.pos = pos;
+                change.content.push_str(&content);
             }
+            _ => self.changes.push(Action::Delete(Change { content, pos })),
         }
     }
 }
@@ -203,4 +197,46 @@ mod tests {
             assert_eq!(&text.to_string(), "te te");
         }
     }
+
+    #[test]
+    fn test_apart_changes() {
+        let mut text = ropey::Rope::from("select a, b from t");
+
+        let mut tx = Transaction::new();
+        tx.delete_str(16, "from");
+        tx.delete_str(8, "a");
+        tx.insert_str(7, "x");
+        tx.insert_str(10, "y");
+        tx.apply(&mut text);
+        assert_eq!("select x, yb  t", text.to_string());
+
+        tx.inverse().apply(&mut text);
+        assert_eq!("select a, b from t", text.to_string());
+
+        // deleting forward from the same char twice
+        let mut tx = Transaction::new();
+        tx.delete_str(8, "a");
+        tx.delete_str(8, ",");
+        let mut merged = Transaction::new();
+        merged.merge(tx);
+        merged.apply(&mut text);
+        assert_eq!("select  b from t", text.to_string());
+
+        merged.inverse().apply(&mut text);
+        assert_eq!("select a, b from t", text.to_string());
+    }
+
+    #[test]
+    fn test_map_pos() {
+        let mut tx = Transaction::new();
+        tx.insert_str(0, "-- t\n");
+        tx.delete_str(15, "a, b");
+        tx.shift(0);
+
+        assert_eq!(5, tx.map_pos(0));
+        assert_eq!(11, tx.map_pos(6));
+        assert_eq!(11, tx.map_pos(8));
+        assert_eq!(12, tx.map_pos(11));
+        assert_eq!(0, tx.inverse().map_pos(5));
+    }
 }
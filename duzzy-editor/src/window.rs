@@ -0,0 +1,509 @@
+use std::collections::BTreeMap;
+
+use ratatui::layout::{Constraint, Layout, Rect};
+
+use crate::{buffer::ViewState, document::DocumentId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ViewId(usize);
+
+/// Document shown in a window, with the cursor, scroll and selection of that window.
+/// While the view has the focus, its state lives in the buffer of the document instead.
+#[derive(Debug, Clone)]
+pub struct View {
+    pub doc: DocumentId,
+    pub state: ViewState,
+}
+
+/// How a window is split, `Horizontal` stacking the windows and `Vertical`
+/// putting them side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+/// Layout tree, the children of a split weighted by the cells they take.
+#[derive(Debug, Clone)]
+enum Node {
+    View(ViewId),
+    Split(Split, Vec<(Self, u16)>),
+}
+
+impl Node {
+    fn contains(&self, id: ViewId) -> bool {
+        match self {
+            Self::View(view) => *view == id,
+            Self::Split(_, children) => children.iter().any(|(child, _)| child.contains(id)),
+        }
+    }
+
+    fn first(&self) -> ViewId {
+        match self {
+            Self::View(view) => *view,
+            Self::Split(_, children) => children[0].0.first(),
+        }
+    }
+
+    /// Areas of the views under this node, in layout order.
+    fn areas(&self, area: Rect, areas: &mut Vec<(ViewId, Rect)>) {
+        match self {
+            Self::View(view) => areas.push((*view, area)),
+            Self::Split(split, children) => {
+                let total = children.iter().map(|(_, weight)| u32::from(*weight)).sum();
+                let constraints = children
+                    .iter()
+                    .map(|(_, weight)| Constraint::Ratio(u32::from(*weight), total));
+
+                let rects = match split {
+                    Split::Horizontal => Layout::vertical(constraints).split(area),
+                    Split::Vertical => Layout::horizontal(constraints).split(area),
+                };
+
+                for ((child, _), rect) in children.iter().zip(rects.iter()) {
+                    child.areas(*rect, areas);
+                }
+            }
+        }
+    }
+
+    /// Split node directly holding `id`, with the position of the child leading to it.
+    fn parent_of(&mut self, id: ViewId, split: Option<Split>) -> Option<(&mut Self, usize)> {
+        let (kind, position, nested) = match &*self {
+            Self::Split(kind, children) => {
+                let position = children.iter().position(|(child, _)| child.contains(id))?;
+                // the nearest matching split wins over this one
+                (*kind, position, children[position].0.has_parent(id, split))
+            }
+            Self::View(_) => return None,
+        };
+
+        if nested {
+            let Self::Split(_, children) = self else {
+                unreachable!("matched a split");
+            };
+            return children[position].0.parent_of(id, split);
+        }
+
+        split
+            .is_none_or(|split| split == kind)
+            .then_some((self, position))
+    }
+
+    fn has_parent(&self, id: ViewId, split: Option<Split>) -> bool {
+        let Self::Split(kind, children) = self else {
+            return false;
+        };
+
+        children.iter().any(|(child, _)| {
+            child.contains(id)
+                && (split.is_none_or(|split| split == *kind) || child.has_parent(id, split))
+        })
+    }
+
+    /// Sets the weights of the children of this split to the cells they take in `area`.
+    fn measure(&mut self, areas: &[(ViewId, Rect)]) {
+        let Self::Split(split, children) = self else {
+            return;
+        };
+
+        for (child, weight) in children.iter_mut() {
+            let rects: Vec<_> = areas
+                .iter()
+                .filter(|(id, _)| child.contains(*id))
+                .map(|(_, rect)| *rect)
+                .collect();
+
+            let size = match split {
+                Split::Horizontal => {
+                    rects.iter().map(|r| r.bottom()).max().unwrap_or(0)
+                        - rects.iter().map(|r| r.y).min().unwrap_or(0)
+                }
+                Split::Vertical => {
+                    rects.iter().map(|r| r.right()).max().unwrap_or(0)
+                        - rects.iter().map(|r| r.x).min().unwrap_or(0)
+                }
+            };
+
+            *weight = size.max(1);
+        }
+    }
+
+    fn equalize(&mut self) {
+        if let Self::Split(_, children) = self {
+            for (child, weight) in children.iter_mut() {
+                *weight = 1;
+                child.equalize();
+            }
+        }
+    }
+}
+
+/// Windows of the editor, splitting the screen between views of documents.
+#[derive(Debug, Clone)]
+pub struct Windows {
+    root: Node,
+    views: BTreeMap<ViewId, View>,
+    focus: ViewId,
+    next_id: usize,
+    /// Screen area shared by the windows, the status lines included.
+    area: Rect,
+}
+
+impl Windows {
+    /// Rows or columns a window keeps at least, a status line and a text row when stacked.
+    const MIN_SIZE: u16 = 2;
+
+    pub fn new(doc: DocumentId) -> Self {
+        let id = ViewId(0);
+        let view = View {
+            doc,
+            state: ViewState::default(),
+        };
+
+        Self {
+            root: Node::View(id),
+            views: BTreeMap::from([(id, view)]),
+            focus: id,
+            next_id: 1,
+            area: Rect::default(),
+        }
+    }
+
+    pub const fn area(&self) -> Rect {
+        self.area
+    }
+
+    pub const fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    pub const fn focus(&self) -> ViewId {
+        self.focus
+    }
+
+    pub fn view(&self, id: ViewId) -> Option<&View> {
+        self.views.get(&id)
+    }
+
+    pub fn views(&self) -> impl Iterator<Item = &View> {
+        self.views.values()
+    }
+
+    pub fn views_mut(&mut self) -> impl Iterator<Item = &mut View> {
+        self.views.values_mut()
+    }
+
+    pub fn focused(&self) -> &View {
+        &self.views[&self.focus]
+    }
+
+    pub fn focused_mut(&mut self) -> &mut View {
+        self.views.get_mut(&self.focus).expect("focused view")
+    }
+
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    /// Areas of the views on the screen, from the top left.
+    pub fn areas(&self) -> Vec<(ViewId, Rect)> {
+        let mut areas = Vec::with_capacity(self.views.len());
+        self.root.areas(self.area, &mut areas);
+        areas
+    }
+
+    pub fn focused_area(&self) -> Rect {
+        self.area_of(self.focus).unwrap_or(self.area)
+    }
+
+    fn area_of(&self, id: ViewId) -> Option<Rect> {
+        self.areas()
+            .into_iter()
+            .find(|(view, _)| *view == id)
+            .map(|(_, rect)| rect)
+    }
+
+    /// Part of window `rect` showing text, above its status line and left to
+    /// the border drawn against the window on its right.
+    pub const fn inner(&self, rect: Rect) -> Rect {
+        let border = (rect.x + rect.width < self.area.x + self.area.width) as u16;
+
+        Rect {
+            width: rect.width.saturating_sub(border),
+            height: rect.height.saturating_sub(1),
+            ..rect
+        }
+    }
+
+    /// Splits the focused window in two showing the same view, the new one taking the focus.
+    pub fn split(&mut self, split: Split) -> anyhow::Result<ViewId> {
+        let rect = self.focused_area();
+        let size = match split {
+            Split::Horizontal => rect.height,
+            Split::Vertical => rect.width,
+        };
+
+        if size < Self::MIN_SIZE * 2 {
+            anyhow::bail!("Not enough room");
+        }
+
+        let id = ViewId(self.next_id);
+        self.next_id += 1;
+        self.views.insert(id, self.focused().clone());
+
+        let areas = self.areas();
+        let focus = self.focus;
+        let (first, second) = (size - size / 2, size / 2);
+
+        // the window joins a split of the same kind, or gets split itself
+        let parent = self
+            .root
+            .parent_of(focus, None)
+            .filter(|(parent, _)| matches!(parent, Node::Split(kind, _) if *kind == split));
+
+        if let Some((parent, position)) = parent {
+            parent.measure(&areas);
+            if let Node::Split(_, children) = parent {
+                children[position].1 = first;
+                children.insert(position + 1, (Node::View(id), second));
+            }
+        } else {
+            let node = self.node_mut(focus).expect("focused node");
+            *node = Node::Split(
+                split,
+                vec![(Node::View(focus), first), (Node::View(id), second)],
+            );
+        }
+
+        self.focus = id;
+        Ok(id)
+    }
+
+    /// Closes window `id`, its room going to a neighbour which takes the focus
+    /// if `id` had it. The last window can't be closed.
+    pub fn close(&mut self, id: ViewId) -> anyhow::Result<()> {
+        if self.views.len() == 1 {
+            anyhow::bail!("Cannot close last window");
+        }
+
+        let (parent, position) = self.root.parent_of(id, None).expect("split above a view");
+        let Node::Split(_, children) = parent else {
+            unreachable!("parent is a split");
+        };
+
+        let (_, weight) = children.remove(position);
+        let neighbour = position.min(children.len() - 1);
+        children[neighbour].1 += weight;
+        let next_focus = children[neighbour].0.first();
+
+        // a split left with one child is replaced by it
+        if children.len() == 1 {
+            let (child, _) = children.remove(0);
+            *parent = child;
+        }
+
+        self.views.remove(&id);
+        if self.focus == id {
+            self.focus = next_focus;
+        }
+
+        Ok(())
+    }
+
+    /// Closes every window but the focused one.
+    pub fn only(&mut self) {
+        self.root = Node::View(self.focus);
+        self.views.retain(|id, _| *id == self.focus);
+    }
+
+    /// Grows the focused window by `delta` cells across `split`, shrinks it when negative,
+    /// taking the room from its next neighbour, or the previous one for the last window.
+    pub fn resize(&mut self, split: Split, delta: i32) {
+        let areas = self.areas();
+        let Some((parent, position)) = self.root.parent_of(self.focus, Some(split)) else {
+            return;
+        };
+
+        parent.measure(&areas);
+        let Node::Split(_, children) = parent else {
+            return;
+        };
+
+        let neighbour = match position + 1 < children.len() {
+            true => position + 1,
+            false => position - 1,
+        };
+
+        let min = i32::from(Self::MIN_SIZE);
+        let (size, other) = (
+            i32::from(children[position].1),
+            i32::from(children[neighbour].1),
+        );
+        let delta = delta.clamp(min - size, other - min);
+
+        children[position].1 = (size + delta) as u16;
+        children[neighbour].1 = (other - delta) as u16;
+    }
+
+    /// Gives every window of a split the same room.
+    pub fn equalize(&mut self) {
+        self.root.equalize();
+    }
+
+    /// Window next to the focused one towards `direction`, the one beside
+    /// its top left corner when there are several.
+    pub fn neighbour(&self, direction: Direction) -> Option<ViewId> {
+        let areas = self.areas();
+        let focus = self.area_of(self.focus)?;
+
+        let beside = |rect: &Rect| match direction {
+            Direction::Left => rect.right() == focus.x,
+            Direction::Right => rect.x == focus.right(),
+            Direction::Up => rect.bottom() == focus.y,
+            Direction::Down => rect.y == focus.bottom(),
+        };
+        let overlap = |rect: &Rect| match direction {
+            Direction::Left | Direction::Right => {
+                rect.y.max(focus.y)..rect.bottom().min(focus.bottom())
+            }
+            Direction::Up | Direction::Down => rect.x.max(focus.x)..rect.right().min(focus.right()),
+        };
+        let corner = match direction {
+            Direction::Left | Direction::Right => focus.y,
+            Direction::Up | Direction::Down => focus.x,
+        };
+
+        let mut candidates: Vec<_> = areas
+            .iter()
+            .filter(|(_, rect)| beside(rect) && !overlap(rect).is_empty())
+            .collect();
+        candidates.sort_by_key(|(_, rect)| !overlap(rect).contains(&corner));
+
+        candidates.first().map(|(id, _)| *id)
+    }
+
+    /// Window after the focused one in layout order, wrapping around.
+    pub fn next(&self) -> ViewId {
+        let areas = self.areas();
+        let position = areas.iter().position(|(id, _)| *id == self.focus);
+        let next = position.map_or(0, |position| (position + 1) % areas.len());
+
+        areas[next].0
+    }
+
+    /// Moves the focus to window `id`, the caller moving the view states along.
+    pub fn set_focus(&mut self, id: ViewId) {
+        if self.views.contains_key(&id) {
+            self.focus = id;
+        }
+    }
+
+    fn node_mut(&mut self, id: ViewId) -> Option<&mut Node> {
+        fn find(node: &mut Node, id: ViewId) -> Option<&mut Node> {
+            match node {
+                Node::View(view) if *view == id => Some(node),
+                Node::View(_) => None,
+                Node::Split(_, children) => {
+                    children.iter_mut().find_map(|(child, _)| find(child, id))
+                }
+            }
+        }
+
+        find(&mut self.root, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn windows() -> Windows {
+        let mut windows = Windows::new(DocumentId::next());
+        windows.set_area(Rect::new(0, 0, 80, 24));
+        windows
+    }
+
+    fn rects(windows: &Windows) -> Vec<Rect> {
+        windows.areas().into_iter().map(|(_, rect)| rect).collect()
+    }
+
+    #[test]
+    fn test_split() {
+        let mut windows = windows();
+        let first = windows.focus();
+
+        let second = windows.split(Split::Horizontal).unwrap();
+        assert_eq!(second, windows.focus());
+        assert_eq!(
+            vec![Rect::new(0, 0, 80, 12), Rect::new(0, 12, 80, 12)],
+            rects(&windows)
+        );
+
+        let third = windows.split(Split::Vertical).unwrap();
+        assert_eq!(
+            vec![
+                Rect::new(0, 0, 80, 12),
+                Rect::new(0, 12, 40, 12),
+                Rect::new(40, 12, 40, 12)
+            ],
+            rects(&windows)
+        );
+
+        assert_eq!(Some(second), windows.neighbour(Direction::Left));
+        assert_eq!(Some(first), windows.neighbour(Direction::Up));
+        assert_eq!(None, windows.neighbour(Direction::Down));
+        assert_eq!(first, windows.next());
+
+        windows.set_focus(first);
+        assert_eq!(Some(second), windows.neighbour(Direction::Down));
+
+        windows.close(first).unwrap();
+        assert_eq!(second, windows.focus());
+        assert_eq!(
+            vec![Rect::new(0, 0, 40, 24), Rect::new(40, 0, 40, 24)],
+            rects(&windows)
+        );
+
+        windows.close(third).unwrap();
+        assert_eq!(vec![Rect::new(0, 0, 80, 24)], rects(&windows));
+        assert!(windows.close(second).is_err());
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut windows = windows();
+        windows.split(Split::Horizontal).unwrap();
+        windows.split(Split::Horizontal).unwrap();
+        assert_eq!(vec![12, 6, 6], heights(&windows));
+
+        windows.resize(Split::Horizontal, 2);
+        assert_eq!(vec![12, 4, 8], heights(&windows));
+
+        windows.resize(Split::Horizontal, -20);
+        assert_eq!(vec![12, 10, 2], heights(&windows));
+
+        // no vertical split to resize
+        windows.resize(Split::Vertical, 5);
+        assert_eq!(vec![12, 10, 2], heights(&windows));
+
+        windows.equalize();
+        assert_eq!(vec![8, 8, 8], heights(&windows));
+
+        windows.only();
+        assert_eq!(vec![24], heights(&windows));
+        assert_eq!(1, windows.len());
+    }
+
+    fn heights(windows: &Windows) -> Vec<u16> {
+        rects(windows).iter().map(|rect| rect.height).collect()
+    }
+}
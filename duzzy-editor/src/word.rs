@@ -0,0 +1,243 @@
+use std::ops::Range;
+
+use crate::{buffer::Buffer, options::WordChars};
+
+/// Which word boundary a word motion moves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordMotion {
+    NextStart,
+    PrevStart,
+    NextEnd,
+    PrevEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Blank,
+    Word,
+    Punct,
+    /// Identifier in quotes, a word of its own.
+    Quoted,
+}
+
+/// How text is split into words: `big` words are runs of non-blank chars,
+/// like vim `WORD`s.
+#[derive(Debug, Clone, Copy)]
+pub struct Words<'a> {
+    chars: &'a WordChars,
+    big: bool,
+}
+
+impl<'a> Words<'a> {
+    pub const fn new(chars: &'a WordChars, big: bool) -> Self {
+        Self { chars, big }
+    }
+
+    fn class(&self, ch: char) -> Class {
+        if ch.is_whitespace() {
+            Class::Blank
+        } else if self.big || ch.is_alphanumeric() || self.chars.extra.contains(ch) {
+            Class::Word
+        } else {
+            Class::Punct
+        }
+    }
+
+    fn is_quote(&self, ch: char) -> bool {
+        !self.big && self.chars.quotes.contains(ch)
+    }
+
+    /// Char ranges of the words in line `index`, an empty line being an empty word.
+    pub fn tokens(&self, buf: &Buffer, index: usize) -> Vec<Range<usize>> {
+        let start = buf.line_char(index);
+        let graphemes: Vec<_> = buf
+            .graphemes(index, (0, 0), 1)
+            .map(|g| {
+                (
+                    start + g.offset,
+                    start + g.offset + g.slice.len_chars(),
+                    g.slice.char(0),
+                )
+            })
+            .collect();
+
+        let mut tokens: Vec<Range<usize>> = vec![];
+
+        if buf.line_len(index) == 0 {
+            tokens.push(start..start);
+            return tokens;
+        }
+
+        let mut last = Class::Blank;
+        let mut i = 0;
+
+        while i < graphemes.len() {
+            let (from, to, ch) = graphemes[i];
+
+            let closing = self
+                .is_quote(ch)
+                .then(|| graphemes[i + 1..].iter().position(|g| g.2 == ch))
+                .flatten();
+
+            if let Some(skip) = closing {
+                let (_, to, _) = graphemes[i + 1 + skip];
+                tokens.push(from..to);
+                last = Class::Quoted;
+                i += skip + 2;
+                continue;
+            }
+
+            let class = self.class(ch);
+            match (class, tokens.last_mut()) {
+                (Class::Blank, _) => (),
+                (class, Some(token)) if class == last => token.end = to,
+                _ => tokens.push(from..to),
+            }
+
+            last = class;
+            i += 1;
+        }
+
+        tokens
+    }
+
+    /// Char index `count` words away from `pos` in the direction of `motion`.
+    pub fn find(&self, buf: &Buffer, pos: usize, motion: WordMotion, count: usize) -> usize {
+        let mut pos = pos;
+
+        for _ in 0..count {
+            let next = match motion {
+                WordMotion::NextStart => self.next_start(buf, pos),
+                WordMotion::PrevStart => self.prev_start(buf, pos),
+                WordMotion::NextEnd => self.next_end(buf, pos),
+                WordMotion::PrevEnd => self.prev_end(buf, pos),
+            };
+
+            if next == pos {
+                break;
+            }
+            pos = next;
+        }
+
+        pos
+    }
+
+    fn next_start(&self, buf: &Buffer, pos: usize) -> usize {
+        let line = buf.text().char_to_line(pos);
+
+        (line..buf.len_lines())
+            .flat_map(|index| self.tokens(buf, index))
+            .map(|token| token.start)
+            .find(|start| *start > pos)
+            .unwrap_or_else(|| buf.len_chars())
+    }
+
+    fn prev_start(&self, buf: &Buffer, pos: usize) -> usize {
+        let line = buf.text().char_to_line(pos);
+
+        (0..=line)
+            .rev()
+            .flat_map(|index| self.tokens(buf, index).into_iter().rev())
+            .map(|token| token.start)
+            .find(|start| *start < pos)
+            .unwrap_or(0)
+    }
+
+    /// Words ending past `pos`, skipping empty lines.
+    fn next_end(&self, buf: &Buffer, pos: usize) -> usize {
+        let line = buf.text().char_to_line(pos);
+
+        (line..buf.len_lines())
+            .flat_map(|index| self.tokens(buf, index))
+            .filter(|token| !token.is_empty())
+            .map(|token| buf.prev_grapheme(token.end))
+            .find(|end| *end > pos)
+            .unwrap_or(pos)
+    }
+
+    fn prev_end(&self, buf: &Buffer, pos: usize) -> usize {
+        let line = buf.text().char_to_line(pos);
+
+        (0..=line)
+            .rev()
+            .flat_map(|index| self.tokens(buf, index).into_iter().rev())
+            .map(|token| match token.is_empty() {
+                true => token.start,
+                false => buf.prev_grapheme(token.end),
+            })
+            .find(|end| *end < pos)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::buffer;
+
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let chars = WordChars::default();
+        let buf = buffer("select t.$id, \"order id\" from a_b\n\n  `x`");
+
+        let words: Vec<_> = Words::new(&chars, false)
+            .tokens(&buf, 0)
+            .into_iter()
+            .map(|token| buf.text().slice(token).to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "select",
+                "t",
+                ".",
+                "$id",
+                ",",
+                "\"order id\"",
+                "from",
+                "a_b"
+            ],
+            words
+        );
+
+        let words: Vec<_> = Words::new(&chars, true)
+            .tokens(&buf, 0)
+            .into_iter()
+            .map(|token| buf.text().slice(token).to_string())
+            .collect();
+        assert_eq!(
+            vec!["select", "t.$id,", "\"order", "id\"", "from", "a_b"],
+            words
+        );
+
+        assert_eq!(
+            Some(34..34),
+            Words::new(&chars, false).tokens(&buf, 1).pop()
+        );
+        assert_eq!(
+            Some(37..40),
+            Words::new(&chars, false).tokens(&buf, 2).pop()
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let chars = WordChars::default();
+        let words = Words::new(&chars, false);
+        let buf = buffer("select a.b\n\nfrom t");
+
+        assert_eq!(7, words.find(&buf, 0, WordMotion::NextStart, 1));
+        assert_eq!(11, words.find(&buf, 0, WordMotion::NextStart, 4));
+        assert_eq!(18, words.find(&buf, 17, WordMotion::NextStart, 1));
+        assert_eq!(5, words.find(&buf, 0, WordMotion::NextEnd, 1));
+        assert_eq!(15, words.find(&buf, 9, WordMotion::NextEnd, 1));
+        assert_eq!(11, words.find(&buf, 12, WordMotion::PrevStart, 1));
+        assert_eq!(0, words.find(&buf, 7, WordMotion::PrevStart, 3));
+        assert_eq!(11, words.find(&buf, 12, WordMotion::PrevEnd, 1));
+        assert_eq!(9, words.find(&buf, 11, WordMotion::PrevEnd, 1));
+
+        let big = Words::new(&chars, true);
+        assert_eq!(9, big.find(&buf, 7, WordMotion::NextEnd, 1));
+        assert_eq!(7, big.find(&buf, 9, WordMotion::PrevStart, 1));
+    }
+}
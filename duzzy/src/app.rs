@@ -1,7 +1,7 @@
 use std::{io::Write, time::Duration};
 
-use crossterm::{event::EventStream, execute, ExecutableCommand};
-use duzzy_editor::{editor::DuzzyEditor, renderer::EventOutcome};
+use crossterm::{event::EventStream, execute};
+use duzzy_editor::{clipboard, editor::DuzzyEditor, renderer::EventOutcome};
 use futures_util::StreamExt;
 use ratatui::{backend::Backend, Terminal};
 
@@ -16,6 +16,7 @@ impl<B: Backend + Write> App<B> {
         let size = terminal.size()?;
 
         let mut editor = DuzzyEditor::new(size.width as usize, size.height as usize);
+        editor.set_clipboard(clipboard::detect());
 
         let mut opened = 0;
         let mut failed = 0;
@@ -41,7 +42,8 @@ impl<B: Backend + Write> App<B> {
         crossterm::execute!(
             &mut terminal.backend_mut(),
             crossterm::terminal::EnterAlternateScreen,
-            crossterm::event::EnableMouseCapture
+            crossterm::event::EnableMouseCapture,
+            crossterm::event::EnableBracketedPaste
         )
         .expect("enable rules");
 
@@ -52,10 +54,13 @@ impl<B: Backend + Write> App<B> {
         let hook = std::panic::take_hook();
 
         std::panic::set_hook(Box::new(move |info| {
-            let mut stdout = std::io::stdout();
-            stdout
-                .execute(crossterm::terminal::LeaveAlternateScreen)
-                .ok();
+            execute!(
+                std::io::stdout(),
+                crossterm::terminal::LeaveAlternateScreen,
+                crossterm::event::DisableMouseCapture,
+                crossterm::event::DisableBracketedPaste
+            )
+            .ok();
             crossterm::terminal::disable_raw_mode().ok();
 
             hook(info);
@@ -83,6 +88,12 @@ impl<B: Backend + Write> App<B> {
 
             let outcome = self.editor.on_event(event);
 
+            if let Some(output) = self.editor.take_terminal_output() {
+                let backend = self.terminal.backend_mut();
+                backend.write_all(output.as_bytes())?;
+                Write::flush(backend)?;
+            }
+
             match outcome {
                 EventOutcome::Exit => break,
                 EventOutcome::Render => {
@@ -118,7 +129,8 @@ impl<B: Backend + Write> Drop for App<B> {
         crossterm::execute!(
             self.terminal.backend_mut(),
             crossterm::terminal::LeaveAlternateScreen,
-            crossterm::event::DisableMouseCapture
+            crossterm::event::DisableMouseCapture,
+            crossterm::event::DisableBracketedPaste
         )
         .expect("disable rules");
     }